@@ -0,0 +1,57 @@
+use std::hint::black_box;
+use std::str::FromStr;
+use criterion::{criterion_group, criterion_main, Criterion};
+use chess_core::engine::eval::piece_square_eval;
+use chess_core::types::board::board::Board;
+use chess_core::types::square::Square;
+
+const FEN: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+/// How `to_squares` used to walk a bitboard, kept here as the baseline.
+fn collect_squares(bits: u64) -> Vec<Square> {
+    let mut squares = Vec::with_capacity(bits.count_ones() as usize);
+    let mut bits = bits;
+    while bits != 0 {
+        let index = bits.trailing_zeros() as u8;
+        bits ^= 1 << index;
+        squares.push(Square::new(index));
+    }
+    squares
+}
+
+fn bench_square_iteration(c: &mut Criterion) {
+    let board = Board::from_str(FEN).unwrap();
+    let mut group = c.benchmark_group("square_iteration");
+    group.bench_function("vec", |b| {
+        b.iter(|| {
+            let mut sum = 0u32;
+            for bb in black_box(&board.pieces) {
+                for sq in collect_squares(bb.0).iter() {
+                    sum += sq.to_index() as u32;
+                }
+            }
+            sum
+        })
+    });
+    group.bench_function("iterator", |b| {
+        b.iter(|| {
+            let mut sum = 0u32;
+            for bb in black_box(&board.pieces) {
+                for sq in bb {
+                    sum += sq.to_index() as u32;
+                }
+            }
+            sum
+        })
+    });
+    group.finish();
+}
+
+fn bench_hot_paths(c: &mut Criterion) {
+    let board = Board::from_str(FEN).unwrap();
+    c.bench_function("compute_hash", |b| b.iter(|| black_box(&board).compute_hash()));
+    c.bench_function("piece_square_eval", |b| b.iter(|| piece_square_eval(black_box(&board))));
+}
+
+criterion_group!(benches, bench_square_iteration, bench_hot_paths);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+use std::hint::black_box;
+use std::str::FromStr;
+use criterion::{criterion_group, criterion_main, Criterion};
+use chess_core::engine::perft::perft;
+use chess_core::types::bitboard::Bitboard;
+use chess_core::types::board::board::Board;
+use chess_core::types::board::lookup::{lookup_rook, lookup_bishop, pext_supported, set_slider_backend, SliderBackend};
+use chess_core::types::square::Square;
+
+const FEN: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+fn backends() -> Vec<(&'static str, SliderBackend)> {
+    let mut backends = vec![("magic", SliderBackend::Magic)];
+    if pext_supported() {
+        backends.push(("pext", SliderBackend::Pext));
+    }
+    backends
+}
+
+fn bench_lookups(c: &mut Criterion) {
+    let occupied = Board::from_str(FEN).unwrap().occupied();
+    let mut group = c.benchmark_group("slider_lookups");
+    for (name, backend) in backends() {
+        set_slider_backend(backend);
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut attacks = Bitboard(0);
+                for index in 0..64u8 {
+                    let sq = Square::new(index);
+                    attacks = attacks ^ lookup_rook(&sq, black_box(&occupied)) ^ lookup_bishop(&sq, black_box(&occupied));
+                }
+                attacks
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_perft(c: &mut Criterion) {
+    let mut group = c.benchmark_group("slider_perft");
+    group.sample_size(10);
+    for (name, backend) in backends() {
+        set_slider_backend(backend);
+        let mut board = Board::from_str(FEN).unwrap();
+        group.bench_function(name, |b| b.iter(|| perft(&mut board, 3)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_lookups, bench_perft);
+criterion_main!(benches);
@@ -52,8 +52,185 @@ impl ZobristKeys{
 
 
 
+/// Steps in (rank, file) of the eight directions, in the order of `lookup::Direction`.
+const DIRECTIONS: [(i8, i8); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+
+/// Board geometry that never changes, written out as tables so nothing is computed at runtime.
+pub struct Geometry {
+    pub between: Vec<[u64; 64]>,
+    pub line: Vec<[u64; 64]>,
+    pub rays: [[u64; 64]; 8],
+    pub chebyshev: [[u8; 64]; 64],
+    pub manhattan: [[u8; 64]; 64],
+    pub adjacent_files: [u64; 8],
+    pub forward_span: [[u64; 64]; 2],
+    pub passed_pawn: [[u64; 64]; 2],
+}
+
+impl Geometry {
+    fn new() -> Self {
+        let file_mask = |file: i8| 0x0101_0101_0101_0101u64 << file;
+        let mut rays = [[0u64; 64]; 8];
+        for (ray, (dr, df)) in rays.iter_mut().zip(DIRECTIONS.iter()) {
+            for (sq, mask) in ray.iter_mut().enumerate() {
+                let (mut r, mut f) = ((sq / 8) as i8 + dr, (sq % 8) as i8 + df);
+                while (0..8).contains(&r) && (0..8).contains(&f) {
+                    *mask |= 1 << (r * 8 + f);
+                    r += dr;
+                    f += df;
+                }
+            }
+        }
+
+        let mut between = vec![[0u64; 64]; 64];
+        let mut line = vec![[0u64; 64]; 64];
+        let mut chebyshev = [[0u8; 64]; 64];
+        let mut manhattan = [[0u8; 64]; 64];
+        for a in 0..64 {
+            for b in 0..64 {
+                let (dr, df) = ((b / 8) as i8 - (a / 8) as i8, (b % 8) as i8 - (a % 8) as i8);
+                chebyshev[a][b] = dr.abs().max(df.abs()) as u8;
+                manhattan[a][b] = (dr.abs() + df.abs()) as u8;
+                for dir in 0..8 {
+                    if rays[dir][a] & (1 << b) != 0 {
+                        let opposite = (dir + 4) % 8;
+                        between[a][b] = rays[dir][a] & rays[opposite][b];
+                        line[a][b] = rays[dir][a] | rays[opposite][a] | (1 << a);
+                    }
+                }
+            }
+        }
+
+        let mut adjacent_files = [0u64; 8];
+        for file in 0..8i8 {
+            if file > 0 {
+                adjacent_files[file as usize] |= file_mask(file - 1);
+            }
+            if file < 7 {
+                adjacent_files[file as usize] |= file_mask(file + 1);
+            }
+        }
+
+        let mut forward_span = [[0u64; 64]; 2];
+        let mut passed_pawn = [[0u64; 64]; 2];
+        for sq in 0..64 {
+            let file = sq % 8;
+            // White moves north (ray 0), black south (ray 4)
+            for (color, dir) in [(0, 0), (1, 4)] {
+                forward_span[color][sq] = rays[dir][sq];
+                let mut mask = rays[dir][sq];
+                if file > 0 {
+                    mask |= rays[dir][sq - 1];
+                }
+                if file < 7 {
+                    mask |= rays[dir][sq + 1];
+                }
+                passed_pawn[color][sq] = mask;
+            }
+        }
+
+        Geometry { between, line, rays, chebyshev, manhattan, adjacent_files, forward_span, passed_pawn }
+    }
+}
+
+fn write_table<T: std::fmt::LowerHex, W: Write>(f: &mut W, name: &str, ty: &str, rows: &[[T; 64]]) {
+    writeln!(f, "pub static {}: [[{}; 64]; {}] = [", name, ty, rows.len()).unwrap();
+    for row in rows {
+        write!(f, "    [").unwrap();
+        for value in row.iter() {
+            write!(f, "0x{:x}, ", value).unwrap();
+        }
+        writeln!(f, "],").unwrap();
+    }
+    writeln!(f, "];").unwrap();
+}
+
+fn write_geometry(out_dir: &Path) {
+    let geometry = Geometry::new();
+    let mut f = File::create(out_dir.join("geometry.rs")).unwrap();
+    writeln!(f, "// This file is @generated by build.rs. Do not edit manually.").unwrap();
+
+    write_table(&mut f, "BETWEEN", "u64", &geometry.between);
+    write_table(&mut f, "LINE", "u64", &geometry.line);
+    write_table(&mut f, "RAYS", "u64", &geometry.rays);
+    write_table(&mut f, "FORWARD_SPAN", "u64", &geometry.forward_span);
+    write_table(&mut f, "PASSED_PAWN_MASK", "u64", &geometry.passed_pawn);
+    write!(f, "pub static ADJACENT_FILES: [u64; 8] = [").unwrap();
+    for mask in geometry.adjacent_files {
+        write!(f, "0x{:x}, ", mask).unwrap();
+    }
+    writeln!(f, "];").unwrap();
+    write_table(&mut f, "CHEBYSHEV_DISTANCE", "u8", &geometry.chebyshev);
+    write_table(&mut f, "MANHATTAN_DISTANCE", "u8", &geometry.manhattan);
+}
+
+
+
+// The checked-in magics, the attack tables are derived from them
+#[allow(dead_code)]
+mod magics {
+    include!("src/data/magics.rs");
+}
+
+/// Squares a slider on `sq` attacks with `blockers` on the board, going in the (rank, file) `steps`.
+fn slider_attacks(sq: usize, blockers: u64, steps: &[(i8, i8)]) -> u64 {
+    let mut attacks = 0u64;
+    for (dr, df) in steps {
+        let (mut r, mut f) = ((sq / 8) as i8 + dr, (sq % 8) as i8 + df);
+        while (0..8).contains(&r) && (0..8).contains(&f) {
+            attacks |= 1 << (r * 8 + f);
+            if blockers & (1 << (r * 8 + f)) != 0 {
+                break;
+            }
+            r += dr;
+            f += df;
+        }
+    }
+    attacks
+}
+
+/// One flat table with the attacks of every square at its magic index, like `generate_magic` builds it.
+fn attack_table(magics: &[magics::MagicEntry], steps: &[(i8, i8)]) -> Vec<u64> {
+    let len = magics.iter().map(|m| m.offset + m.size).max().unwrap_or(0);
+    let mut table = vec![0u64; len];
+    for (sq, entry) in magics.iter().enumerate() {
+        // Every subset of the mask, with the carry-rippler trick
+        let mut blockers = 0u64;
+        loop {
+            let index = entry.offset + (blockers.wrapping_mul(entry.magic) >> entry.shift) as usize;
+            let attacks = slider_attacks(sq, blockers, steps);
+            assert!(table[index] == 0 || table[index] == attacks, "magic of square {} collides, regenerate data/magics.rs", sq);
+            table[index] = attacks;
+            blockers = blockers.wrapping_sub(entry.mask) & entry.mask;
+            if blockers == 0 {
+                break;
+            }
+        }
+    }
+    table
+}
+
+fn write_attacks(out_dir: &Path) {
+    let mut f = std::io::BufWriter::new(File::create(out_dir.join("attacks.rs")).unwrap());
+    writeln!(f, "// This file is @generated by build.rs from data/magics.rs. Do not edit manually.").unwrap();
+    let bishops = attack_table(&magics::BISHOP_MAGICS, &[(1, 1), (1, -1), (-1, 1), (-1, -1)]);
+    let rooks = attack_table(&magics::ROOK_MAGICS, &[(1, 0), (-1, 0), (0, 1), (0, -1)]);
+    for (name, table) in [("BISHOP_ATTACKS", bishops), ("ROOK_ATTACKS", rooks)] {
+        writeln!(f, "pub static {}: [u64; {}] = [", name, table.len()).unwrap();
+        for attacks in table {
+            writeln!(f, "    0x{:x},", attacks).unwrap();
+        }
+        writeln!(f, "];").unwrap();
+    }
+}
+
+
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/data/magics.rs");
+
+    write_geometry(Path::new(&env::var_os("OUT_DIR").unwrap()));
+    write_attacks(Path::new(&env::var_os("OUT_DIR").unwrap()));
 
 
     let keys = ZobristKeys::new();
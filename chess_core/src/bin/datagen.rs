@@ -0,0 +1,44 @@
+use chess_core::training::datagen::{run, DatagenConfig, OutputFormat};
+use std::env;
+use std::fs::File;
+use std::io::BufWriter;
+use std::process;
+use std::time::Instant;
+
+const USAGE: &str = "usage: datagen --out FILE [--games N] [--threads N] [--seed N] [--nodes N] [--random-plies N] [--packed]";
+
+fn main() {
+    let mut config = DatagenConfig::default();
+    let mut out = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| fail(&format!("missing value for {}", arg)));
+        match arg.as_str() {
+            "--out" => out = Some(value()),
+            "--games" => config.games = value().parse().unwrap_or_else(|_| fail("invalid game count")),
+            "--threads" => config.threads = value().parse().unwrap_or_else(|_| fail("invalid thread count")),
+            "--seed" => config.seed = value().parse().unwrap_or_else(|_| fail("invalid seed")),
+            "--nodes" => config.nodes = value().parse().unwrap_or_else(|_| fail("invalid node count")),
+            "--random-plies" => config.random_plies = value().parse().unwrap_or_else(|_| fail("invalid ply count")),
+            "--packed" => config.format = OutputFormat::Packed,
+            _ => fail(&format!("unknown argument {}", arg)),
+        }
+    }
+    let out = out.unwrap_or_else(|| fail("no output file given"));
+    let file = File::create(&out).unwrap_or_else(|e| fail(&format!("could not create {}: {}", out, e)));
+
+    let start = Instant::now();
+    match run(&config, BufWriter::new(file)) {
+        Ok(positions) => println!(
+            "Wrote {} positions from {} games to {} in {:.1}s",
+            positions, config.games, out, start.elapsed().as_secs_f32()
+        ),
+        Err(e) => fail(&format!("could not write {}: {}", out, e)),
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(1);
+}
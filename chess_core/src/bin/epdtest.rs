@@ -0,0 +1,97 @@
+use chess_core::engine::testsuite::{run_position, SearchLimit};
+use chess_core::types::board::board::Board;
+use chess_core::types::board::transposition::TranspositionTable;
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::process;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "usage: epdtest FILE [--time MS] [--nodes N] [--depth N] [--hash MB] [--verbose]";
+
+fn main() {
+    let mut limit = SearchLimit::default();
+    let mut hash = 16;
+    let mut verbose = false;
+    let mut path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| fail(&format!("missing value for {}", arg)));
+        match arg.as_str() {
+            "--time" => limit.time = Some(Duration::from_millis(value().parse().unwrap_or_else(|_| fail("invalid time")))),
+            "--nodes" => limit.nodes = Some(value().parse().unwrap_or_else(|_| fail("invalid node count"))),
+            "--depth" => limit.depth = Some(value().parse().unwrap_or_else(|_| fail("invalid depth"))),
+            "--hash" => hash = value().parse().unwrap_or_else(|_| fail("invalid hash size")),
+            "--verbose" => verbose = true,
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => fail(&format!("unknown argument {}", arg)),
+        }
+    }
+    if limit.time.is_none() && limit.nodes.is_none() && limit.depth.is_none() {
+        limit.time = Some(Duration::from_secs(1));
+    }
+    let path = path.unwrap_or_else(|| fail("no EPD file given"));
+    let file = File::open(&path).unwrap_or_else(|e| fail(&format!("could not open {}: {}", path, e)));
+
+    let mut tt = TranspositionTable::new(hash);
+    let start = Instant::now();
+    let (mut solved, mut total, mut nodes) = (0, 0, 0);
+    let mut failed = Vec::new();
+
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.unwrap_or_else(|e| fail(&format!("could not read {}: {}", path, e)));
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (board, ops) = match Board::from_epd(&line) {
+            Ok(position) => position,
+            Err(e) => {
+                eprintln!("line {}: {}", number + 1, e);
+                continue;
+            }
+        };
+        let result = run_position(&board, &ops, &mut tt, limit);
+        total += 1;
+        nodes += result.nodes;
+
+        let id = ops.id.clone().unwrap_or_else(|| format!("line {}", number + 1));
+        let found = result.best_move.map_or("none".to_string(), |mv| mv.to_san(&board));
+        let expected = if ops.best_moves.is_empty() {
+            let avoid: Vec<String> = ops.avoid_moves.iter().map(|mv| mv.to_san(&board)).collect();
+            format!("am {}", avoid.join(" "))
+        } else {
+            let best: Vec<String> = ops.best_moves.iter().map(|mv| mv.to_san(&board)).collect();
+            format!("bm {}", best.join(" "))
+        };
+        if result.solved {
+            solved += 1;
+        } else {
+            failed.push(format!("{}: {} found {}, {}", id, expected, found, board.to_fen()));
+        }
+        if verbose {
+            let score = result.mate_in().map_or(format!("cp {}", result.score), |n| format!("mate {}", n));
+            println!(
+                "{:<20} {:<6} {:<16} found {:<8} depth {:<3} score {}",
+                id, if result.solved { "ok" } else { "FAIL" }, expected, found, result.depth, score
+            );
+        }
+    }
+
+    if !failed.is_empty() {
+        println!("\nFailed positions:");
+        for line in failed.iter() {
+            println!("  {}", line);
+        }
+    }
+    let elapsed = start.elapsed().as_secs_f32();
+    println!(
+        "\nSolved {}/{} ({:.1}%) in {:.1}s, {} nodes",
+        solved, total, 100.0 * solved as f32 / total.max(1) as f32, elapsed, nodes
+    );
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(1);
+}
@@ -0,0 +1,46 @@
+use chess_core::engine::syzygy::generator;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::time::Instant;
+
+// Needs the `syzygy-generator` feature: cargo run --features syzygy-generator --bin gen_syzygy
+const USAGE: &str = "usage: gen_syzygy --out DIR MATERIAL...   (materials like KRvK, up to 5 pieces)";
+
+fn main() {
+    let mut out = None;
+    let mut materials = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => out = Some(args.next().unwrap_or_else(|| fail("missing value for --out"))),
+            _ if !arg.starts_with("--") => materials.push(arg),
+            _ => fail(&format!("unknown argument {}", arg)),
+        }
+    }
+    let out = out.unwrap_or_else(|| fail("no output directory given"));
+    if materials.is_empty() {
+        fail("no materials given");
+    }
+
+    fs::create_dir_all(&out).unwrap_or_else(|e| fail(&format!("could not create {}: {}", out, e)));
+    for material in materials.iter() {
+        let start = Instant::now();
+        let (wdl, dtz) = generator::generate(material).unwrap_or_else(|e| fail(&e.to_string()));
+        for (suffix, bytes) in [("rtbw", &wdl), ("rtbz", &dtz)] {
+            let path = Path::new(&out).join(format!("{}.{}", material, suffix));
+            fs::write(&path, bytes).unwrap_or_else(|e| fail(&format!("could not write {}: {}", path.display(), e)));
+        }
+        println!(
+            "{}: {} bytes WDL, {} bytes DTZ in {:.1}s",
+            material, wdl.len(), dtz.len(), start.elapsed().as_secs_f32()
+        );
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(1);
+}
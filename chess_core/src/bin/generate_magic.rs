@@ -1,11 +1,42 @@
-use chess_core::magic::io::{precalc_magics_to_file};
+use chess_core::magic::io::write_magics_file;
+use chess_core::magic::magicgen::{generate_magics_attack_tables, MagicSearch};
+use std::env;
+use std::path::Path;
+use std::process;
+use std::time::Instant;
 
+const USAGE: &str = "usage: generate_magic --out DIR [--seed N] [--dense] [--attempts N]";
 
 fn main() {
-    println!("Starting magic number generation...");
-    precalc_magics_to_file().expect("Failed to calculate magics");
-    println!("Magic number generation finished.");
-    //println!("{:?}", res);
+    let mut search = MagicSearch::default();
+    let mut out = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| fail(&format!("missing value for {}", arg)));
+        match arg.as_str() {
+            "--out" => out = Some(value()),
+            "--seed" => search.seed = value().parse().unwrap_or_else(|_| fail("invalid seed")),
+            "--dense" => search.dense = true,
+            "--attempts" => search.attempts = value().parse().unwrap_or_else(|_| fail("invalid attempt count")),
+            _ => fail(&format!("unknown argument {}", arg)),
+        }
+    }
+    let out = out.unwrap_or_else(|| fail("no output directory given"));
+
+    let start = Instant::now();
+    let rooks = generate_magics_attack_tables(true, &search).unwrap_or_else(|e| fail(&e.to_string()));
+    let bishops = generate_magics_attack_tables(false, &search).unwrap_or_else(|e| fail(&e.to_string()));
+    write_magics_file(Path::new(&out), &rooks.0, &bishops.0, &search)
+        .unwrap_or_else(|e| fail(&format!("could not write to {}: {}", out, e)));
+
+    println!(
+        "Wrote verified magics to {} in {:.1}s: {} rook and {} bishop attack entries, build.rs derives the tables",
+        out, start.elapsed().as_secs_f32(), rooks.1.len(), bishops.1.len()
+    );
 }
 
-//
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(1);
+}
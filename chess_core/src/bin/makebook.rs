@@ -0,0 +1,77 @@
+use chess_core::engine::book_builder::{BookBuilder, BookBuilderConfig};
+use chess_core::types::board::parse_pgn::PgnReader;
+use std::env;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process;
+use std::time::Instant;
+
+const USAGE: &str = "usage: makebook --out FILE [--text] [--max-ply N] [--min-games N] [--min-elo N] [--min-score F] PGN...";
+
+fn main() {
+    let mut config = BookBuilderConfig::default();
+    let mut out = None;
+    let mut text = false;
+    let mut inputs = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| fail(&format!("missing value for {}", arg)));
+        match arg.as_str() {
+            "--out" => out = Some(value()),
+            "--text" => text = true,
+            "--max-ply" => config.max_ply = value().parse().unwrap_or_else(|_| fail("invalid ply count")),
+            "--min-games" => config.min_games = value().parse().unwrap_or_else(|_| fail("invalid game count")),
+            "--min-elo" => config.min_elo = value().parse().unwrap_or_else(|_| fail("invalid Elo")),
+            "--min-score" => config.min_score = value().parse().unwrap_or_else(|_| fail("invalid score")),
+            _ if !arg.starts_with("--") => inputs.push(arg),
+            _ => fail(&format!("unknown argument {}", arg)),
+        }
+    }
+    let out = out.unwrap_or_else(|| fail("no output file given"));
+    if inputs.is_empty() {
+        fail("no PGN files given");
+    }
+
+    let start = Instant::now();
+    let mut builder = BookBuilder::new(config);
+    let (mut read, mut errors) = (0u64, 0u64);
+    for input in inputs.iter() {
+        let reader = PgnReader::open(Path::new(input)).unwrap_or_else(|e| fail(&format!("could not open {}: {}", input, e)));
+        for game in reader {
+            read += 1;
+            let result = game.and_then(|game| builder.add_game(&game));
+            if let Err(e) = result {
+                errors += 1;
+                eprintln!("{} game {}: {}", input, read, e);
+            }
+            if read % 10000 == 0 {
+                eprintln!("{} games, {} positions", read, builder.positions());
+            }
+        }
+    }
+
+    let file = File::create(&out).unwrap_or_else(|e| fail(&format!("could not create {}: {}", out, e)));
+    let mut writer = BufWriter::new(file);
+    let written = if text {
+        builder.write_text(&mut writer).map(|_| builder.positions())
+    } else {
+        let book = builder.build();
+        book.write(&mut writer).map(|_| book.len())
+    };
+    let written = written
+        .and_then(|written| writer.flush().map(|_| written))
+        .unwrap_or_else(|e| fail(&format!("could not write {}: {}", out, e)));
+
+    println!(
+        "Read {} games ({} used, {} invalid), wrote {} {} to {} in {:.1}s",
+        read, builder.games(), errors, written, if text { "positions" } else { "entries" }, out,
+        start.elapsed().as_secs_f32()
+    );
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(1);
+}
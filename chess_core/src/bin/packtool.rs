@@ -0,0 +1,62 @@
+use chess_core::training::packed::{dedup, packed_to_text, shuffle, text_to_packed, PackedBoard, PackedReader, PackedWriter};
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::process;
+
+const USAGE: &str = "usage:
+  packtool to-packed <text file> <packed file>
+  packtool to-text <packed file> <text file>
+  packtool shuffle <packed file> <packed file> [--seed N] [--dedup]";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 3 {
+        fail("missing arguments");
+    }
+    let input = open(&args[1]);
+    let output = BufWriter::new(File::create(&args[2]).unwrap_or_else(|e| fail(&format!("could not create {}: {}", args[2], e))));
+
+    let written = match args[0].as_str() {
+        "to-packed" => text_to_packed(input, output).unwrap_or_else(|e| fail(&e.to_string())),
+        "to-text" => packed_to_text(input, output).unwrap_or_else(|e| fail(&e.to_string())),
+        "shuffle" => shuffle_file(input, output, &args[3..]).unwrap_or_else(|e| fail(&e.to_string())),
+        command => fail(&format!("unknown command {}", command)),
+    };
+    println!("Wrote {} positions to {}", written, args[2]);
+}
+
+fn shuffle_file(input: BufReader<File>, output: BufWriter<File>, options: &[String]) -> io::Result<u64> {
+    let mut seed = 0u64;
+    let mut remove_duplicates = false;
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--seed" => seed = options.next().and_then(|s| s.parse().ok()).unwrap_or_else(|| fail("invalid seed")),
+            "--dedup" => remove_duplicates = true,
+            _ => fail(&format!("unknown option {}", option)),
+        }
+    }
+
+    let mut positions: Vec<PackedBoard> = PackedReader::new(input).collect::<io::Result<_>>()?;
+    if remove_duplicates {
+        dedup(&mut positions);
+    }
+    shuffle(&mut positions, seed);
+
+    let mut writer = PackedWriter::new(output);
+    for position in positions.iter() {
+        writer.write(position)?;
+    }
+    writer.flush()?;
+    Ok(writer.written())
+}
+
+fn open(path: &str) -> BufReader<File> {
+    BufReader::new(File::open(path).unwrap_or_else(|e| fail(&format!("could not open {}: {}", path, e))))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(1);
+}
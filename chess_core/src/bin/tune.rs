@@ -0,0 +1,76 @@
+use chess_core::engine::tuner::{load_entries, Params, Tuner};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::time::Instant;
+
+const USAGE: &str = "usage: tune <positions>... [--epochs N] [--lr X] [--k K] [--threads N] [--out piece_square_tables.rs]";
+
+fn main() {
+    let mut files = Vec::new();
+    let mut epochs = 1000usize;
+    let mut learning_rate = 1.0f64;
+    let mut k = None;
+    let mut threads = None;
+    let mut out = String::from("piece_square_tables.rs");
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| fail(&format!("missing value for {}", arg)));
+        match arg.as_str() {
+            "--epochs" => epochs = value().parse().unwrap_or_else(|_| fail("invalid epoch count")),
+            "--lr" => learning_rate = value().parse().unwrap_or_else(|_| fail("invalid learning rate")),
+            "--k" => k = Some(value().parse().unwrap_or_else(|_| fail("invalid K"))),
+            "--threads" => threads = Some(value().parse().unwrap_or_else(|_| fail("invalid thread count"))),
+            "--out" => out = value(),
+            _ => files.push(arg),
+        }
+    }
+    if files.is_empty() {
+        fail("no position files given");
+    }
+
+    let start = Instant::now();
+    let mut entries = Vec::new();
+    for file in files.iter() {
+        match load_entries(Path::new(file)) {
+            Ok(loaded) => entries.extend(loaded),
+            Err(e) => fail(&format!("could not read {}: {}", file, e)),
+        }
+    }
+    println!("Loaded {} positions in {:.1}s", entries.len(), start.elapsed().as_secs_f32());
+
+    let mut tuner = Tuner::new(entries, Params::current());
+    tuner.learning_rate = learning_rate;
+    if let Some(threads) = threads {
+        tuner.threads = threads;
+    }
+    match k {
+        Some(k) => tuner.k = k,
+        None => println!("Optimal K: {:.4}", tuner.optimize_k()),
+    }
+    println!("Initial error: {:.6}", tuner.error(tuner.k));
+
+    for epoch in 1..=epochs {
+        let error = tuner.epoch();
+        if epoch % 50 == 0 || epoch == epochs {
+            println!("Epoch {:>5}: error {:.6}", epoch, error);
+            write_tables(&tuner.params, &out);
+        }
+    }
+
+    println!("Scalar terms for eval.rs:\n{}", tuner.params.to_eval_source());
+    println!("Piece-square tables written to {}", out);
+}
+
+fn write_tables(params: &Params, out: &str) {
+    if let Err(e) = fs::write(out, params.to_pst_source()) {
+        fail(&format!("could not write {}: {}", out, e));
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(1);
+}
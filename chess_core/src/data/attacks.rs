@@ -0,0 +1,2 @@
+// Attack tables of the magics in magics.rs, build.rs derives them on every build.
+include!(concat!(env!("OUT_DIR"), "/attacks.rs"));
@@ -1,2 +1,3 @@
 pub mod attacks;
-pub mod magics;
\ No newline at end of file
+pub mod magics;
+pub mod polyglot;
\ No newline at end of file
@@ -0,0 +1,337 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use rand::Rng;
+use crate::data::polyglot::{POLYGLOT_CASTLE_OFFSET, POLYGLOT_EN_PASSANT_OFFSET, POLYGLOT_PIECE_OFFSET, POLYGLOT_RANDOM, POLYGLOT_TURN_OFFSET};
+use crate::types::board::board::Board;
+use crate::types::color::Color;
+use crate::types::moves::{Move, MoveType};
+use crate::types::piece::{Piece, PieceType};
+use crate::types::square::Square;
+
+pub const ENTRY_BYTES: usize = 16;
+
+
+#[derive(Debug)]
+pub enum BookError {
+    Io(io::Error),
+    /// The file size is not a multiple of the entry size.
+    InvalidLength(usize),
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookError::Io(e) => write!(f, "{}", e),
+            BookError::InvalidLength(len) => write!(f, "book size {} is not a multiple of {} bytes", len, ENTRY_BYTES),
+        }
+    }
+}
+
+impl std::error::Error for BookError {}
+
+impl From<io::Error> for BookError {
+    fn from(e: io::Error) -> Self {
+        BookError::Io(e)
+    }
+}
+
+impl Board {
+    /// Position key of the Polyglot book format. Unlike our own hash the en passant
+    /// file only counts if a pawn of the side to move can actually capture.
+    pub fn polyglot_key(&self) -> u64 {
+        let mut key = 0;
+        for (index, bitboard) in self.pieces.iter().enumerate() {
+            // Polyglot orders kinds as black pawn, white pawn, black knight, ...
+            let kind = 2 * (index % 6) + if index < 6 { 1 } else { 0 };
+            for square in bitboard {
+                key ^= POLYGLOT_RANDOM[POLYGLOT_PIECE_OFFSET + 64 * kind + square as usize];
+            }
+        }
+
+        let castling = self.state.castling;
+        for (i, right) in [castling.white_king(), castling.white_queen(), castling.black_king(), castling.black_queen()].into_iter().enumerate() {
+            if right {
+                key ^= POLYGLOT_RANDOM[POLYGLOT_CASTLE_OFFSET + i];
+            }
+        }
+
+        if self.state.en_passant != Square::None {
+            let target = self.state.en_passant as u8;
+            let file = target % 8;
+            let (pawns, rank) = match self.state.color {
+                Color::White => (self.pieces[Piece::WhitePawn as usize], 4),
+                _ => (self.pieces[Piece::BlackPawn as usize], 3),
+            };
+            let capturer = |f: i8| (0..8).contains(&f) && pawns.is_set(rank * 8 + f as u8);
+            if capturer(file as i8 - 1) || capturer(file as i8 + 1) {
+                key ^= POLYGLOT_RANDOM[POLYGLOT_EN_PASSANT_OFFSET + file as usize];
+            }
+        }
+
+        if self.state.color == Color::White {
+            key ^= POLYGLOT_RANDOM[POLYGLOT_TURN_OFFSET];
+        }
+        key
+    }
+}
+
+/// Encodes `mv` as a Polyglot move: to square in bits 0-5, from square in bits 6-11
+/// and the promotion piece in bits 12-14. Castling is written as the king taking its rook.
+pub fn encode_move(mv: &Move) -> u16 {
+    let from = mv.from() as u16;
+    let to = match mv.kind() {
+        MoveType::KingCastle => mv.to() as u16 + 1,
+        MoveType::QueenCastle => mv.to() as u16 - 2,
+        _ => mv.to() as u16,
+    };
+    let promotion = if mv.is_promotion() {
+        match mv.promotion_type() {
+            PieceType::Knight => 1,
+            PieceType::Bishop => 2,
+            PieceType::Rook => 3,
+            _ => 4,
+        }
+    } else {
+        0
+    };
+    to | (from << 6) | (promotion << 12)
+}
+
+/// Finds the legal move of `board` a Polyglot move stands for.
+pub fn decode_move(board: &Board, encoded: u16) -> Option<Move> {
+    let mut board = board.clone();
+    let moves = board.generate_all_moves();
+    moves.iter().find(|mv| encode_move(mv) == encoded).copied()
+}
+
+/// One 16 byte record of a Polyglot book, stored big endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PolyglotEntry {
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl PolyglotEntry {
+    pub fn from_bytes(bytes: &[u8; ENTRY_BYTES]) -> Self {
+        PolyglotEntry {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            mv: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; ENTRY_BYTES] {
+        let mut bytes = [0u8; ENTRY_BYTES];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.mv.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+}
+
+/// How a move is chosen when a position has several book moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookSelection {
+    /// The move with the highest weight.
+    Best,
+    /// A random move, with probability proportional to its weight.
+    Weighted,
+}
+
+/// A Polyglot `.bin` book held in memory, entries sorted by key.
+#[derive(Debug, Clone, Default)]
+pub struct PolyglotBook {
+    entries: Vec<PolyglotEntry>,
+}
+
+impl PolyglotBook {
+    /// Sorts the entries by key, moves of a position by descending weight.
+    pub fn from_entries(mut entries: Vec<PolyglotEntry>) -> Self {
+        entries.sort_by(|a, b| a.key.cmp(&b.key).then(b.weight.cmp(&a.weight)));
+        PolyglotBook { entries }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BookError> {
+        if !bytes.len().is_multiple_of(ENTRY_BYTES) {
+            return Err(BookError::InvalidLength(bytes.len()));
+        }
+        let entries: Vec<PolyglotEntry> = bytes
+            .chunks_exact(ENTRY_BYTES)
+            .map(|chunk| PolyglotEntry::from_bytes(chunk.try_into().unwrap()))
+            .collect();
+        // Books are sorted by specification, but binary search silently fails if one is not
+        if entries.windows(2).all(|w| w[0].key <= w[1].key) {
+            Ok(PolyglotBook { entries })
+        } else {
+            Ok(PolyglotBook::from_entries(entries))
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, BookError> {
+        PolyglotBook::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for entry in self.entries.iter() {
+            out.write_all(&entry.to_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[PolyglotEntry] {
+        &self.entries
+    }
+
+    /// All entries stored for `key`.
+    pub fn entries_for(&self, key: u64) -> &[PolyglotEntry] {
+        let start = self.entries.partition_point(|e| e.key < key);
+        let end = start + self.entries[start..].partition_point(|e| e.key == key);
+        &self.entries[start..end]
+    }
+
+    /// Legal book moves of `board` with their weights. Entries that do not decode
+    /// to a legal move, e.g. because of a key collision, are skipped.
+    pub fn probe(&self, board: &Board) -> Vec<(Move, u16)> {
+        self.entries_for(board.polyglot_key())
+            .iter()
+            .filter_map(|entry| decode_move(board, entry.mv).map(|mv| (mv, entry.weight)))
+            .collect()
+    }
+
+    /// Chooses a book move for `board`, `None` if the position is not in the book.
+    pub fn pick<R: Rng>(&self, board: &Board, selection: BookSelection, rng: &mut R) -> Option<Move> {
+        let moves = self.probe(board);
+        match selection {
+            BookSelection::Best => moves.iter().max_by_key(|(_, weight)| *weight).map(|(mv, _)| *mv),
+            BookSelection::Weighted => {
+                let total: u32 = moves.iter().map(|(_, weight)| *weight as u32).sum();
+                if total == 0 {
+                    return moves.first().map(|(mv, _)| *mv);
+                }
+                let mut choice = rng.random_range(0..total);
+                for (mv, weight) in moves.iter() {
+                    if choice < *weight as u32 {
+                        return Some(*mv);
+                    }
+                    choice -= *weight as u32;
+                }
+                None
+            }
+        }
+    }
+}
+
+
+
+
+/// ######################################################
+/// ################### TESTING ##########################
+/// ######################################################
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use std::str::FromStr;
+
+    fn play(moves: &[&str]) -> Board {
+        let mut board = Board::start();
+        for lan in moves {
+            let mv = Move::from_lan(&board, lan);
+            board.apply_move(&mv);
+        }
+        board
+    }
+
+    #[test]
+    fn test_polyglot_keys() {
+        // Reference keys from the Polyglot book format description
+        for (moves, key) in [
+            (vec![], 0x463b96181691fc9c),
+            (vec!["e2e4"], 0x823c9b50fd114196),
+            (vec!["e2e4", "d7d5"], 0x0756b94461c50fb0),
+            (vec!["e2e4", "d7d5", "e4e5"], 0x662fafb965db29d4),
+            (vec!["e2e4", "d7d5", "e4e5", "f7f5"], 0x22a48b5a8e47ff78),
+            (vec!["e2e4", "d7d5", "e4e5", "f7f5", "e1e2"], 0x652a607ca3f242c1),
+            (vec!["e2e4", "d7d5", "e4e5", "f7f5", "e1e2", "e8f7"], 0x00fdd303c946bdd9),
+            (vec!["a2a4", "b7b5", "h2h4", "b5b4", "c2c4"], 0x3c8123ea7b067637),
+            (vec!["a2a4", "b7b5", "h2h4", "b5b4", "c2c4", "b4c3", "a1a3"], 0x5c3f9b829b279560),
+        ] {
+            assert_eq!(play(&moves).polyglot_key(), key, "{:?}", moves);
+        }
+        let fen = Board::from_str("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3").unwrap();
+        assert_eq!(fen.polyglot_key(), 0x22a48b5a8e47ff78);
+    }
+
+    #[test]
+    fn test_move_encoding() {
+        let board = Board::from_str("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let mut moves_board = board.clone();
+        for mv in moves_board.generate_all_moves().iter() {
+            assert_eq!(decode_move(&board, encode_move(mv)), Some(*mv));
+        }
+        // e1h1 and e1a1 stand for castling
+        assert_eq!(decode_move(&board, 7 | (4 << 6)).map(|mv| mv.kind()), Some(MoveType::KingCastle));
+        assert_eq!(decode_move(&board, 4 << 6).map(|mv| mv.kind()), Some(MoveType::QueenCastle));
+        // b7a8=N
+        assert_eq!(decode_move(&board, 56 | (49 << 6) | (1 << 12)).map(|mv| mv.promotion_type()), Some(PieceType::Knight));
+        assert_eq!(decode_move(&board, 1 | (2 << 6)), None);
+    }
+
+    #[test]
+    fn test_book_probe_and_pick() {
+        let start = Board::start();
+        let after_e4 = play(&["e2e4"]);
+        let entry = |board: &Board, lan: &str, weight| PolyglotEntry {
+            key: board.polyglot_key(),
+            mv: encode_move(&Move::from_lan(board, lan)),
+            weight,
+            learn: 0,
+        };
+        let book = PolyglotBook::from_entries(vec![
+            entry(&after_e4, "e7e5", 10),
+            entry(&start, "d2d4", 5),
+            entry(&start, "e2e4", 20),
+            entry(&after_e4, "c7c5", 30),
+            entry(&start, "g1f3", 0),
+        ]);
+
+        let mut bytes = Vec::new();
+        book.write(&mut bytes).unwrap();
+        let book = PolyglotBook::from_bytes(&bytes).unwrap();
+        assert_eq!(book.len(), 5);
+        assert!(matches!(PolyglotBook::from_bytes(&bytes[..17]), Err(BookError::InvalidLength(17))));
+
+        let probed: Vec<String> = book.probe(&start).iter().map(|(mv, _)| mv.to_lan()).collect();
+        assert_eq!(probed, vec!["e2e4", "d2d4", "g1f3"]);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(book.pick(&after_e4, BookSelection::Best, &mut rng).map(|mv| mv.to_lan()), Some("c7c5".to_string()));
+        let mut counts = [0; 2];
+        for _ in 0..1000 {
+            match book.pick(&start, BookSelection::Weighted, &mut rng).unwrap().to_lan().as_str() {
+                "e2e4" => counts[0] += 1,
+                "d2d4" => counts[1] += 1,
+                other => panic!("zero weight move {} picked", other),
+            }
+        }
+        assert!(counts[0] > 2 * counts[1]);
+        assert_eq!(book.pick(&play(&["a2a3"]), BookSelection::Best, &mut rng), None);
+    }
+}
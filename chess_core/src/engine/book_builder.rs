@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::str::FromStr;
+use super::book::{encode_move, PolyglotBook, PolyglotEntry};
+use crate::types::board::board::Board;
+use crate::types::board::internalstate::GameState;
+use crate::types::board::parse_pgn::{PgnError, PgnGame};
+use crate::types::color::Color;
+use crate::types::moves::Move;
+
+#[derive(Debug, Clone)]
+pub struct BookBuilderConfig {
+    /// Only moves played before this ply are counted.
+    pub max_ply: usize,
+    /// Moves played in fewer games are left out.
+    pub min_games: u32,
+    /// Games are skipped unless both `WhiteElo` and `BlackElo` reach this.
+    pub min_elo: u32,
+    /// Moves scoring less for the side that played them are left out, from 0.0 to 1.0.
+    pub min_score: f32,
+}
+
+impl Default for BookBuilderConfig {
+    fn default() -> Self {
+        BookBuilderConfig {
+            max_ply: 20,
+            min_games: 1,
+            min_elo: 0,
+            min_score: 0.0,
+        }
+    }
+}
+
+/// Results of a move from the point of view of the side that played it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MoveStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MoveStats {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn score(&self) -> f32 {
+        (self.wins as f32 + 0.5 * self.draws as f32) / self.games().max(1) as f32
+    }
+
+    /// Polyglot convention, two points for a win and one for a draw.
+    pub fn points(&self) -> u32 {
+        2 * self.wins + self.draws
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PositionStats {
+    fen: String,
+    moves: Vec<(Move, MoveStats)>,
+}
+
+/// Collects move statistics per position from PGN games.
+#[derive(Debug, Clone, Default)]
+pub struct BookBuilder {
+    config: BookBuilderConfig,
+    positions: HashMap<u64, PositionStats>,
+    games: u64,
+}
+
+impl BookBuilder {
+    pub fn new(config: BookBuilderConfig) -> Self {
+        BookBuilder {
+            config,
+            positions: HashMap::new(),
+            games: 0,
+        }
+    }
+
+    /// Number of games that passed the filters.
+    pub fn games(&self) -> u64 {
+        self.games
+    }
+
+    pub fn positions(&self) -> usize {
+        self.positions.len()
+    }
+
+    fn elo(game: &PgnGame, tag: &str) -> u32 {
+        game.tags.get(tag).and_then(|elo| elo.parse().ok()).unwrap_or(0)
+    }
+
+    /// Adds the first `max_ply` moves of `game`. Returns `false` if the game was skipped
+    /// because it has no result or a player is rated below `min_elo`. A game with an
+    /// invalid move among them is rejected as a whole.
+    pub fn add_game(&mut self, game: &PgnGame) -> Result<bool, PgnError> {
+        if game.result == GameState::InProgress
+            || Self::elo(game, "WhiteElo") < self.config.min_elo
+            || Self::elo(game, "BlackElo") < self.config.min_elo
+        {
+            return Ok(false);
+        }
+
+        let start = match game.starting_fen() {
+            Some(fen) => Board::from_str(fen).map_err(|_| PgnError::InvalidFen(fen.to_string()))?,
+            None => Board::start(),
+        };
+        // Parse the whole prefix first so a bad move leaves no partial statistics behind
+        let mut board = start.clone();
+        let mut moves = Vec::with_capacity(self.config.max_ply);
+        for (ply, san) in game.parsed_moves.iter().take(self.config.max_ply).enumerate() {
+            let mv = Move::parse_san(&board, san).map_err(|error| PgnError::InvalidMove { ply, error })?;
+            board.apply_move(&mv);
+            moves.push(mv);
+        }
+
+        let mut board = start;
+        for mv in moves {
+            let won = match board.state.color {
+                Color::White => GameState::WhiteWin,
+                _ => GameState::BlackWin,
+            };
+
+            let position = self.positions.entry(board.polyglot_key()).or_insert_with(|| PositionStats {
+                fen: board.to_fen(),
+                moves: Vec::new(),
+            });
+            let index = match position.moves.iter().position(|(m, _)| *m == mv) {
+                Some(index) => index,
+                None => {
+                    position.moves.push((mv, MoveStats::default()));
+                    position.moves.len() - 1
+                }
+            };
+            let stats = &mut position.moves[index].1;
+            match game.result {
+                GameState::Draw => stats.draws += 1,
+                result if result == won => stats.wins += 1,
+                _ => stats.losses += 1,
+            }
+            board.apply_move(&mv);
+        }
+        self.games += 1;
+        Ok(true)
+    }
+
+    fn keep(&self, stats: &MoveStats) -> bool {
+        stats.games() >= self.config.min_games && stats.score() >= self.config.min_score && stats.points() > 0
+    }
+
+    /// Polyglot book with `2 * wins + draws` as weights, scaled down per position if needed.
+    pub fn build(&self) -> PolyglotBook {
+        let mut entries = Vec::new();
+        for (key, position) in self.positions.iter() {
+            let kept: Vec<&(Move, MoveStats)> = position.moves.iter().filter(|(_, stats)| self.keep(stats)).collect();
+            let max_points = kept.iter().map(|(_, stats)| stats.points()).max().unwrap_or(0);
+            let scale = (max_points as f64 / u16::MAX as f64).max(1.0);
+            for (mv, stats) in kept {
+                entries.push(PolyglotEntry {
+                    key: *key,
+                    mv: encode_move(mv),
+                    weight: ((stats.points() as f64 / scale) as u16).max(1),
+                    learn: 0,
+                });
+            }
+        }
+        PolyglotBook::from_entries(entries)
+    }
+
+    /// Our own text format, one move per line sorted by position and games:
+    /// `FEN | move | games | wins | draws | losses`, the move in SAN.
+    pub fn write_text<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut positions: Vec<&PositionStats> = self.positions.values().collect();
+        positions.sort_by(|a, b| a.fen.cmp(&b.fen));
+        for position in positions {
+            let board = Board::from_str(&position.fen).expect("Stored FENs are valid");
+            let mut moves: Vec<&(Move, MoveStats)> = position.moves.iter().filter(|(_, stats)| self.keep(stats)).collect();
+            moves.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.games()));
+            for (mv, stats) in moves {
+                writeln!(
+                    out,
+                    "{} | {} | {} | {} | {} | {}",
+                    position.fen, mv.to_san(&board), stats.games(), stats.wins, stats.draws, stats.losses
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+
+
+/// ######################################################
+/// ################### TESTING ##########################
+/// ######################################################
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::book::BookSelection;
+    use crate::types::board::parse_pgn::PgnReader;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    const GAMES: &str = r#"[WhiteElo "2400"]
+[BlackElo "2300"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 Nc6 1-0
+
+[WhiteElo "2500"]
+[BlackElo "2450"]
+[Result "1/2-1/2"]
+
+1. e4 c5 2. Nf3 d6 1/2-1/2
+
+[WhiteElo "2500"]
+[BlackElo "2450"]
+[Result "0-1"]
+
+1. d4 d5 2. c4 e6 0-1
+
+[WhiteElo "1500"]
+[BlackElo "2450"]
+[Result "1-0"]
+
+1. b4 e5 1-0
+
+[Result "*"]
+
+1. g4 *
+"#;
+
+    fn builder(config: BookBuilderConfig) -> BookBuilder {
+        let mut builder = BookBuilder::new(config);
+        for game in PgnReader::new(GAMES.as_bytes()) {
+            builder.add_game(&game.unwrap()).unwrap();
+        }
+        builder
+    }
+
+    fn lan_moves(book: &PolyglotBook, board: &Board) -> Vec<(String, u16)> {
+        book.probe(board).iter().map(|(mv, weight)| (mv.to_lan(), *weight)).collect()
+    }
+
+    #[test]
+    fn test_statistics_and_filters() {
+        let all = builder(BookBuilderConfig::default());
+        assert_eq!(all.games(), 4);
+        let start = Board::start();
+        // e4 won once and drew once: 3 points, d4 lost, b4 won
+        assert_eq!(lan_moves(&all.build(), &start), vec![("e2e4".to_string(), 3), ("b2b4".to_string(), 2)]);
+
+        let rated = builder(BookBuilderConfig { min_elo: 2000, min_games: 2, max_ply: 2, ..BookBuilderConfig::default() });
+        assert_eq!(rated.games(), 3);
+        assert_eq!(lan_moves(&rated.build(), &start), vec![("e2e4".to_string(), 3)]);
+        let mut after_e4 = start.clone();
+        after_e4.apply_move(&Move::from_lan(&start, "e2e4"));
+        assert!(rated.build().probe(&after_e4).is_empty());
+
+        let winning = builder(BookBuilderConfig { min_score: 0.8, ..BookBuilderConfig::default() });
+        let moves: Vec<String> = winning.build().probe(&start).iter().map(|(mv, _)| mv.to_lan()).collect();
+        assert_eq!(moves, vec!["b2b4"]);
+        // Black won with d5 after d4
+        let mut after_d4 = start.clone();
+        after_d4.apply_move(&Move::from_lan(&start, "d2d4"));
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(winning.build().pick(&after_d4, BookSelection::Best, &mut rng).map(|mv| mv.to_lan()), Some("d7d5".to_string()));
+    }
+
+    #[test]
+    fn test_text_output() {
+        let builder = builder(BookBuilderConfig { max_ply: 1, ..BookBuilderConfig::default() });
+        let mut out = Vec::new();
+        builder.write_text(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 | e4 | 2 | 1 | 1 | 0\n\
+            rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 | b4 | 1 | 1 | 0 | 0\n");
+    }
+
+    #[test]
+    fn test_invalid_game_adds_nothing() {
+        let mut builder = BookBuilder::new(BookBuilderConfig::default());
+        let game = PgnGame::parse("[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 Ke5 1-0").unwrap();
+        assert!(matches!(builder.add_game(&game), Err(PgnError::InvalidMove { ply: 3, .. })));
+        assert_eq!(builder.games(), 0);
+        assert_eq!(builder.positions(), 0);
+    }
+}
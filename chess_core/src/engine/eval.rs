@@ -1,119 +1,219 @@
 use crate::types::bitboard::Bitboard;
 use crate::types::color::Color;
 use crate::types::piece::Piece;
+use crate::types::moves::Move;
 use crate::types::board::board::Board;
+use crate::types::board::lookup::{adjacent_files, lookup_bishop, lookup_knight, lookup_queen, lookup_rook};
 use crate::engine::piece_square_tables::{PSTS, flip_square};
+use std::fmt;
 
-use std::sync::LazyLock;
-
-static FILES: LazyLock<[Bitboard; 8]> = LazyLock::new(|| [
-    Bitboard::file(0),
-    Bitboard::file(1),
-    Bitboard::file(2),
-    Bitboard::file(3),
-    Bitboard::file(4),
-    Bitboard::file(5),
-    Bitboard::file(6),
-    Bitboard::file(7),
-]);
-static RANKS: LazyLock<[Bitboard; 8]> = LazyLock::new(|| [
-    Bitboard::rank(0),
-    Bitboard::rank(1),
-    Bitboard::rank(2),
-    Bitboard::rank(3),
-    Bitboard::rank(4),
-    Bitboard::rank(5),
-    Bitboard::rank(6),
-    Bitboard::rank(7),
-]);
+// Piece values indexed by pawn, knight, bishop, rook, queen
+pub(crate) const PIECE_VALUES: [i32; 5] = [100, 300, 325, 500, 900];
 
+pub(crate) const TEMPO: i32 = 20;
+
+pub(crate) const DOUBLED_PAWN: i32 = -15;
+pub(crate) const ISOLATED_PAWN: i32 = -15;
+pub(crate) const PAWN_SHIELD: i32 = 10;
+
+// Mobility bonus per reachable square, indexed by knight, bishop, rook, queen
+pub(crate) const MOBILITY: [i32; 4] = [4, 4, 2, 1];
+
+// Game phase contribution per piece, a full board adds up to MAX_PHASE
+const PHASE_KNIGHT: i32 = 1;
+const PHASE_BISHOP: i32 = 1;
+const PHASE_ROOK: i32 = 2;
+const PHASE_QUEEN: i32 = 4;
+pub const MAX_PHASE: i32 = 24;
+
+
+/// A static evaluation function the search can be generic over.
+///
+/// Implementations return a score in centipawns relative to the side to move:
+/// positive means the side to move is better. The search never flips the sign
+/// of a static evaluation itself.
+pub trait Evaluate {
+    fn evaluate(&self, board: &Board) -> i32;
+
+    /// Rebuilds any incrementally updated state from scratch for `board`.
+    /// Called once before a search starts.
+    fn refresh(&mut self, _board: &Board) {}
+
+    /// Called right after `mv` has been applied to `board`.
+    fn make_move(&mut self, _board: &Board, _mv: &Move) {}
+
+    /// Called right before the last move passed to `make_move` is undone.
+    fn undo_move(&mut self) {}
+}
+
+/// Material, piece-square tables, pawn structure, king safety, mobility and tempo.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HandcraftedEval;
+
+impl HandcraftedEval {
+    /// Computes every evaluation term separately for both sides.
+    pub fn trace(&self, board: &Board) -> EvalTrace {
+        let phase = game_phase(board);
+        let mut trace = EvalTrace {
+            material: [0; Color::COUNT],
+            psts: [0; Color::COUNT],
+            pawns: [0; Color::COUNT],
+            king_safety: [0; Color::COUNT],
+            mobility: [0; Color::COUNT],
+            tempo: [0; Color::COUNT],
+            phase,
+            color: board.state.color,
+        };
+        for color in [Color::White, Color::Black] {
+            let side = color as usize;
+            trace.material[side] = material_from_color(board, color);
+            trace.psts[side] = piece_square_from_color(board, color, phase);
+            trace.pawns[side] = pawn_structure_eval(board, color);
+            trace.king_safety[side] = king_safety_eval(board, color, phase);
+            trace.mobility[side] = mobility_eval(board, color);
+        }
+        if board.state.color != Color::None {
+            trace.tempo[board.state.color as usize] = TEMPO;
+        }
+        trace
+    }
+}
+
+impl Evaluate for HandcraftedEval {
+    fn evaluate(&self, board: &Board) -> i32 {
+        self.trace(board).total()
+    }
+}
 
 impl Board{
+    /// Handcrafted evaluation relative to the side to move.
     pub fn eval(&mut self) -> i32{
-        let mut score = 0i32;
-        score += piece_square_eval(self);
-        score += material_eval(self);
-        score += color_eval(self);
-        //score += pawn_eval(self);
-        //score += mobility_eval(self);
-        score
+        HandcraftedEval.evaluate(self)
     }
 }
 
-fn mobility_eval(board: &mut Board) -> i32{
-    let mut score = 0i32;
-    score += (board.generate_attacking_mask(Color::White).count() as i32) * 10;
-    score -= (board.generate_attacking_mask(Color::Black).count() as i32) * 10;
-    score
+/// Per-term, per-side breakdown of the handcrafted evaluation.
+///
+/// Every term is stored from the point of view of the side it belongs to,
+/// indexed by `Color as usize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalTrace {
+    pub material: [i32; Color::COUNT],
+    pub psts: [i32; Color::COUNT],
+    pub pawns: [i32; Color::COUNT],
+    pub king_safety: [i32; Color::COUNT],
+    pub mobility: [i32; Color::COUNT],
+    pub tempo: [i32; Color::COUNT],
+    pub phase: i32,
+    pub color: Color,
 }
 
-pub fn piece_square_eval(board: &Board) -> i32{
-    let mut score = 0i32;
-    score -= piece_square_from_color(board, Color::Black);
-    score += piece_square_from_color(board, Color::White);
-    score
+impl EvalTrace {
+    fn terms(&self) -> [(&'static str, [i32; Color::COUNT]); 6] {
+        [
+            ("Material", self.material),
+            ("PST", self.psts),
+            ("Pawns", self.pawns),
+            ("King safety", self.king_safety),
+            ("Mobility", self.mobility),
+            ("Tempo", self.tempo),
+        ]
+    }
+
+    /// Sum of all terms from white's point of view.
+    pub fn white_relative(&self) -> i32 {
+        self.terms().iter().map(|(_, term)| term[Color::White as usize] - term[Color::Black as usize]).sum()
+    }
+
+    /// Sum of all terms from the side to move's point of view.
+    pub fn total(&self) -> i32 {
+        match self.color {
+            Color::Black => -self.white_relative(),
+            _ => self.white_relative(),
+        }
+    }
 }
 
-pub fn color_eval(board: &Board) -> i32{
-    match board.state.color{
-        Color::White => {return 20},
-        Color::Black => {return -20},
-        _ => {return 0}
+impl fmt::Display for EvalTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "        Term |   White |   Black |   Total")?;
+        writeln!(f, "-------------+---------+---------+---------")?;
+        for (name, term) in self.terms() {
+            let (white, black) = (term[Color::White as usize], term[Color::Black as usize]);
+            writeln!(f, "{:>12} | {:>7} | {:>7} | {:>7}", name, white, black, white - black)?;
+        }
+        writeln!(f, "-------------+---------+---------+---------")?;
+        writeln!(f, "Phase: {}/{}", self.phase, MAX_PHASE)?;
+        writeln!(f, "Total (white): {}", self.white_relative())?;
+        write!(f, "Total (side to move, {}): {}", self.color.to_string(), self.total())
     }
-            
 }
 
-pub fn piece_square_from_color(board: &Board, color: Color) -> i32{
+/// Remaining non-pawn material, from `MAX_PHASE` (opening) down to 0 (pawn endgame).
+pub fn game_phase(board: &Board) -> i32{
+    let mut phase = 0i32;
+    for color_index in [0, 6] {
+        phase += board.pieces[color_index + Piece::WhiteKnight as usize].count_set() as i32 * PHASE_KNIGHT;
+        phase += board.pieces[color_index + Piece::WhiteBishop as usize].count_set() as i32 * PHASE_BISHOP;
+        phase += board.pieces[color_index + Piece::WhiteRook as usize].count_set() as i32 * PHASE_ROOK;
+        phase += board.pieces[color_index + Piece::WhiteQueen as usize].count_set() as i32 * PHASE_QUEEN;
+    }
+    phase.min(MAX_PHASE)
+}
+
+/// Blends a middlegame and an endgame value by the game phase.
+fn taper(mg: i32, eg: i32, phase: i32) -> i32{
+    (mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE
+}
+
+pub fn piece_square_eval(board: &Board) -> i32{
+    let phase = game_phase(board);
+    let mut score = 0i32;
+    score -= piece_square_from_color(board, Color::Black, phase);
+    score += piece_square_from_color(board, Color::White, phase);
+    score
+}
+
+pub fn piece_square_from_color(board: &Board, color: Color, phase: i32) -> i32{
     let mut score = 0i32;
     if color == Color::White{
-        let mut squares = board.pieces[Piece::WhitePawn as usize].to_squares();
-        for sq in squares.iter(){
+        for sq in board.pieces[Piece::WhitePawn as usize]{
             score += PSTS.pawn[sq.to_index() as usize];
         }
-        squares = board.pieces[Piece::WhiteRook as usize].to_squares();
-        for sq in squares.iter(){
+        for sq in board.pieces[Piece::WhiteRook as usize]{
             score += PSTS.rook[sq.to_index() as usize];
         }
-        squares = board.pieces[Piece::WhiteKnight as usize].to_squares();
-        for sq in squares.iter(){
+        for sq in board.pieces[Piece::WhiteKnight as usize]{
             score += PSTS.knight[sq.to_index() as usize];
         }
-        squares = board.pieces[Piece::WhiteBishop as usize].to_squares();
-        for sq in squares.iter(){
+        for sq in board.pieces[Piece::WhiteBishop as usize]{
             score += PSTS.bishop[sq.to_index() as usize];
         }
-        squares = board.pieces[Piece::WhiteQueen as usize].to_squares();
-        for sq in squares.iter(){
+        for sq in board.pieces[Piece::WhiteQueen as usize]{
             score += PSTS.queen[sq.to_index() as usize];
         }
-        squares = board.pieces[Piece::WhiteKing as usize].to_squares();
-        for sq in squares.iter(){
-            score += PSTS.king_mg[sq.to_index() as usize];
+        for sq in board.pieces[Piece::WhiteKing as usize]{
+            score += taper(PSTS.king_mg[sq.to_index() as usize], PSTS.king_eg[sq.to_index() as usize], phase);
         }
     }else if color == Color::Black{
-        let mut squares = board.pieces[Piece::BlackPawn as usize].to_squares();
-        for sq in squares.iter(){
+        for sq in board.pieces[Piece::BlackPawn as usize]{
             score += PSTS.pawn[flip_square(sq.to_index() as usize)];
         }
-        squares = board.pieces[Piece::BlackRook as usize].to_squares();
-        for sq in squares.iter(){
+        for sq in board.pieces[Piece::BlackRook as usize]{
             score += PSTS.rook[flip_square(sq.to_index() as usize)];
         }
-        squares = board.pieces[Piece::BlackKnight as usize].to_squares();
-        for sq in squares.iter(){
+        for sq in board.pieces[Piece::BlackKnight as usize]{
             score += PSTS.knight[flip_square(sq.to_index() as usize)];
         }
-        squares = board.pieces[Piece::BlackBishop as usize].to_squares();
-        for sq in squares.iter(){
+        for sq in board.pieces[Piece::BlackBishop as usize]{
             score += PSTS.bishop[flip_square(sq.to_index() as usize)];
         }
-        squares = board.pieces[Piece::BlackQueen as usize].to_squares();
-        for sq in squares.iter(){
+        for sq in board.pieces[Piece::BlackQueen as usize]{
             score += PSTS.queen[flip_square(sq.to_index() as usize)];
         }
-        squares = board.pieces[Piece::BlackKing as usize].to_squares();
-        for sq in squares.iter(){
-            score += PSTS.king_mg[flip_square(sq.to_index() as usize)];
+        for sq in board.pieces[Piece::BlackKing as usize]{
+            let flipped = flip_square(sq.to_index() as usize);
+            score += taper(PSTS.king_mg[flipped], PSTS.king_eg[flipped], phase);
         }
     }
     score
@@ -128,70 +228,150 @@ pub fn material_eval(board: &Board) -> i32{
 }
 
 fn material_from_color(board: &Board, color: Color) -> i32{
-    let mut score = 0usize;
-    if color == Color::White{
-        score +=board.pieces[Piece::WhitePawn as usize].count() * 100;
-        score +=board.pieces[Piece::WhiteKnight as usize].count() * 300;
-        score +=board.pieces[Piece::WhiteBishop as usize].count() * 325;
-        score +=board.pieces[Piece::WhiteRook as usize].count() * 500;
-        score +=board.pieces[Piece::WhiteQueen as usize].count() * 900;
-    }else if color == Color::Black{
-        score +=board.pieces[Piece::BlackPawn as usize].count() * 100;
-        score +=board.pieces[Piece::BlackKnight as usize].count() * 300;
-        score +=board.pieces[Piece::BlackBishop as usize].count() * 325;
-        score +=board.pieces[Piece::BlackRook as usize].count() * 500;
-        score +=board.pieces[Piece::BlackQueen as usize].count() * 900;
-    }
-    score as i32
-}
-
-fn pawn_eval(board: &Board) -> i32{
+    let color_index = match color {
+        Color::White => 0,
+        Color::Black => 6,
+        _ => return 0
+    };
     let mut score = 0i32;
-    score += pawn_structure_eval(board, Color::White);
-    score -= pawn_structure_eval(board, Color::Black);
+    for (piece, value) in PIECE_VALUES.iter().enumerate() {
+        score += board.pieces[color_index + piece].count_set() as i32 * value;
+    }
     score
 }
 
 fn pawn_structure_eval(board: &Board, color:Color) -> i32{
     let mut score = 0i32;
-    score += get_isolated_pawns(board, color)*-30;
-    score += get_double_pawns(board, color)*-30;
+    score += get_isolated_pawns(board, color) * ISOLATED_PAWN;
+    score += get_double_pawns(board, color) * DOUBLED_PAWN;
     score
 }
 
-fn get_isolated_pawns(board: &Board, color:Color) -> i32{
-    let mut num_isolated = 0i32;
-    let pawns = match color {
+fn pawns_of(board: &Board, color: Color) -> Bitboard{
+    match color {
         Color::White => board.pieces[Piece::WhitePawn as usize],
         Color::Black => board.pieces[Piece::BlackPawn as usize],
         _ => Bitboard(0)
-    };
-    if (pawns & FILES[0]).0 > 0 && (pawns & FILES[1]).0 == 0 {
-        num_isolated += 1;
-    }
-    if (pawns & FILES[7]).0 > 0 && (pawns & FILES[6]).0 == 0 {
-        num_isolated += 1;
     }
-    for i in 0..5{
-        if (pawns & RANKS[i]).0 == 0 && (pawns & RANKS[i+2]).0 == 0 && (pawns & RANKS[i+1]).0 > 0 {
-            num_isolated += 1;
+}
+
+pub(crate) fn get_isolated_pawns(board: &Board, color:Color) -> i32{
+    let mut num_isolated = 0i32;
+    let pawns = pawns_of(board, color);
+    for file in 1..=8u8{
+        if (pawns & adjacent_files(file - 1)).is_empty() {
+            num_isolated += (pawns & Bitboard::file(file)).count_set() as i32;
         }
     }
-     num_isolated   
+    num_isolated
 }
 
-fn get_double_pawns(board: &Board, color:Color) ->i32{
+pub(crate) fn get_double_pawns(board: &Board, color:Color) ->i32{
     let mut num_doubled = 0i32;
-    let pawns = match color {
-        Color::White => board.pieces[Piece::WhitePawn as usize],
-        Color::Black => board.pieces[Piece::BlackPawn as usize],
-        _ => Bitboard(0)
+    let pawns = pawns_of(board, color);
+    for file in 1..=8u8{
+        let on_file = (pawns & Bitboard::file(file)).count_set() as i32;
+        if on_file >= 2 {
+            num_doubled += on_file - 1;
+        }
+    }
+    num_doubled
+}
+
+/// Rewards pawns directly in front of the king, fading out towards the endgame.
+fn king_safety_eval(board: &Board, color: Color, phase: i32) -> i32{
+    taper(pawn_shield(board, color) * PAWN_SHIELD, 0, phase)
+}
+
+/// Number of own pawns on the two ranks in front of the king and its neighbouring files.
+pub(crate) fn pawn_shield(board: &Board, color: Color) -> i32{
+    let (king, pawns) = match color {
+        Color::White => (board.pieces[Piece::WhiteKing as usize], board.pieces[Piece::WhitePawn as usize]),
+        Color::Black => (board.pieces[Piece::BlackKing as usize], board.pieces[Piece::BlackPawn as usize]),
+        _ => return 0
     };
-    for i in 0..7{
-        if (pawns & RANKS[i]).count() >= 2 {
-            num_doubled += 1;
+    if king.is_empty() {
+        return 0;
+    }
+    let sq = king.lsb();
+    let files = Bitboard::file(sq.file() + 1) | adjacent_files(sq.file());
+    let mut ranks = Bitboard(0);
+    for step in 1..=2i8 {
+        let rank = if color == Color::White { sq.rank() as i8 + step } else { sq.rank() as i8 - step };
+        if (0..8).contains(&rank) {
+            ranks = ranks | Bitboard::rank(rank as u8 + 1);
         }
     }
-    num_doubled   
+    (pawns & files & ranks).count_set() as i32
 }
 
+fn mobility_eval(board: &Board, color: Color) -> i32{
+    mobility_counts(board, color).iter().zip(MOBILITY.iter()).map(|(count, bonus)| count * bonus).sum()
+}
+
+/// Counts the squares each minor and major piece can reach that are not occupied by its own side,
+/// summed per piece type in the order of `MOBILITY`.
+pub(crate) fn mobility_counts(board: &Board, color: Color) -> [i32; 4]{
+    let color_index = match color {
+        Color::White => 0,
+        Color::Black => 6,
+        _ => return [0; 4]
+    };
+    let occupied = board.occupied();
+    let own = board.colors[color as usize];
+    let mut counts = [0i32; 4];
+    for sq in board.pieces[color_index + Piece::WhiteKnight as usize] {
+        counts[0] += (lookup_knight(&sq) & !own).count_set() as i32;
+    }
+    for sq in board.pieces[color_index + Piece::WhiteBishop as usize] {
+        counts[1] += (lookup_bishop(&sq, &occupied) & !own).count_set() as i32;
+    }
+    for sq in board.pieces[color_index + Piece::WhiteRook as usize] {
+        counts[2] += (lookup_rook(&sq, &occupied) & !own).count_set() as i32;
+    }
+    for sq in board.pieces[color_index + Piece::WhiteQueen as usize] {
+        counts[3] += (lookup_queen(&sq, &occupied) & !own).count_set() as i32;
+    }
+    counts
+}
+
+
+
+
+/// ######################################################
+/// ################### TESTING ##########################
+/// ######################################################
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_eval_is_side_relative() {
+        let white = Board::from_str("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        let black = Board::from_str("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 2 3").unwrap();
+        let white_trace = HandcraftedEval.trace(&white);
+        let black_trace = HandcraftedEval.trace(&black);
+        assert_eq!(white_trace.total(), white_trace.white_relative());
+        assert_eq!(black_trace.total(), -black_trace.white_relative());
+        // Only the tempo bonus changes hands
+        assert_eq!(white_trace.white_relative() - black_trace.white_relative(), 2 * TEMPO);
+    }
+
+    #[test]
+    fn test_mirrored_position_evaluates_equal() {
+        let board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let mirrored = Board::from_str("r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b KQkq - 0 1").unwrap();
+        assert_eq!(HandcraftedEval.evaluate(&board), HandcraftedEval.evaluate(&mirrored));
+    }
+
+    #[test]
+    fn test_trace_start_position() {
+        let trace = HandcraftedEval.trace(&Board::start());
+        assert_eq!(trace.phase, MAX_PHASE);
+        assert_eq!(trace.material[0], trace.material[1]);
+        assert_eq!(trace.total(), TEMPO);
+    }
+}
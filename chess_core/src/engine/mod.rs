@@ -1,4 +1,9 @@
 pub mod perft;
-//pub mod search;
+pub mod search;
 pub mod eval;
-pub mod piece_square_tables;
\ No newline at end of file
+pub mod piece_square_tables;
+pub mod tuner;
+pub mod testsuite;
+pub mod book;
+pub mod book_builder;
+pub mod syzygy;
\ No newline at end of file
@@ -1,6 +1,16 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+
 use crate::types::board::board::Board;
+use crate::types::moves::Move;
 
+/// Counts the leaf nodes `ply` half-moves below `board`.
+/// The last ply is bulk counted: the legal moves are generated but never applied.
 pub fn perft(board: &mut Board, ply: u8) -> u64 {
+    if ply == 0 {
+        return 1;
+    }
 	let mut num_nodes:u64 = 0;
     let moves = board.generate_all_moves();
 
@@ -16,6 +26,148 @@ pub fn perft(board: &mut Board, ply: u8) -> u64 {
     num_nodes
 }
 
+/// Entry packed into two words. The key is stored xor the data, so an entry
+/// torn by two threads writing at once fails the key check instead of
+/// returning a wrong count.
+#[derive(Default)]
+struct PerftEntry {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+const DEPTH_BITS: u32 = 8;
+
+/// Always-replace cache of subtree counts keyed by Zobrist hash and remaining depth.
+/// Lock free, one table is shared by all threads of a divide.
+pub struct PerftTable {
+    entries: Vec<PerftEntry>,
+    mask: usize,
+}
+
+impl PerftTable {
+    pub fn new(mb: usize) -> Self {
+        let max_entries = (mb * 1024 * 1024) / std::mem::size_of::<PerftEntry>();
+        let num_entries = if max_entries == 0 { 1 } else { 1 << max_entries.ilog2() };
+        PerftTable {
+            entries: (0..num_entries).map(|_| PerftEntry::default()).collect(),
+            mask: num_entries - 1,
+        }
+    }
+
+    #[inline]
+    fn probe(&self, key: u64, depth: u8) -> Option<u64> {
+        let entry = &self.entries[key as usize & self.mask];
+        let data = entry.data.load(Ordering::Relaxed);
+        let stored_key = entry.key.load(Ordering::Relaxed) ^ data;
+        (stored_key == key && data as u8 == depth).then_some(data >> DEPTH_BITS)
+    }
+
+    #[inline]
+    fn store(&self, key: u64, depth: u8, nodes: u64) {
+        if nodes >> (64 - DEPTH_BITS) != 0 {
+            return;
+        }
+        let data = (nodes << DEPTH_BITS) | depth as u64;
+        let entry = &self.entries[key as usize & self.mask];
+        entry.key.store(key ^ data, Ordering::Relaxed);
+        entry.data.store(data, Ordering::Relaxed);
+    }
+}
+
+/// Same as [`perft`] but caches subtrees of depth 2 and more in `table`.
+pub fn perft_hashed(board: &mut Board, ply: u8, table: &PerftTable) -> u64 {
+    if ply <= 1 {
+        return perft(board, ply);
+    }
+    let key = board.hash();
+    if let Some(nodes) = table.probe(key, ply) {
+        return nodes;
+    }
+
+    let mut num_nodes:u64 = 0;
+    let moves = board.generate_all_moves();
+    for mv in moves.iter(){
+        board.apply_move(mv);
+        num_nodes += perft_hashed(board, ply-1, table);
+        board.undo_move(mv);
+    }
+
+    table.store(key, ply, num_nodes);
+    num_nodes
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PerftOptions {
+    /// Number of threads the root moves are split across.
+    pub threads: usize,
+    /// Size of the hash table shared by all threads in MB, 0 disables hashing.
+    pub hash_mb: usize,
+}
+
+impl Default for PerftOptions {
+    fn default() -> Self {
+        PerftOptions { threads: 1, hash_mb: 0 }
+    }
+}
+
+fn perft_subtree(board: &mut Board, mv: &Move, ply: u8, table: Option<&PerftTable>) -> u64 {
+    board.apply_move(mv);
+    let nodes = match table {
+        Some(table) => perft_hashed(board, ply - 1, table),
+        None => perft(board, ply - 1),
+    };
+    board.undo_move(mv);
+    nodes
+}
+
+/// Node counts per root move, in move generation order.
+/// Root moves are dealt round-robin to `options.threads` workers, each with its own board.
+pub fn perft_divide(board: &Board, ply: u8, options: &PerftOptions) -> Vec<(Move, u64)> {
+    if ply == 0 {
+        return Vec::new();
+    }
+    let mut root = board.clone();
+    let moves: Vec<Move> = root.generate_all_moves().iter().copied().collect();
+    let threads = options.threads.clamp(1, moves.len().max(1));
+    let table = (options.hash_mb > 0).then(|| PerftTable::new(options.hash_mb));
+
+    let mut counts = vec![0u64; moves.len()];
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| {
+                let (moves, table) = (&moves, table.as_ref());
+                let mut board = board.clone();
+                scope.spawn(move || {
+                    (worker..moves.len())
+                        .step_by(threads)
+                        .map(|i| (i, perft_subtree(&mut board, &moves[i], ply, table)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            for (i, nodes) in worker.join().expect("perft worker panicked") {
+                counts[i] = nodes;
+            }
+        }
+    });
+
+    moves.into_iter().zip(counts).collect()
+}
+
+/// Formats a divide the way Stockfish prints `go perft`.
+pub fn format_divide(divide: &[(Move, u64)]) -> String {
+    let mut out = String::new();
+    for (mv, nodes) in divide {
+        let _ = writeln!(out, "{}: {}", mv.to_lan(), nodes);
+    }
+    let total: u64 = divide.iter().map(|(_, nodes)| nodes).sum();
+    let _ = write!(out, "\nNodes searched: {}", total);
+    out
+}
+
+
 
 
 
@@ -70,5 +222,55 @@ mod legal_move_count_tests {
         assert_eq!(num, 119060324);
     }
 
+    #[test]
+    fn perft_slider_backends_agree() {
+        use std::str::FromStr;
+        use crate::types::board::lookup::{pext_supported, set_slider_backend, slider_backend, SliderBackend};
+        if !pext_supported() {
+            eprintln!("skipping PEXT perft, the CPU has no BMI2");
+            return;
+        }
+        let previous = slider_backend();
+        let mut counts = Vec::new();
+        for backend in [SliderBackend::Magic, SliderBackend::Pext] {
+            assert!(set_slider_backend(backend));
+            let mut board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+            counts.push(perft::perft(&mut board, 3));
+        }
+        set_slider_backend(previous);
+        assert_eq!(counts, vec![97862, 97862]);
+    }
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    #[test]
+    fn perft_divide_sums_to_perft() {
+        use std::str::FromStr;
+        let board = Board::from_str(KIWIPETE).unwrap();
+        let divide = perft::perft_divide(&board, 3, &perft::PerftOptions::default());
+        assert_eq!(divide.len(), 48);
+        assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 97862);
+
+        let output = perft::format_divide(&divide);
+        assert!(output.starts_with(&format!("{}: {}\n", divide[0].0.to_lan(), divide[0].1)));
+        assert!(output.ends_with("\n\nNodes searched: 97862"));
+    }
+
+    #[test]
+    fn perft_hashed_and_parallel_agree() {
+        use std::str::FromStr;
+        let board = Board::from_str(KIWIPETE).unwrap();
+        let plain = perft::perft_divide(&board, 4, &perft::PerftOptions::default());
+        let hashed = perft::perft_divide(&board, 4, &perft::PerftOptions { threads: 1, hash_mb: 1 });
+        let parallel = perft::perft_divide(&board, 4, &perft::PerftOptions { threads: 4, hash_mb: 1 });
+        assert_eq!(plain, hashed);
+        assert_eq!(plain, parallel);
+        assert_eq!(plain.iter().map(|(_, nodes)| nodes).sum::<u64>(), 4085603);
+
+        let mut board = Board::start();
+        let table = perft::PerftTable::new(1);
+        assert_eq!(perft::perft_hashed(&mut board, 5, &table), 4865609);
+        assert_eq!(perft::perft(&mut board, 0), 1);
+    }
 
 }
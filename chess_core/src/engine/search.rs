@@ -1,56 +1,147 @@
 use crate::types::board::board::Board;
 use crate::types::moves::Move;
-use crate::types::color::Color;
 use crate::types::board::transposition::{TranspositionTable, TableEntryFlag};
+use crate::engine::eval::Evaluate;
+use std::cell::Cell;
 use std::time::Duration;
 use std::sync::Arc;
-use std::sync::atomic::AtomicBool;
-//use crate::api::uci::uci::{UciInfo, post_uci_info};
-use crate::types::board::internalstate::GameState;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::types::board::outcome::Outcome;
+use crate::engine::syzygy::{self, Tablebase, Wdl};
 
 
 const MAX_QUIESCENCE_PLY: u8 = 32;
 const MAX_DEPTH: i8 = 64;
 
-fn count_hash_occurrences(hashes_array: &[u64; 100], target_hash: u64) -> usize {
-    let mut count = 0;
-    for &hash in hashes_array.iter() {
-        if hash == target_hash {
-            count += 1;
+/// When a running search has to give up: at `end_time`, or as soon as `stop` is set.
+/// Also holds the tablebase the search probes and counts its hits.
+pub struct SearchControl<'a> {
+    pub end_time: Instant,
+    pub stop: Option<&'a AtomicBool>,
+    pub tablebase: Option<&'a dyn Tablebase>,
+    /// Root moves the tablebase allows, the others are not searched.
+    pub root_moves: Option<Vec<Move>>,
+    tb_hits: Cell<u64>,
+}
+
+impl<'a> SearchControl<'a> {
+    /// Searches until `end_time` without a tablebase and cannot be stopped otherwise.
+    pub fn until(end_time: Instant) -> Self {
+        SearchControl { end_time, stop: None, tablebase: None, root_moves: None, tb_hits: Cell::new(0) }
+    }
+
+    /// Also gives up once `stop` is set, like `stop` in UCI does.
+    pub fn with_stop(self, stop: &'a AtomicBool) -> Self {
+        SearchControl { stop: Some(stop), ..self }
+    }
+
+    pub fn with_tablebase(self, tablebase: Option<&'a dyn Tablebase>) -> Self {
+        SearchControl { tablebase, ..self }
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.end_time <= Instant::now() || self.stop.is_some_and(|stop| stop.load(Ordering::Relaxed))
+    }
+
+    /// Successful tablebase probes so far.
+    pub fn tb_hits(&self) -> u64 {
+        self.tb_hits.get()
+    }
+
+    fn add_tb_hits(&self, hits: u64) {
+        self.tb_hits.set(self.tb_hits.get() + hits);
+    }
+
+    /// Result of `board` from the tablebase, if it covers the position.
+    fn probe_wdl(&self, board: &mut Board) -> Option<Wdl> {
+        let tablebase = self.tablebase.filter(|tablebase| syzygy::probeable(*tablebase, board))?;
+        let wdl = tablebase.probe_wdl(board)?;
+        self.add_tb_hits(1);
+        Some(wdl)
+    }
+}
+
+pub struct UciInfo {
+    pub depth: u8,
+    pub seldepth: u8,
+    pub score_cp: Option<i32>,
+    pub score_mate: Option<i32>,
+    pub nodes: u64,
+    pub nps: u64,
+    pub time: Duration,
+    pub tbhits: u64,
+    pub pv: Vec<Move>,
+}
+
+pub fn post_uci_info(info: UciInfo) {
+    // Start with the base "info" command
+    let mut uci_string = String::from("info");
+
+    // Add depth and selective depth
+    uci_string.push_str(&format!(" depth {}", info.depth));
+    uci_string.push_str(&format!(" seldepth {}", info.seldepth));
+
+    // Add score. It can be either centipawns (cp) or mate in X.
+    if let Some(mate_in) = info.score_mate {
+        uci_string.push_str(&format!(" score mate {}", mate_in));
+    } else if let Some(centipawns) = info.score_cp {
+        uci_string.push_str(&format!(" score cp {}", centipawns));
+    }
+
+    // Add node count, nodes per second, and time
+    uci_string.push_str(&format!(" nodes {}", info.nodes));
+    uci_string.push_str(&format!(" nps {}", info.nps));
+    uci_string.push_str(&format!(" time {}", info.time.as_millis()));
+    if info.tbhits > 0 {
+        uci_string.push_str(&format!(" tbhits {}", info.tbhits));
+    }
+
+    // Add the Principal Variation (PV)
+    if !info.pv.is_empty() {
+        uci_string.push_str(" pv");
+        for mv in info.pv {
+            uci_string.push_str(&format!(" {}", mv.to_lan()));
         }
     }
-    count
+
+    // Print the final string to standard output, which the GUI will read.
+    println!("{}", uci_string);
 }
 
 
-pub fn negamax(
+#[allow(clippy::too_many_arguments)]
+pub fn negamax<E: Evaluate>(
     board: &mut Board,
+    evaluator: &mut E,
     tt: &mut TranspositionTable,
     depth: i8,
     total_depth: i8,
     mut alpha: i32,
     beta: i32,
-    end_time: Instant
+    control: &SearchControl
 ) -> (i32, Option<Move>, u64, u8) {
     let mut q_depth = 0u8;
 
-    let multiplier = if board.state.color == Color::White { 1 } else { -1 };
-
-    
     // At a leaf node, return the static evaluation.
     if depth == 0 {
         // We only care about the score and nodes from qsearch
-        let (score, _, nodes, depth) = quiescence_search(board, alpha, beta, 0, end_time);
+        let (score, _, nodes, depth) = quiescence_search(board, evaluator, alpha, beta, 0, control);
         q_depth = q_depth.max(depth);
         return (score, None, nodes, q_depth);
     }
 
     let zobrist_hash = board.hash();
 
-    if count_hash_occurrences(&board.state.hash_history, zobrist_hash) >= 2{
+    if board.is_repetition(3){
         return (0, None, 0, 0);
     }
 
+    // Below the root, positions in the tablebase are scored exactly right after a
+    // zeroing move, later the fifty move rule may turn a win into a draw
+    if depth < total_depth && board.state.halfmove_clock == 0 && let Some(wdl) = control.probe_wdl(board) {
+        return (wdl.to_score((total_depth - depth) as i32), None, 1, q_depth);
+    }
+
     // Probe the TT for an existing entry for this position
     if let Some(entry) = tt.probe(zobrist_hash) {
         // If the stored search was deep enough, we can use the result
@@ -82,11 +173,14 @@ pub fn negamax(
     let mut best_move = None;
     let mut moves = board.generate_all_moves();
 
-    match board.state.game_state {
-        GameState::BlackWin => {return ((-i32::MAX + (total_depth - depth) as i32) * multiplier, None, 1, q_depth)},
-        GameState::WhiteWin => {return ((i32::MAX - (total_depth - depth) as i32) * multiplier, None, 1, q_depth)},
-        GameState::Draw => {return (0, None, 1, q_depth)},
-        GameState::InProgress => {}
+    match board.outcome_with_moves(&moves, !board.state.checker.is_empty()) {
+        // A winner here means the side to move has been mated
+        Some(Outcome { winner: Some(_), .. }) => {return (-i32::MAX + (total_depth - depth) as i32, None, 1, q_depth)},
+        Some(_) => {return (0, None, 1, q_depth)},
+        None => {}
+    }
+    if depth == total_depth && let Some(root_moves) = &control.root_moves {
+        moves.moves.retain(|mv| root_moves.contains(mv));
     }
     
     let original_alpha = alpha;
@@ -99,7 +193,7 @@ pub fn negamax(
     }
 
     for mv in moves.iter() {
-        if end_time <= Instant::now() {
+        if control.stopped() {
             /*
             if let Some(_best_move) = best_move {
                 let flag = if best_score <= original_alpha {
@@ -115,13 +209,15 @@ pub fn negamax(
             return (0, None, 0,0);
             
         }
-        board.apply_move(&mv);
+        board.apply_move(mv);
+        evaluator.make_move(board, mv);
         
-        let (mut score, _, num, depth) = negamax(board, tt, depth - 1, total_depth, -beta, -alpha, end_time);
+        let (mut score, _, num, depth) = negamax(board, evaluator, tt, depth - 1, total_depth, -beta, -alpha, control);
         q_depth = q_depth.max(depth);
         nodes += num;
         score = -score;
-        board.undo_move(&mv);
+        evaluator.undo_move();
+        board.undo_move(mv);
 
 
         if score > best_score {
@@ -154,29 +250,28 @@ pub fn negamax(
 
 
 
-pub fn quiescence_search(
+pub fn quiescence_search<E: Evaluate>(
     board: &mut Board,
+    evaluator: &mut E,
     mut alpha: i32,
     beta: i32,
     ply: u8, // Tracks the depth of the quiescence search itself
-    end_time: Instant
+    control: &SearchControl
 ) -> (i32, Option<Move>, u64, u8) { // We return Option<Move> for consistency, but it's often unused
-    //if end_time <= Instant::now() {
-        //return (0, None, 0, 0);
-    //}
+    if control.stopped() {
+        return (0, None, 0, 0);
+    }
     let mut max_q_depth = 0u8;
 
-    let multiplier = if board.state.color == Color::White { 1 } else { -1 };
-    
     if ply >= MAX_QUIESCENCE_PLY {
-        return (board.eval() * multiplier, None, 1, ply);
+        return (evaluator.evaluate(board), None, 1, ply);
     }
 
     let mut nodes = 1u64;
     // 1. "Stand Pat" Score: First, get the evaluation of the current position.
     // This represents the score we can get if we choose not to make any more captures.
     
-    let stand_pat_score = board.eval() * multiplier;
+    let stand_pat_score = evaluator.evaluate(board);
 
     // 2. Alpha-Beta Pruning check with the stand-pat score.
     // If our static eval is already better than what the opponent can guarantee,
@@ -192,15 +287,17 @@ pub fn quiescence_search(
     // 3. Generate and search only capture moves.
     // It's CRITICAL to order these moves, e.g., using MVV-LVA.
     // Most Valuable Victim - Least Valuable Attacker is highly effective here.
-    let mut noisy = board.generate_noisy_moves(); // You'll need to implement this helper
+    let noisy = board.generate_noisy_moves(); // You'll need to implement this helper
     // TODO: Sort `captures` using MVV-LVA for huge performance gains.
 
     for mv in noisy.iter() {
         board.apply_move(mv);
-        let (mut score, _, num, q_depth) = quiescence_search(board, -beta, -alpha, ply + 1, end_time);
+        evaluator.make_move(board, mv);
+        let (mut score, _, num, q_depth) = quiescence_search(board, evaluator, -beta, -alpha, ply + 1, control);
         max_q_depth = max_q_depth.max(q_depth);
         score = -score;
         nodes += num;
+        evaluator.undo_move();
         board.undo_move(mv);
 
         if score > stand_pat_score {
@@ -224,41 +321,53 @@ pub fn quiescence_search(
 
 use std::time::Instant;
 
-pub fn iterative_deepening_search(board: &mut Board, search_time_limit: Duration, stop_signal: Arc<AtomicBool>) -> Option<Move>{
+/// Searches `board` deeper and deeper until the time is up or `stop_signal` is set, with a
+/// transposition table of `hash_mb` MB, and returns the best move of the last finished depth.
+pub fn iterative_deepening_search<E: Evaluate>(board: &mut Board, evaluator: &mut E, tablebase: Option<&dyn Tablebase>, hash_mb: usize, search_time_limit: Duration, stop_signal: Arc<AtomicBool>) -> Option<Move>{
     let start_time = Instant::now();
-    let end_time = start_time + search_time_limit;
+    let mut control = SearchControl::until(start_time + search_time_limit).with_stop(&stop_signal).with_tablebase(tablebase);
     let mut total_nodes = 0;
     let mut principal_variation: Vec<Move> = Vec::new();
-    let mut tt = TranspositionTable::new(64);
+    let mut tt = TranspositionTable::new(hash_mb);
+    evaluator.refresh(board);
+
+    // With the root in the tablebase only the moves keeping its best result are
+    // searched, and that result is the score
+    let root = tablebase.and_then(|tablebase| syzygy::filter_root_moves(tablebase, board));
+    let root_wdl = root.as_ref().map(|(_, wdl)| *wdl);
+    if let Some((moves, _)) = root {
+        control.add_tb_hits(board.generate_all_moves().len() as u64);
+        control.root_moves = Some(moves);
+    }
 
 
     for depth in 1i8..=MAX_DEPTH { // MAX_DEPTH is a constant like 64
-        if start_time.elapsed() >= search_time_limit || stop_signal.load(std::sync::atomic::Ordering::Relaxed) {
+        if control.stopped() {
             break;
         }
         
         // --- Call your core search function ---
-        let (mut score, best_move_for_depth, nodes_this_iteration, q_depth) = negamax(
+        let (score, best_move_for_depth, nodes_this_iteration, q_depth) = negamax(
             board,
+            evaluator,
             &mut tt, // your transposition table
             depth,
             depth, 
             i32::MIN + 1,
             i32::MAX ,
-            end_time,
+            &control,
         );
         total_nodes += nodes_this_iteration;
+        // An interrupted iteration has no reliable result
+        if control.stopped() && !principal_variation.is_empty() {
+            break;
+        }
         
 
-        //let multiplier = if board.state.color == Color::White { 1 } else { -1 };
-        //score *= multiplier;
-
-
         // After each depth, update the PV and post the UCI info
-        if let Some(mv) = best_move_for_depth {
+        if best_move_for_depth.is_some() {
              // You need to reconstruct the PV from your transposition table
-             principal_variation = vec!(best_move_for_depth.clone().unwrap_or_default());
-        } else {
+             principal_variation = vec!(best_move_for_depth.unwrap_or_default());
         }
         // --- Check for time up ---
 
@@ -273,7 +382,7 @@ pub fn iterative_deepening_search(board: &mut Board, search_time_limit: Duration
 
         let mut score_mate: Option<i32> = None;
         if score <= -i32::MAX + 512{
-            score_mate = Some((score + i32::MAX))
+            score_mate = Some(score + i32::MAX)
     
         }else if score >= i32::MAX - 512{
             score_mate = Some(-(score - i32::MAX))
@@ -282,11 +391,12 @@ pub fn iterative_deepening_search(board: &mut Board, search_time_limit: Duration
         let uci_update = UciInfo {
             depth: depth as u8,
             seldepth: q_depth, // You would need to track this from qsearch
-            score_cp: Some(score), // Or parse for mate scores
-            score_mate: score_mate,      //
+            score_cp: Some(root_wdl.map_or(score, |wdl| wdl.to_score(0))), // Or parse for mate scores
+            score_mate,      //
             nodes: total_nodes,
             nps,
             time: elapsed_time,
+            tbhits: control.tb_hits(),
             pv: principal_variation.clone(),
         };
 
@@ -295,10 +405,105 @@ pub fn iterative_deepening_search(board: &mut Board, search_time_limit: Duration
         
     }
 
-    // Finally, send the best move found from the last completed iteration
-    if let Some(best_move) = principal_variation.get(0) {
-        return Some(*best_move)
-    }else{
-        return None
+    // Finally, send the best move found from the last completed iteration, stopped
+    // before the first one finished any allowed move has to do
+    principal_variation.first().copied().or_else(|| match &control.root_moves {
+        Some(root_moves) => root_moves.first().copied(),
+        None => board.generate_all_moves().iter().next().copied(),
+    })
+}
+
+
+
+/// ######################################################
+/// ################### TESTING ##########################
+/// ######################################################
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::eval::HandcraftedEval;
+    use crate::types::color::Color;
+    use crate::types::piece::Piece;
+    use std::str::FromStr;
+    use crate::types::square::Square;
+
+    /// Rewards a white pawn on e4 and nothing else.
+    struct PawnOnE4;
+
+    impl Evaluate for PawnOnE4 {
+        fn evaluate(&self, board: &Board) -> i32 {
+            let score = if board.is_square_set(Piece::WhitePawn, &Square::E4) { 100 } else { 0 };
+            if board.state.color == Color::White { score } else { -score }
+        }
+    }
+
+    #[test]
+    fn test_negamax_uses_injected_evaluator() {
+        let mut board = Board::start();
+        let mut tt = TranspositionTable::new(1);
+        let control = SearchControl::until(Instant::now() + Duration::from_secs(60));
+        let (score, best_move, _, _) = negamax(&mut board, &mut PawnOnE4, &mut tt, 1, 1, i32::MIN + 1, i32::MAX, &control);
+        assert_eq!(score, 100);
+        assert_eq!(best_move.unwrap().to_lan(), "e2e4");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_negamax_finds_mate_in_one_for_either_side() {
+        let control = SearchControl::until(Instant::now() + Duration::from_secs(60));
+        for (fen, expected) in [
+            ("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4", "h5f7"),
+            ("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2", "d8h4"),
+        ] {
+            let mut board = Board::from_str(fen).unwrap();
+            let mut tt = TranspositionTable::new(1);
+            let (score, best_move, _, _) = negamax(&mut board, &mut HandcraftedEval, &mut tt, 2, 2, i32::MIN + 1, i32::MAX, &control);
+            assert_eq!(best_move.unwrap().to_lan(), expected, "FEN {}", fen);
+            assert_eq!(score, i32::MAX - 1, "FEN {}", fen);
+        }
+    }
+
+    #[test]
+    fn test_stop_ends_the_running_iteration() {
+        let mut board = Board::start();
+        let stop = Arc::new(AtomicBool::new(false));
+        let signal = stop.clone();
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            signal.store(true, Ordering::Relaxed);
+        });
+
+        let start = Instant::now();
+        let best_move = iterative_deepening_search(&mut board, &mut HandcraftedEval, None, 16, Duration::from_secs(3600), stop);
+        stopper.join().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5), "search ran {:?} after stop", start.elapsed());
+        assert!(board.generate_all_moves().iter().any(|mv| Some(*mv) == best_move));
+    }
+
+    #[test]
+    fn test_tablebase_scores_and_root_moves() {
+        use crate::engine::syzygy::{tests::RookEndings, TB_MAX_PLY, TB_WIN};
+        // Taking the knight zeroes the clock, so the rook ending after it is probed
+        let mut board = Board::from_str("8/8/8/n7/8/8/1k6/R3K3 w - - 0 1").unwrap();
+        let mut tt = TranspositionTable::new(1);
+        let control = SearchControl::until(Instant::now() + Duration::from_secs(60)).with_tablebase(Some(&RookEndings));
+        let (score, best_move, _, _) = negamax(&mut board, &mut HandcraftedEval, &mut tt, 2, 2, i32::MIN + 1, i32::MAX, &control);
+        assert_eq!(score, TB_WIN - 1);
+        assert_eq!(best_move.unwrap().to_lan(), "a1a5");
+        assert!(control.tb_hits() > 0);
+
+        // Rook moves do not zero, a win found after them could still be a draw by the clock
+        let mut board = Board::from_str("8/8/8/8/8/8/1k6/R3K3 w - - 0 1").unwrap();
+        let mut tt = TranspositionTable::new(1);
+        let control = SearchControl::until(Instant::now() + Duration::from_secs(60)).with_tablebase(Some(&RookEndings));
+        let (score, _, _, _) = negamax(&mut board, &mut HandcraftedEval, &mut tt, 2, 2, i32::MIN + 1, i32::MAX, &control);
+        assert!(score < TB_WIN - TB_MAX_PLY);
+
+        // At the root only the moves the tablebase allows are searched
+        let (root_moves, _) = syzygy::filter_root_moves(&RookEndings, &board).unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let best_move = iterative_deepening_search(&mut board, &mut HandcraftedEval, Some(&RookEndings), 1, Duration::from_millis(300), stop);
+        assert!(root_moves.contains(&best_move.unwrap()));
+    }
+}
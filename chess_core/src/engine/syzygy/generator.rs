@@ -0,0 +1,739 @@
+//! Builds Syzygy tables by retrograde analysis, every position held in memory.
+//! Meant for the small endgames the tests use: tables with pawns on both
+//! sides or with results the fifty move rule changes are not supported.
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+use crate::types::board::board::Board;
+use super::table::{
+    piece_code, Encoding, Layout, Material, TableKind, HAS_PAWNS, LEAF, LOSS_PLIES, MAPPED, SINGLE_VALUE, SPLIT, STM,
+    TB_PIECES, WIN_PLIES,
+};
+use super::is_zeroing;
+
+/// Largest table the generator builds.
+const MAX_PIECES: usize = 5;
+/// 32 byte blocks of codes and a sparse index entry every 4096 values.
+const BLOCK_SIZE_LOG2: u8 = 5;
+const SPAN_LOG2: u8 = 12;
+/// Symbol ids have 12 bits, 0xFFF marks a leaf.
+const MAX_SYMBOLS: usize = LEAF as usize;
+/// Values one symbol may stand for.
+const MAX_SYMBOL_VALUES: u32 = 256;
+/// Pairs seen less often are not worth a symbol.
+const MIN_PAIR_COUNT: u32 = 8;
+const MAX_CODE_LEN: u32 = 32;
+/// Values one block may hold, its length is stored in 16 bits.
+const MAX_BLOCK_VALUES: u32 = 1 << 16;
+
+// Edges from a position to the positions after its moves
+const EXTERNAL: u32 = 1 << 31;
+const ZEROING: u32 = 1 << 30;
+const TARGET: u32 = ZEROING - 1;
+
+const UNKNOWN: i8 = i8::MIN;
+const WIN: i8 = 2;
+const DRAW: i8 = 0;
+const LOSS: i8 = -2;
+
+
+#[derive(Debug)]
+pub enum GenerateError {
+    InvalidName(String),
+    Unsupported(String),
+    FiftyMoveRule(String),
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenerateError::InvalidName(name) => write!(f, "{} is not a material of 3 to {} pieces", name, MAX_PIECES),
+            GenerateError::Unsupported(name) => write!(f, "{} has pawns on both sides", name),
+            GenerateError::FiftyMoveRule(name) => write!(f, "{} has results the fifty move rule changes", name),
+        }
+    }
+}
+
+impl std::error::Error for GenerateError {}
+
+/// Contents of the `.rtbw` and `.rtbz` files of the material `name`, like `KRvK`.
+/// Tables of the materials reachable by captures and promotions are solved too.
+pub fn generate(name: &str) -> Result<(Vec<u8>, Vec<u8>), GenerateError> {
+    let material = Material::from_name(name)
+        .filter(|m| (3..=MAX_PIECES).contains(&m.piece_count()))
+        .ok_or_else(|| GenerateError::InvalidName(name.to_string()))?;
+    if material.pawn_count()[1] > 0 {
+        return Err(GenerateError::Unsupported(name.to_string()));
+    }
+    let mut solved = HashMap::new();
+    solve_reachable(&material, &mut solved)?;
+    let table = solve(material, &solved)?;
+    Ok((write_wdl(&table), write_dtz(&table)))
+}
+
+
+/// Results of a material and the ones it reaches straight from the retrograde
+/// analysis, to check tables read from files against.
+#[cfg(test)]
+pub(super) struct Solution(HashMap<u64, Solved>);
+
+#[cfg(test)]
+impl Solution {
+    pub(super) fn new(name: &str) -> Result<Solution, GenerateError> {
+        let material = Material::from_name(name).ok_or_else(|| GenerateError::InvalidName(name.to_string()))?;
+        let mut solved = HashMap::new();
+        solve_reachable(&material, &mut solved)?;
+        let table = solve(material, &solved)?;
+        solved.insert(material.key(), table);
+        Ok(Solution(solved))
+    }
+
+    /// WDL and DTZ of `board` as `probe_wdl` and `probe_dtz` report them.
+    pub(super) fn probe(&self, board: &Board) -> Option<(super::Wdl, i32)> {
+        let table = find(&self.0, &Material::of_board(board))?;
+        let position = table.position(board);
+        Some((super::Wdl::from_i8(table.wdl[position])?, table.dtz[position] as i32))
+    }
+}
+
+/// Every position of one material with its result.
+struct Solved {
+    layout: Layout,
+    /// First position of every sub-table, by side to move and leading pawn file.
+    offsets: [[usize; 4]; 2],
+    legal: Vec<bool>,
+    wdl: Vec<i8>,
+    /// Plies to the next zeroing move, negative for losses, as `probe_dtz` reports it.
+    dtz: Vec<i16>,
+}
+
+impl Solved {
+    fn position(&self, board: &Board) -> usize {
+        let (stm, file, idx) = self.layout.locate(board);
+        self.offsets[stm % self.layout.sides][file] + idx as usize
+    }
+
+    /// Positions of the sub-table of `side` and `file`.
+    fn range(&self, side: usize, file: usize) -> std::ops::Range<usize> {
+        let start = self.offsets[side][file];
+        start..start + self.layout.encodings[side][file].size() as usize
+    }
+}
+
+/// Materials one capture or promotion away.
+fn sub_materials(material: &Material) -> Vec<Material> {
+    let mut subs = Vec::new();
+    let captures = |material: Material, side: usize, subs: &mut Vec<Material>| {
+        for piece_type in 0..5 {
+            if material.counts[1 - side][piece_type] > 0 {
+                let mut sub = material;
+                sub.counts[1 - side][piece_type] -= 1;
+                subs.push(sub);
+            }
+        }
+    };
+    for side in 0..2 {
+        captures(*material, side, &mut subs);
+        if material.counts[side][0] > 0 {
+            for promoted in 1..5 {
+                let mut sub = *material;
+                sub.counts[side][0] -= 1;
+                sub.counts[side][promoted] += 1;
+                subs.push(sub);
+                captures(sub, side, &mut subs);
+            }
+        }
+    }
+    subs
+}
+
+fn find<'a>(solved: &'a HashMap<u64, Solved>, material: &Material) -> Option<&'a Solved> {
+    solved.get(&material.key()).or_else(|| solved.get(&material.mirror().key()))
+}
+
+/// Solves the materials reachable from `material`, smallest first.
+fn solve_reachable(material: &Material, solved: &mut HashMap<u64, Solved>) -> Result<(), GenerateError> {
+    for sub in sub_materials(material) {
+        if sub.piece_count() == 2 || find(solved, &sub).is_some() {
+            continue;
+        }
+        solve_reachable(&sub, solved)?;
+        let table = solve(sub, solved)?;
+        solved.insert(sub.key(), table);
+    }
+    Ok(())
+}
+
+/// Piece order and grouping the generator uses, shaped like the official tables:
+/// leading pawns, otherwise up to three unique pieces or the kings, come first.
+fn layout(material: &Material) -> (Layout, [u8; 2]) {
+    let counts = &material.counts;
+    let lead = material.lead_color();
+    let unique = !material.has_pawns() && material.has_unique_pieces();
+    let mut pieces = Vec::new();
+    for _ in 0..counts[lead][0] {
+        pieces.push(piece_code(lead, 0));
+    }
+    if unique {
+        for (side, side_counts) in counts.iter().enumerate() {
+            (1..5).filter(|&t| side_counts[t] == 1).for_each(|t| pieces.push(piece_code(side, t)));
+        }
+    }
+    pieces.push(piece_code(0, 5));
+    pieces.push(piece_code(1, 5));
+    for (side, side_counts) in counts.iter().enumerate() {
+        for piece_type in (1..5).filter(|&t| !(unique && side_counts[t] == 1)) {
+            (0..side_counts[piece_type]).for_each(|_| pieces.push(piece_code(side, piece_type)));
+        }
+    }
+
+    // The leading group is the most significant part of the index
+    let groups = Encoding::new(material, &pieces, [0, 0xF], 0).groups();
+    let order = [groups as u8 - 1, 0xF];
+    let sides = if material.symmetric() { 1 } else { 2 };
+    let mut layout = Layout { material: *material, sides, encodings: Default::default() };
+    for side in 0..2 {
+        for file in 0..layout.files() {
+            layout.encodings[side][file] = Encoding::new(material, &pieces, order, file);
+        }
+    }
+    (layout, order)
+}
+
+/// Board of pieces given as table piece codes, with white to move if `stm` is 0.
+fn board(pieces: &[u8], squares: &[u8], stm: usize) -> Board {
+    let mut letters = [None; 64];
+    for (&piece, &square) in pieces.iter().zip(squares) {
+        let letter = b"PNBRQK"[(piece & 7) as usize - 1] as char;
+        letters[square as usize] = Some(if piece >> 3 == 1 { letter.to_ascii_lowercase() } else { letter });
+    }
+    let mut fen = String::new();
+    for rank in (0..8).rev() {
+        let mut empty = 0;
+        for letter in &letters[rank * 8..rank * 8 + 8] {
+            match letter {
+                Some(letter) => {
+                    if empty > 0 {
+                        fen.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    fen.push(*letter);
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            fen.push_str(&empty.to_string());
+        }
+        if rank > 0 {
+            fen.push('/');
+        }
+    }
+    Board::from_str(&format!("{} {} - - 0 1", fen, if stm == 0 { "w" } else { "b" })).unwrap()
+}
+
+/// Calls `visit` with every placement of `pieces`, equal pieces in ascending order.
+fn place(pieces: &[u8], squares: &mut [u8; TB_PIECES], i: usize, visit: &mut dyn FnMut(&[u8])) {
+    if i == pieces.len() {
+        visit(&squares[..i]);
+        return;
+    }
+    let pawn = pieces[i] & 7 == 1;
+    let mut from = if pawn { 8 } else { 0 };
+    if i > 0 && pieces[i] == pieces[i - 1] {
+        from = from.max(squares[i - 1] + 1);
+    }
+    for square in from..if pawn { 56 } else { 64 } {
+        if !squares[..i].contains(&square) {
+            squares[i] = square;
+            place(pieces, squares, i + 1, visit);
+        }
+    }
+}
+
+/// Result of `board` from a solved smaller material.
+fn external_wdl(board: &Board, solved: &HashMap<u64, Solved>) -> i8 {
+    if board.occupied().count_set() == 2 {
+        return DRAW;
+    }
+    let table = find(solved, &Material::of_board(board)).expect("smaller materials are solved first");
+    table.wdl[table.position(board)]
+}
+
+fn solve(material: Material, solved: &HashMap<u64, Solved>) -> Result<Solved, GenerateError> {
+    let name = material.name();
+    let (layout, _) = layout(&material);
+    let mut offsets = [[0; 4]; 2];
+    let mut total = 0;
+    for (side_offsets, encodings) in offsets.iter_mut().zip(&layout.encodings).take(layout.sides) {
+        for (offset, encoding) in side_offsets.iter_mut().zip(encodings).take(layout.files()) {
+            *offset = total;
+            total += encoding.size() as usize;
+        }
+    }
+
+    // One legal placement for every index, the others stand for illegal
+    // positions or for none at all
+    let pieces = layout.encodings[0][0].pieces[..material.piece_count()].to_vec();
+    let mut placements: Vec<Option<([u8; TB_PIECES], usize)>> = vec![None; total];
+    place(&pieces, &mut [0; TB_PIECES], 0, &mut |squares| {
+        for stm in 0..layout.sides {
+            let (side, file, idx) = layout.index(&pieces, squares, stm);
+            let position = offsets[side][file] + idx as usize;
+            if placements[position].is_none() && board(&pieces, squares, stm).is_legal() {
+                let mut placement = [0; TB_PIECES];
+                placement[..squares.len()].copy_from_slice(squares);
+                placements[position] = Some((placement, stm));
+            }
+        }
+    });
+
+    let mut table = Solved {
+        layout,
+        offsets,
+        legal: placements.iter().map(Option::is_some).collect(),
+        wdl: vec![DRAW; total],
+        dtz: vec![0; total],
+    };
+
+    // Moves of every position: in-table targets by position, others by result
+    let mut first = Vec::with_capacity(total + 1);
+    let mut edges = Vec::new();
+    let mut mated = vec![false; total];
+    let mut wdl = vec![UNKNOWN; total];
+    for (position, placement) in placements.iter().enumerate() {
+        first.push(edges.len());
+        let Some((squares, stm)) = placement else { continue };
+        let mut board = board(&pieces, &squares[..pieces.len()], *stm);
+        let moves = board.generate_all_moves();
+        if moves.is_empty() {
+            mated[position] = !board.state.checker.is_empty();
+            wdl[position] = if mated[position] { LOSS } else { DRAW };
+        }
+        for mv in moves.iter() {
+            let zeroing = if is_zeroing(&board, mv) { ZEROING } else { 0 };
+            board.apply_move(mv);
+            let edge = if Material::of_board(&board) == material {
+                table.position(&board) as u32
+            } else {
+                EXTERNAL | (external_wdl(&board, solved) + 2) as u32
+            };
+            board.undo_move(mv);
+            edges.push(edge | zeroing);
+        }
+    }
+    first.push(edges.len());
+    drop(placements);
+
+    // Child result from the child's side to move
+    let child_wdl = |wdl: &[i8], edge: u32| -> i8 {
+        if edge & EXTERNAL != 0 { (edge & TARGET) as i8 - 2 } else { wdl[(edge & TARGET) as usize] }
+    };
+
+    // WDL: a position wins with a move to a lost one and loses if every move
+    // goes to a won one, whatever is left when nothing changes is a draw
+    let mut unresolved: Vec<usize> = (0..total).filter(|&p| table.legal[p] && wdl[p] == UNKNOWN).collect();
+    loop {
+        let before = unresolved.len();
+        unresolved.retain(|&position| {
+            let children = edges[first[position]..first[position + 1]].iter().map(|&e| child_wdl(&wdl, e));
+            let (mut wins, mut loses) = (false, true);
+            for child in children {
+                wins |= child == LOSS;
+                loses &= child == WIN;
+            }
+            if wins || loses {
+                wdl[position] = if wins { WIN } else { LOSS };
+            }
+            !(wins || loses)
+        });
+        if unresolved.len() == before {
+            break;
+        }
+    }
+    for position in unresolved {
+        wdl[position] = DRAW;
+    }
+
+    // DTZ in rounds: round d settles the wins whose best move needs d plies to
+    // the next zeroing move or mate, and the losses that last d plies at most
+    let mut dtz = vec![0i16; total];
+    for (position, mated) in mated.iter().enumerate() {
+        if *mated {
+            dtz[position] = -1;
+        }
+    }
+    let mut unresolved: Vec<usize> = (0..total).filter(|&p| table.legal[p] && wdl[p] != DRAW && dtz[p] == 0).collect();
+    let mut round = 0i16;
+    while !unresolved.is_empty() {
+        round += 1;
+        if round > 100 {
+            return Err(GenerateError::FiftyMoveRule(name));
+        }
+        let previous = dtz.clone();
+        // Plies of a move if its target is settled before this round
+        let cost = |edge: u32| -> Option<i16> {
+            if edge & (ZEROING | EXTERNAL) != 0 {
+                return Some(1);
+            }
+            let target = (edge & TARGET) as usize;
+            if mated[target] {
+                return Some(1);
+            }
+            let child = previous[target].abs();
+            (child != 0).then_some(child + 1)
+        };
+        unresolved.retain(|&position| {
+            let moves = &edges[first[position]..first[position + 1]];
+            let settled = if wdl[position] == WIN {
+                moves.iter().filter(|&&e| child_wdl(&wdl, e) == LOSS).filter_map(|&e| cost(e)).any(|c| c <= round)
+            } else {
+                moves.iter().all(|&e| cost(e).is_some_and(|c| c <= round))
+            };
+            if settled {
+                dtz[position] = if wdl[position] == WIN { round } else { -round };
+            }
+            !settled
+        });
+    }
+
+    table.wdl = wdl;
+    table.dtz = dtz;
+    Ok(table)
+}
+
+
+/// Sections of one compressed sub-table.
+#[derive(Debug, Default)]
+struct Compressed {
+    sizes: Vec<u8>,
+    sparse_index: Vec<u8>,
+    block_lengths: Vec<u8>,
+    data: Vec<u8>,
+}
+
+impl Compressed {
+    fn len(&self) -> usize {
+        self.sizes.len() + self.sparse_index.len() + self.block_lengths.len() + self.data.len()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Symbol {
+    Value(u16),
+    Pair(usize, usize),
+}
+
+/// Replaces the most frequent pair of adjacent symbols by a new symbol until
+/// no pair is frequent enough. Returns the symbols, the values each stands for
+/// and the sequence of symbols.
+fn pair_symbols(values: &[u16]) -> (Vec<Symbol>, Vec<u32>, Vec<usize>) {
+    let mut distinct = values.to_vec();
+    distinct.sort_unstable();
+    distinct.dedup();
+    let mut symbols: Vec<Symbol> = distinct.iter().map(|&v| Symbol::Value(v)).collect();
+    let mut covered = vec![1; symbols.len()];
+    let mut sequence: Vec<usize> = values.iter().map(|v| distinct.binary_search(v).unwrap()).collect();
+
+    while symbols.len() < MAX_SYMBOLS {
+        let mut counts: HashMap<(usize, usize), u32> = HashMap::new();
+        for pair in sequence.windows(2) {
+            if covered[pair[0]] + covered[pair[1]] <= MAX_SYMBOL_VALUES {
+                *counts.entry((pair[0], pair[1])).or_default() += 1;
+            }
+        }
+        let Some((&(left, right), &count)) = counts.iter().max_by_key(|(pair, count)| (**count, Reverse(**pair))) else {
+            break;
+        };
+        if count < MIN_PAIR_COUNT {
+            break;
+        }
+
+        let symbol = symbols.len();
+        symbols.push(Symbol::Pair(left, right));
+        covered.push(covered[left] + covered[right]);
+        let mut paired = Vec::with_capacity(sequence.len());
+        let mut i = 0;
+        while i < sequence.len() {
+            if i + 1 < sequence.len() && sequence[i] == left && sequence[i + 1] == right {
+                paired.push(symbol);
+                i += 2;
+            } else {
+                paired.push(sequence[i]);
+                i += 1;
+            }
+        }
+        sequence = paired;
+    }
+    (symbols, covered, sequence)
+}
+
+/// Huffman code lengths of at most `MAX_CODE_LEN` bits, 0 for unused symbols.
+fn code_lengths(frequencies: &[u64]) -> Vec<u32> {
+    let mut lengths = vec![0; frequencies.len()];
+    let used: Vec<usize> = (0..frequencies.len()).filter(|&s| frequencies[s] > 0).collect();
+    if used.len() == 1 {
+        lengths[used[0]] = 1;
+        return lengths;
+    }
+    let mut frequencies = frequencies.to_vec();
+    loop {
+        let mut parent = vec![usize::MAX; 2 * frequencies.len()];
+        let mut heap: BinaryHeap<Reverse<(u64, usize)>> = used.iter().map(|&s| Reverse((frequencies[s], s))).collect();
+        let mut node = frequencies.len();
+        while let (Some(Reverse((f1, n1))), Some(Reverse((f2, n2)))) = (heap.pop(), heap.pop()) {
+            parent[n1] = node;
+            parent[n2] = node;
+            heap.push(Reverse((f1 + f2, node)));
+            node += 1;
+        }
+        for &symbol in used.iter() {
+            let (mut length, mut at) = (0, symbol);
+            while parent[at] != usize::MAX {
+                length += 1;
+                at = parent[at];
+            }
+            lengths[symbol] = length;
+        }
+        if lengths.iter().all(|&l| l <= MAX_CODE_LEN) {
+            return lengths;
+        }
+        // Flatten the frequencies until the longest code fits
+        frequencies.iter_mut().filter(|f| **f > 0).for_each(|f| *f = f.div_ceil(2));
+    }
+}
+
+/// Writes `bits` of `code` at `*at` bits into `block`, most significant bit first.
+fn put_bits(block: &mut [u8], at: &mut usize, code: u64, bits: u32) {
+    for bit in (0..bits).rev() {
+        if code >> bit & 1 != 0 {
+            block[*at / 8] |= 0x80 >> (*at % 8);
+        }
+        *at += 1;
+    }
+}
+
+fn compress(values: &[u16], flags: u8) -> Compressed {
+    if values.iter().all(|&v| v == values[0]) {
+        return Compressed { sizes: vec![flags | SINGLE_VALUE, values[0] as u8], ..Compressed::default() };
+    }
+    let (symbols, covered, sequence) = pair_symbols(values);
+    let mut frequencies = vec![0u64; symbols.len()];
+    sequence.iter().for_each(|&s| frequencies[s] += 1);
+    let lengths = code_lengths(&frequencies);
+
+    // Ids go to the longest codes first, symbols without a code come last
+    let mut by_id: Vec<usize> = (0..symbols.len()).collect();
+    by_id.sort_by_key(|&s| (lengths[s] == 0, Reverse(lengths[s]), s));
+    let mut ids = vec![0; symbols.len()];
+    by_id.iter().enumerate().for_each(|(id, &s)| ids[s] = id);
+
+    // Canonical codes: the longest start at 0, shorter codes are numerically larger
+    let max_len = *lengths.iter().max().unwrap();
+    let min_len = *lengths.iter().filter(|&&l| l > 0).min().unwrap();
+    let mut per_length = vec![0u64; max_len as usize + 2];
+    lengths.iter().filter(|&&l| l > 0).for_each(|&l| per_length[l as usize] += 1);
+    let mut first_code = vec![0u64; max_len as usize + 2];
+    let mut lowest_id = vec![0u64; max_len as usize + 2];
+    for len in (min_len as usize..max_len as usize).rev() {
+        first_code[len] = (first_code[len + 1] + per_length[len + 1]) / 2;
+        lowest_id[len] = lowest_id[len + 1] + per_length[len + 1];
+    }
+    let mut codes = vec![0u64; symbols.len()];
+    for &symbol in by_id.iter().filter(|&&s| lengths[s] > 0) {
+        let len = lengths[symbol] as usize;
+        codes[symbol] = first_code[len] + ids[symbol] as u64 - lowest_id[len];
+    }
+
+    let mut sizes = vec![flags, BLOCK_SIZE_LOG2, SPAN_LOG2, 1];
+    let mut data = Vec::new();
+    let mut block_values = Vec::new();
+    let block_size = 1usize << BLOCK_SIZE_LOG2;
+    let (mut block, mut bits, mut count) = (vec![0u8; block_size], 0, 0);
+    for &symbol in sequence.iter() {
+        if bits + lengths[symbol] as usize > 8 * block_size || count + covered[symbol] > MAX_BLOCK_VALUES {
+            data.append(&mut block);
+            block = vec![0u8; block_size];
+            block_values.push(count);
+            (bits, count) = (0, 0);
+        }
+        put_bits(&mut block, &mut bits, codes[symbol], lengths[symbol]);
+        count += covered[symbol];
+    }
+    data.append(&mut block);
+    block_values.push(count);
+
+    sizes.extend_from_slice(&(block_values.len() as u32).to_le_bytes());
+    sizes.extend_from_slice(&[max_len as u8, min_len as u8]);
+    for lowest in &lowest_id[min_len as usize..=max_len as usize] {
+        sizes.extend_from_slice(&(*lowest as u16).to_le_bytes());
+    }
+    sizes.extend_from_slice(&(symbols.len() as u16).to_le_bytes());
+    for &symbol in by_id.iter() {
+        let (left, right) = match symbols[symbol] {
+            Symbol::Value(value) => (value as usize, LEAF as usize),
+            Symbol::Pair(left, right) => (ids[left], ids[right]),
+        };
+        sizes.extend_from_slice(&[left as u8, (left >> 8) as u8 | (right << 4) as u8, (right >> 4) as u8]);
+    }
+    if symbols.len() % 2 == 1 {
+        sizes.push(0);
+    }
+
+    // One padding block after the last, for the index entries past the end
+    let mut block_lengths = Vec::new();
+    for values in block_values.iter() {
+        block_lengths.extend_from_slice(&((*values - 1) as u16).to_le_bytes());
+    }
+    block_lengths.extend_from_slice(&0u16.to_le_bytes());
+
+    // Every entry points to the value in the middle of its span
+    let mut starts = vec![0u64];
+    block_values.iter().for_each(|&v| starts.push(starts.last().unwrap() + v as u64));
+    let span = 1u64 << SPAN_LOG2;
+    let mut sparse_index = Vec::new();
+    for k in 0..(values.len() as u64).div_ceil(span) {
+        let middle = k * span + span / 2;
+        let (block, offset) = if middle >= values.len() as u64 {
+            (block_values.len(), middle - values.len() as u64)
+        } else {
+            let block = starts.partition_point(|&start| start <= middle) - 1;
+            (block, middle - starts[block])
+        };
+        sparse_index.extend_from_slice(&(block as u32).to_le_bytes());
+        sparse_index.extend_from_slice(&(offset as u16).to_le_bytes());
+    }
+    Compressed { sizes, sparse_index, block_lengths, data }
+}
+
+
+/// Values of a sub-table, `value` of the legal positions and the previous
+/// value in between so those compress away.
+fn sub_table_values(table: &Solved, side: usize, file: usize, value: impl Fn(usize) -> Option<u16>) -> Vec<u16> {
+    let mut previous = 0;
+    table
+        .range(side, file)
+        .map(|position| {
+            if table.legal[position] && let Some(v) = value(position) {
+                previous = v;
+            }
+            previous
+        })
+        .collect()
+}
+
+fn header(kind: TableKind, layout: &Layout, order: [u8; 2]) -> Vec<u8> {
+    let material = &layout.material;
+    let mut bytes = kind.magic().to_vec();
+    bytes.push(if material.symmetric() { 0 } else { SPLIT } | if material.has_pawns() { HAS_PAWNS } else { 0 });
+    for file in 0..layout.files() {
+        bytes.push(order[0] | order[0] << 4);
+        for &piece in &layout.encodings[0][file].pieces[..material.piece_count()] {
+            bytes.push(piece | piece << 4);
+        }
+    }
+    bytes.resize(bytes.len().next_multiple_of(2), 0);
+    bytes
+}
+
+/// Joins the header, the sub-tables in file order and the DTZ value maps.
+fn assemble(mut bytes: Vec<u8>, parts: &[Compressed], map: &[u8]) -> Vec<u8> {
+    parts.iter().for_each(|p| bytes.extend_from_slice(&p.sizes));
+    bytes.extend_from_slice(map);
+    bytes.resize(bytes.len().next_multiple_of(2), 0);
+    parts.iter().for_each(|p| bytes.extend_from_slice(&p.sparse_index));
+    parts.iter().for_each(|p| bytes.extend_from_slice(&p.block_lengths));
+    for part in parts {
+        bytes.resize(bytes.len().next_multiple_of(64), 0);
+        bytes.extend_from_slice(&part.data);
+    }
+    bytes
+}
+
+fn write_wdl(table: &Solved) -> Vec<u8> {
+    let (_, order) = layout(&table.layout.material);
+    let mut parts = Vec::new();
+    for file in 0..table.layout.files() {
+        for side in 0..table.layout.sides {
+            let values = sub_table_values(table, side, file, |p| Some((table.wdl[p] + 2) as u16));
+            parts.push(compress(&values, 0));
+        }
+    }
+    assemble(header(TableKind::Wdl, &table.layout, order), &parts, &[])
+}
+
+/// Distinct values of `plies`, most frequent first.
+fn frequency_order(plies: impl Iterator<Item = u16>) -> Vec<u16> {
+    let mut counts: HashMap<u16, usize> = HashMap::new();
+    plies.for_each(|p| *counts.entry(p).or_default() += 1);
+    let mut order: Vec<u16> = counts.keys().copied().collect();
+    order.sort_by_key(|p| (Reverse(counts[p]), *p));
+    order
+}
+
+/// DTZ tables store one side to move per file, whichever compresses better.
+/// Values are plies minus one, mapped through a list per result.
+fn write_dtz(table: &Solved) -> Vec<u8> {
+    let (_, order) = layout(&table.layout.material);
+    let mut parts = Vec::new();
+    let mut map = Vec::new();
+    for file in 0..table.layout.files() {
+        let mut best: Option<(Compressed, Vec<u8>)> = None;
+        for side in 0..table.layout.sides {
+            let positions = || table.range(side, file).filter(|&p| table.legal[p] && table.wdl[p] != DRAW);
+            let plies = |p: usize| table.dtz[p].unsigned_abs() - 1;
+            let wins = frequency_order(positions().filter(|&p| table.wdl[p] == WIN).map(plies));
+            let losses = frequency_order(positions().filter(|&p| table.wdl[p] == LOSS).map(plies));
+            let values = sub_table_values(table, side, file, |p| {
+                let list = match table.wdl[p] {
+                    WIN => &wins,
+                    LOSS => &losses,
+                    _ => return None,
+                };
+                list.iter().position(|&v| v == plies(p)).map(|i| i as u16)
+            });
+
+            let flags = (side as u8 * STM) | MAPPED | WIN_PLIES | LOSS_PLIES;
+            let compressed = compress(&values, flags);
+            let mut lists = Vec::new();
+            for list in [&wins, &losses, &Vec::new(), &Vec::new()] {
+                lists.push(list.len() as u8);
+                lists.extend(list.iter().map(|&v| v as u8));
+            }
+            if best.as_ref().is_none_or(|(b, _)| compressed.len() < b.len()) {
+                best = Some((compressed, lists));
+            }
+        }
+        let (compressed, lists) = best.unwrap();
+        parts.push(compressed);
+        map.extend(lists);
+    }
+    assemble(header(TableKind::Dtz, &table.layout, order), &parts, &map)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_unsupported_materials() {
+        assert!(matches!(generate("KvK"), Err(GenerateError::InvalidName(_))));
+        assert!(matches!(generate("KQRBvKN"), Err(GenerateError::InvalidName(_))));
+        assert!(matches!(generate("KPvKP"), Err(GenerateError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_layout_puts_the_leading_group_first() {
+        let pieces = |name: &str| {
+            let material = Material::from_name(name).unwrap();
+            let (layout, order) = layout(&material);
+            (layout.encodings[0][0].pieces[..material.piece_count()].to_vec(), order[0])
+        };
+        assert_eq!(pieces("KQvK"), (vec![5, 6, 14], 0));
+        assert_eq!(pieces("KNNvK"), (vec![6, 14, 2, 2], 1));
+        assert_eq!(pieces("KPvK"), (vec![1, 6, 14], 2));
+    }
+}
@@ -0,0 +1,627 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::types::board::board::Board;
+use crate::types::moves::Move;
+use crate::types::piece::PieceType;
+
+mod table;
+#[cfg(any(test, feature = "syzygy-generator"))]
+pub mod generator;
+
+use table::{Dtz, Material, Table, TableKind, TB_PIECES};
+
+/// Score of a tablebase win at the root, a win found `ply` plies deep scores `TB_WIN - ply`.
+/// Below mate scores and small enough to survive the 16 bit scores of the TT.
+pub const TB_WIN: i32 = 20_000;
+/// Deepest ply a tablebase score is reported for, everything above is a TB score.
+pub const TB_MAX_PLY: i32 = 1_000;
+
+const WDL_SUFFIX: &str = "rtbw";
+const DTZ_SUFFIX: &str = "rtbz";
+/// Piece letters in the order Syzygy file names use them.
+const NAME_ORDER: [(char, usize); 6] = [('K', 5), ('Q', 4), ('R', 3), ('B', 2), ('N', 1), ('P', 0)];
+
+
+/// Win/draw/loss from the point of view of the side to move. Cursed wins and
+/// blessed losses are wins and losses that the fifty move rule turns into draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    pub fn from_i8(value: i8) -> Option<Wdl> {
+        match value {
+            -2 => Some(Wdl::Loss),
+            -1 => Some(Wdl::BlessedLoss),
+            0 => Some(Wdl::Draw),
+            1 => Some(Wdl::CursedWin),
+            2 => Some(Wdl::Win),
+            _ => None,
+        }
+    }
+
+    /// The same result seen from the other side.
+    pub fn invert(self) -> Wdl {
+        Wdl::from_i8(-(self as i8)).unwrap()
+    }
+
+    /// Search score of this result `ply` plies from the root. Results the fifty move
+    /// rule decides are scored just next to a draw.
+    pub fn to_score(self, ply: i32) -> i32 {
+        match self {
+            Wdl::Win => TB_WIN - ply,
+            Wdl::CursedWin => 1,
+            Wdl::Draw => 0,
+            Wdl::BlessedLoss => -1,
+            Wdl::Loss => -TB_WIN + ply,
+        }
+    }
+
+    /// DTZ of a position whose best move zeroes, counting that move.
+    fn dtz_before_zeroing(self) -> i32 {
+        match self {
+            Wdl::Win => 1,
+            Wdl::CursedWin => 101,
+            Wdl::Draw => 0,
+            Wdl::BlessedLoss => -101,
+            Wdl::Loss => -1,
+        }
+    }
+}
+
+/// Source of endgame tablebase results. Probes may play moves on `board` but
+/// leave it as it was.
+pub trait Tablebase: Send + Sync {
+    /// Largest number of pieces, kings included, that can be probed.
+    fn max_pieces(&self) -> u32;
+
+    /// Result of `board` with perfect play, `None` if it is not covered.
+    fn probe_wdl(&self, board: &mut Board) -> Option<Wdl>;
+
+    /// Distance to the next zeroing move (capture or pawn move) with perfect play,
+    /// positive if the side to move wins and negative if it loses, 0 for draws.
+    fn probe_dtz(&self, board: &mut Board) -> Option<i32>;
+}
+
+
+#[derive(Debug)]
+pub enum SyzygyError {
+    Io(io::Error),
+    InvalidMagic(PathBuf),
+    InvalidName(PathBuf),
+    Corrupt(PathBuf),
+}
+
+impl fmt::Display for SyzygyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyzygyError::Io(e) => write!(f, "{}", e),
+            SyzygyError::InvalidMagic(path) => write!(f, "{} is not a Syzygy table", path.display()),
+            SyzygyError::InvalidName(path) => write!(f, "{} is not named after its material", path.display()),
+            SyzygyError::Corrupt(path) => write!(f, "{} does not match the Syzygy format", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for SyzygyError {}
+
+impl From<io::Error> for SyzygyError {
+    fn from(e: io::Error) -> Self {
+        SyzygyError::Io(e)
+    }
+}
+
+/// Syzygy name of the material of `board`, like `KRPvKR`, white first if `white_first`.
+pub fn material_name(board: &Board, white_first: bool) -> String {
+    let material = Material::of_board(board);
+    if white_first { material.name() } else { material.mirror().name() }
+}
+
+fn is_zeroing(board: &Board, mv: &Move) -> bool {
+    mv.is_capture() || board.mailbox[mv.from() as usize].piece_type() == PieceType::Pawn
+}
+
+#[derive(Debug, Default)]
+struct TableFiles {
+    wdl: Option<Table>,
+    dtz: Option<Table>,
+}
+
+/// Syzygy `.rtbw`/`.rtbz` files found in the directories of a `SyzygyPath`.
+///
+/// Files are memory mapped and their headers parsed when opened, values are
+/// decompressed on demand. Positions with castling rights are not covered.
+#[derive(Debug, Default)]
+pub struct SyzygyTablebase {
+    /// By material key, each table is found under the key of its name.
+    tables: HashMap<u64, TableFiles>,
+    max_pieces: u32,
+}
+
+impl SyzygyTablebase {
+    /// Opens every table in `paths`, directories separated by `:` (`;` on Windows).
+    /// Returns the tablebase and the files that were skipped with the reason.
+    pub fn open(paths: &str) -> (Self, Vec<SyzygyError>) {
+        let separator = if cfg!(windows) { ';' } else { ':' };
+        let mut tablebase = SyzygyTablebase::default();
+        let mut errors = Vec::new();
+
+        for dir in paths.split(separator).filter(|p| !p.is_empty()) {
+            let entries = match fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    errors.push(e.into());
+                    continue;
+                }
+            };
+            let mut files: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path())).collect();
+            files.sort();
+            for path in files {
+                if let Err(e) = tablebase.add_file(&path) {
+                    errors.push(e);
+                }
+            }
+        }
+        (tablebase, errors)
+    }
+
+    fn add_file(&mut self, path: &Path) -> Result<(), SyzygyError> {
+        let kind = match path.extension().and_then(|e| e.to_str()) {
+            Some(WDL_SUFFIX) => TableKind::Wdl,
+            Some(DTZ_SUFFIX) => TableKind::Dtz,
+            _ => return Ok(()),
+        };
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        let material = Material::from_name(name)
+            .filter(|m| m.piece_count() <= TB_PIECES)
+            .ok_or_else(|| SyzygyError::InvalidName(path.to_path_buf()))?;
+
+        let table = Table::open(path, material, kind)?;
+        let files = self.tables.entry(material.key()).or_default();
+        if kind == TableKind::Wdl { files.wdl = Some(table) } else { files.dtz = Some(table) }
+        self.max_pieces = self.max_pieces.max(material.piece_count() as u32);
+        Ok(())
+    }
+
+    /// Number of WDL and DTZ tables found.
+    pub fn num_tables(&self) -> (usize, usize) {
+        let wdl = self.tables.values().filter(|t| t.wdl.is_some()).count();
+        let dtz = self.tables.values().filter(|t| t.dtz.is_some()).count();
+        (wdl, dtz)
+    }
+
+    /// Files of the material of `board`, tables exist for one color order only.
+    fn files(&self, board: &Board) -> Option<&TableFiles> {
+        let material = Material::of_board(board);
+        self.tables.get(&material.key()).or_else(|| self.tables.get(&material.mirror().key()))
+    }
+
+    /// Stored WDL of `board`, two bare kings have no table.
+    fn table_wdl(&self, board: &Board) -> Option<Wdl> {
+        if board.occupied().count_set() == 2 {
+            return Some(Wdl::Draw);
+        }
+        self.files(board)?.wdl.as_ref()?.wdl(board)
+    }
+
+    /// Result of `board` and whether a zeroing move is best. Tables store any
+    /// value where a capture, or with `zeroing` also a pawn move, is at least as
+    /// good, so those moves are searched first.
+    fn search(&self, board: &mut Board, zeroing: bool) -> Option<(Wdl, bool)> {
+        let moves = board.generate_all_moves();
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+
+        for mv in moves.iter() {
+            let searched_move = if zeroing { is_zeroing(board, mv) } else { mv.is_capture() };
+            if !searched_move {
+                continue;
+            }
+            searched += 1;
+            board.apply_move(mv);
+            let child = self.search(board, false);
+            board.undo_move(mv);
+            let (wdl, _) = child?;
+
+            let wdl = wdl.invert();
+            if wdl > best {
+                best = wdl;
+                if wdl == Wdl::Win {
+                    return Some((wdl, true));
+                }
+            }
+        }
+
+        // With every move searched the table is not needed, it would be wrong
+        // for an en passant capture the index does not know about
+        let all_searched = searched > 0 && searched == moves.len();
+        let stored = if all_searched { best } else { self.table_wdl(board)? };
+        if best >= stored {
+            Some((best, best > Wdl::Draw || all_searched))
+        } else {
+            Some((stored, false))
+        }
+    }
+}
+
+impl Tablebase for SyzygyTablebase {
+    fn max_pieces(&self) -> u32 {
+        self.max_pieces
+    }
+
+    fn probe_wdl(&self, board: &mut Board) -> Option<Wdl> {
+        self.search(board, false).map(|(wdl, _)| wdl)
+    }
+
+    fn probe_dtz(&self, board: &mut Board) -> Option<i32> {
+        let (wdl, zeroing_best) = self.search(board, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing_best {
+            return Some(wdl.dtz_before_zeroing());
+        }
+        let sign = (wdl as i32).signum();
+        let table = self.files(board)?.dtz.as_ref()?;
+        if let Dtz::Plies(plies) = table.dtz(board, wdl)? {
+            let cursed = matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss);
+            return Some((plies + if cursed { 100 } else { 0 }) * sign);
+        }
+
+        // The table only has the other side to move, take the best move's DTZ
+        let mut best = 0xFFFF;
+        for mv in board.generate_all_moves().iter() {
+            let zeroing = is_zeroing(board, mv);
+            board.apply_move(mv);
+            let child = if zeroing {
+                self.search(board, false).map(|(wdl, _)| -wdl.dtz_before_zeroing())
+            } else {
+                self.probe_dtz(board).map(|dtz| -dtz)
+            };
+            let mates = child == Some(1) && board.generate_all_moves().is_empty() && !board.state.checker.is_empty();
+            board.undo_move(mv);
+            let mut dtz = child?;
+
+            if mates {
+                best = 1;
+            }
+            // Zeroing moves already count themselves
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz < best && dtz.signum() == sign {
+                best = dtz;
+            }
+        }
+        // Without moves the side to move is mated
+        Some(if best == 0xFFFF { -1 } else { best })
+    }
+}
+
+
+/// Whether `board` is small enough for `tablebase`. Tables do not know castling.
+pub fn probeable(tablebase: &dyn Tablebase, board: &Board) -> bool {
+    board.occupied().count_set() <= tablebase.max_pieces() && board.state.castling.is_empty()
+}
+
+/// Root moves that keep the best result the fifty move rule still allows, and that
+/// result, filtered by DTZ like the reference probers do. Winning moves must zero
+/// in time; while the clock leaves room every such move is kept, so the search can
+/// pick among them. Losing moves are all kept until the clock gets close, then only
+/// the ones that hold out longest. `None` if the root or any move cannot be probed.
+pub fn filter_root_moves(tablebase: &dyn Tablebase, board: &Board) -> Option<(Vec<Move>, Wdl)> {
+    if !probeable(tablebase, board) {
+        return None;
+    }
+    let mut board = board.clone();
+    let clock = board.state.halfmove_clock as i32;
+    let moves = board.generate_all_moves();
+    let mut ranked = Vec::with_capacity(moves.len());
+
+    for mv in moves.iter() {
+        let zeroing = is_zeroing(&board, mv);
+        board.apply_move(mv);
+        // DTZ of the root side counting this move, a move that ends the game has no table entry
+        let dtz = if board.generate_all_moves().is_empty() {
+            Some(if board.state.checker.is_empty() { 0 } else { 1 })
+        } else if zeroing {
+            tablebase.probe_wdl(&mut board).map(|wdl| wdl.invert().dtz_before_zeroing())
+        } else {
+            tablebase.probe_dtz(&mut board).map(|dtz| -dtz - dtz.signum())
+        };
+        board.undo_move(mv);
+        ranked.push((*mv, dtz?));
+    }
+
+    let keep = |ranked: &[(Move, i32)], keep: &dyn Fn(i32) -> bool| -> Vec<Move> {
+        ranked.iter().filter(|(_, dtz)| keep(*dtz)).map(|(mv, _)| *mv).collect()
+    };
+    if let Some(best) = ranked.iter().map(|(_, dtz)| *dtz).filter(|&dtz| dtz > 0).min() {
+        // Tables may round DTZ up by one ply, so wins keep a ply of margin
+        let max = if best + clock <= 99 { 99 - clock } else { best };
+        let wdl = if best + clock <= 100 { Wdl::Win } else { Wdl::CursedWin };
+        Some((keep(&ranked, &|dtz| dtz > 0 && dtz <= max), wdl))
+    } else if ranked.iter().any(|(_, dtz)| *dtz == 0) {
+        Some((keep(&ranked, &|dtz| dtz == 0), Wdl::Draw))
+    } else {
+        let best = ranked.iter().map(|(_, dtz)| *dtz).min()?;
+        let wdl = if -best + clock <= 100 { Wdl::Loss } else { Wdl::BlessedLoss };
+        if -best * 2 + clock < 100 {
+            Some((keep(&ranked, &|_| true), wdl))
+        } else {
+            Some((keep(&ranked, &|dtz| dtz == best), wdl))
+        }
+    }
+}
+
+
+
+
+/// ######################################################
+/// ################### TESTING ##########################
+/// ######################################################
+
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::types::color::Color;
+    use crate::types::piece::Piece;
+    use std::fs::File;
+    use std::io::Write;
+    use std::str::FromStr;
+
+    /// Knows KRvK and KvK: the side with the rook wins, the distance is the
+    /// distance of the losing king to the corner it is closest to.
+    pub(crate) struct RookEndings;
+
+    impl RookEndings {
+        fn material(board: &Board) -> Option<bool> {
+            match (material_name(board, true).as_str(), material_name(board, false).as_str()) {
+                ("KvK", _) => Some(false),
+                ("KRvK", _) | (_, "KRvK") => Some(true),
+                _ => None,
+            }
+        }
+    }
+
+    impl Tablebase for RookEndings {
+        fn max_pieces(&self) -> u32 {
+            3
+        }
+
+        fn probe_wdl(&self, board: &mut Board) -> Option<Wdl> {
+            if !Self::material(board)? {
+                return Some(Wdl::Draw);
+            }
+            let rooks = board.pieces[Piece::WhiteRook as usize] | board.pieces[Piece::BlackRook as usize];
+            let rook_side = if rooks.is_empty() { return None } else if board.pieces[Piece::WhiteRook as usize].is_empty() { 1 } else { 0 };
+            Some(if board.state.color as usize == rook_side { Wdl::Win } else { Wdl::Loss })
+        }
+
+        fn probe_dtz(&self, board: &mut Board) -> Option<i32> {
+            let wdl = self.probe_wdl(board)?;
+            if wdl == Wdl::Draw {
+                return Some(0);
+            }
+            let losing_king = if board.pieces[Piece::WhiteRook as usize].is_empty() { Piece::WhiteKing } else { Piece::BlackKing };
+            let king = board.pieces[losing_king as usize].lsb();
+            let corner = (king.file().min(7 - king.file())).max(king.rank().min(7 - king.rank())) as i32 + 1;
+            Some(if wdl == Wdl::Win { corner } else { -corner })
+        }
+    }
+
+    #[test]
+    fn test_material_name() {
+        let board = Board::from_str("8/8/4k3/2r5/8/1P6/2Q5/K7 w - - 0 1").unwrap();
+        assert_eq!(material_name(&board, true), "KQPvKR");
+        assert_eq!(material_name(&board, false), "KRvKQP");
+        let material = Material::from_name("KQPvKR").unwrap();
+        assert_eq!(material, Material::of_board(&board));
+        assert_eq!(material.piece_count(), 5);
+        assert_eq!(material.mirror().name(), "KRvKQP");
+        assert_eq!(Material::from_name("KQvX"), None);
+        assert_eq!(Material::from_name("KQK"), None);
+        assert_eq!(Material::from_name("QvK"), None);
+    }
+
+    #[test]
+    fn test_scores_and_inversion() {
+        assert_eq!(Wdl::Win.invert(), Wdl::Loss);
+        assert_eq!(Wdl::CursedWin.invert(), Wdl::BlessedLoss);
+        assert_eq!(Wdl::Win.to_score(3), TB_WIN - 3);
+        assert_eq!(Wdl::Loss.to_score(3), -TB_WIN + 3);
+        assert!(Wdl::Win.to_score(TB_MAX_PLY) > TB_WIN - TB_MAX_PLY - 1);
+        assert_eq!(Wdl::Draw.to_score(5), 0);
+    }
+
+    #[test]
+    fn test_open_finds_and_checks_tables() {
+        let dir = std::env::temp_dir().join(format!("clockwork_syzygy_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, bytes: &[u8]| File::create(dir.join(name)).unwrap().write_all(bytes).unwrap();
+        let fixture = |name: &str| fs::read(Path::new(FIXTURES).join(name)).unwrap();
+        write("KQvK.rtbw", &fixture("KQvK.rtbw"));
+        write("KQvK.rtbz", &fixture("KQvK.rtbz"));
+        write("KRPvKR.rtbw", &[table::WDL_MAGIC.as_slice(), &[0; 60]].concat());
+        write("KPvK.rtbw", &fixture("KPvK.rtbz"));
+        write("readme.txt", b"not a table");
+
+        let (tablebase, errors) = SyzygyTablebase::open(&format!("{}:/nonexistent/syzygy", dir.display()));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(tablebase.num_tables(), (1, 1));
+        assert_eq!(tablebase.max_pieces(), 3);
+        assert_eq!(errors.len(), 3);
+        assert!(errors.iter().any(|e| matches!(e, SyzygyError::InvalidMagic(path) if path.ends_with("KPvK.rtbw"))));
+        assert!(errors.iter().any(|e| matches!(e, SyzygyError::Corrupt(path) if path.ends_with("KRPvKR.rtbw"))));
+        assert!(errors.iter().any(|e| matches!(e, SyzygyError::Io(_))));
+
+        let board = Board::from_str("8/8/8/8/8/8/1q6/k6K b - - 0 1").unwrap();
+        assert!(tablebase.files(&board).is_some_and(|files| files.wdl.is_some() && files.dtz.is_some()));
+    }
+
+    /// Tables written by `generator`, see the `gen_syzygy` binary. They only show
+    /// the reader agrees with the generator, see the official tables test for more.
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/syzygy");
+
+    fn fixtures() -> SyzygyTablebase {
+        let (tablebase, errors) = SyzygyTablebase::open(FIXTURES);
+        assert!(errors.is_empty(), "{:?}", errors);
+        tablebase
+    }
+
+    /// Random legal positions of the material `name`.
+    fn random_positions(name: &str, count: usize, seed: u64) -> Vec<Board> {
+        use rand::{Rng, SeedableRng, rngs::StdRng};
+        let material = Material::from_name(name).unwrap();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut boards = Vec::new();
+        while boards.len() < count {
+            let mut board = Board::from_str("8/8/8/8/8/8/8/8 w - - 0 1").unwrap();
+            for (piece, count) in material.counts.iter().flatten().enumerate() {
+                for _ in 0..*count {
+                    let squares = if piece % 6 == 0 { 8..56 } else { 0..64 };
+                    let square = std::iter::repeat_with(|| rng.random_range(squares.clone()))
+                        .find(|&s| !board.occupied().is_set(s))
+                        .unwrap();
+                    board.set_piece(Piece::try_from(piece).unwrap(), square);
+                }
+            }
+            board.state.color = if rng.random() { Color::White } else { Color::Black };
+            if board.is_legal() {
+                boards.push(board);
+            }
+        }
+        boards
+    }
+
+    #[test]
+    fn test_fixture_probes_known_positions() {
+        let tablebase = fixtures();
+        assert_eq!(tablebase.num_tables(), (7, 7));
+        assert_eq!(tablebase.max_pieces(), 4);
+        for (fen, wdl, dtz) in [
+            // Qb8 mates, also with the colors swapped
+            ("7k/8/6K1/8/8/8/8/1Q6 w - - 0 1", Wdl::Win, Some(1)),
+            ("7K/8/6k1/8/8/8/8/1q6 b - - 0 1", Wdl::Win, Some(1)),
+            ("7k/8/6K1/8/8/8/8/1Q6 b - - 0 1", Wdl::Loss, None),
+            // The queen hangs
+            ("8/8/8/8/8/8/1Qk5/7K b - - 0 1", Wdl::Draw, Some(0)),
+            ("8/8/8/8/8/2k5/8/R3K3 w - - 0 1", Wdl::Win, None),
+            ("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1", Wdl::Win, None),
+            ("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1", Wdl::Loss, None),
+            ("8/8/8/8/8/4k3/4P3/4K3 w - - 0 1", Wdl::Draw, Some(0)),
+            ("k7/8/8/8/P7/8/8/K7 w - - 0 1", Wdl::Draw, Some(0)),
+            ("8/8/8/4k3/8/8/8/KNN5 w - - 0 1", Wdl::Draw, Some(0)),
+            ("8/8/8/4k3/8/8/8/KN4n1 b - - 0 1", Wdl::Draw, Some(0)),
+            ("8/8/8/4k3/8/8/8/KB6 w - - 0 1", Wdl::Draw, Some(0)),
+        ] {
+            let mut board = Board::from_str(fen).unwrap();
+            assert_eq!(tablebase.probe_wdl(&mut board), Some(wdl), "{}", fen);
+            let probed = tablebase.probe_dtz(&mut board).unwrap();
+            assert_eq!(probed.signum(), (wdl as i32).signum(), "{}", fen);
+            assert!(dtz.is_none_or(|dtz| dtz == probed), "{} has DTZ {}", fen, probed);
+            assert_eq!(board.to_fen(), Board::from_str(fen).unwrap().to_fen());
+        }
+
+        // Material without a table
+        let mut board = Board::from_str("8/8/8/4k3/8/8/8/KQR5 w - - 0 1").unwrap();
+        assert_eq!(tablebase.probe_wdl(&mut board), None);
+    }
+
+    /// Official tables decode to the results of the retrograde analysis itself, which
+    /// never went through the generator's encoder. Run it with the official tables
+    /// of 3 pieces in `SYZYGY_PATH`: `cargo test official -- --ignored`.
+    #[test]
+    #[ignore = "needs the official KQvK, KRvK and KPvK tables in SYZYGY_PATH"]
+    fn test_official_tables_match_retrograde_analysis() {
+        let path = std::env::var("SYZYGY_PATH").expect("SYZYGY_PATH names the official tables");
+        let (tablebase, _) = SyzygyTablebase::open(&path);
+        assert!(tablebase.max_pieces() >= 3, "no tables in {}", path);
+        for (seed, name) in ["KQvK", "KRvK", "KPvK"].iter().enumerate() {
+            let solution = super::generator::Solution::new(name).unwrap();
+            for mut board in random_positions(name, 2000, seed as u64) {
+                let fen = board.to_fen();
+                let (wdl, dtz) = solution.probe(&board).unwrap();
+                assert_eq!(tablebase.probe_wdl(&mut board), Some(wdl), "{}", fen);
+                // Official DTZ tables may store some values rounded up by one ply
+                let probed = tablebase.probe_dtz(&mut board).unwrap();
+                assert!(probed.signum() == dtz.signum() && (probed - dtz).abs() <= 1, "{}: {} for {}", fen, probed, dtz);
+            }
+        }
+    }
+
+    #[test]
+    fn test_root_moves_keep_the_result_the_clock_allows() {
+        let tablebase = fixtures();
+        let fen = |clock: i32| format!("8/8/8/8/8/8/1k6/R3K3 w - - {} 1", clock);
+        let mut board = Board::from_str(&fen(0)).unwrap();
+        let best = tablebase.probe_dtz(&mut board).unwrap();
+        let moves = board.generate_all_moves();
+
+        // A fresh clock keeps every winning move, losing the rook is not one of them
+        let (kept, wdl) = filter_root_moves(&tablebase, &board).unwrap();
+        assert_eq!(wdl, Wdl::Win);
+        assert!(kept.len() > 1 && kept.len() < moves.len());
+        assert!(!kept.iter().any(|mv| mv.to_lan() == "a1a2"));
+
+        // With the clock just allowing the fastest win only the fastest moves are left
+        let board = Board::from_str(&fen(99 - best)).unwrap();
+        let (fastest, wdl) = filter_root_moves(&tablebase, &board).unwrap();
+        assert_eq!(wdl, Wdl::Win);
+        assert!(fastest.len() < kept.len() && fastest.iter().all(|mv| kept.contains(mv)));
+        for mv in fastest.iter() {
+            let mut child = board.clone();
+            child.apply_move(mv);
+            assert_eq!(tablebase.probe_dtz(&mut child), Some(-(best - 1)), "{}", mv.to_lan());
+        }
+
+        // Later the win comes too late and is a draw by the fifty move rule
+        let board = Board::from_str(&fen(101 - best)).unwrap();
+        assert_eq!(filter_root_moves(&tablebase, &board).unwrap(), (fastest, Wdl::CursedWin));
+    }
+
+    #[test]
+    fn test_fixture_probes_agree_with_their_moves() {
+        let tablebase = fixtures();
+        for (seed, name) in ["KQvK", "KRvK", "KPvK", "KNvK", "KNNvK", "KNvKN"].iter().enumerate() {
+            for mut board in random_positions(name, 150, seed as u64) {
+                let fen = board.to_fen();
+                let wdl = tablebase.probe_wdl(&mut board).unwrap();
+                let dtz = tablebase.probe_dtz(&mut board).unwrap();
+
+                // Wins take the fastest way to a zeroing move or mate, losses the slowest
+                let moves = board.generate_all_moves();
+                let in_check = !board.state.checker.is_empty();
+                let rank = |(wdl, plies): (Wdl, i32)| (wdl, if wdl > Wdl::Draw { -plies } else { plies });
+                let mut best: Option<(Wdl, i32)> = None;
+                for mv in moves.iter() {
+                    let zeroing = is_zeroing(&board, mv);
+                    board.apply_move(mv);
+                    let mates = board.generate_all_moves().is_empty() && !board.state.checker.is_empty();
+                    let child = tablebase.probe_wdl(&mut board).unwrap().invert();
+                    let plies = if zeroing || mates { 1 } else { tablebase.probe_dtz(&mut board).unwrap().abs() + 1 };
+                    board.undo_move(mv);
+                    if best.is_none_or(|best| rank((child, plies)) > rank(best)) {
+                        best = Some((child, plies));
+                    }
+                }
+                let expected = match best {
+                    None if in_check => (Wdl::Loss, -1),
+                    None | Some((Wdl::Draw, _)) => (Wdl::Draw, 0),
+                    Some((wdl, plies)) => (wdl, plies * (wdl as i32).signum()),
+                };
+                assert_eq!((wdl, dtz), expected, "{}", fen);
+            }
+        }
+    }
+}
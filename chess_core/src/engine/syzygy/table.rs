@@ -0,0 +1,912 @@
+//! The Syzygy file format: how positions are numbered and how the values of a
+//! table are compressed. The layout follows the reference prober (Fathom and
+//! Stockfish's tbprobe), so tables from the official generator can be read.
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::LazyLock;
+use crate::types::board::board::Board;
+use crate::types::color::Color;
+use super::{SyzygyError, Wdl, NAME_ORDER};
+
+pub(super) const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+pub(super) const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+/// Most pieces, kings included, a table can have.
+pub(super) const TB_PIECES: usize = 7;
+
+// Header flags of a file
+pub(super) const SPLIT: u8 = 1;
+pub(super) const HAS_PAWNS: u8 = 2;
+
+// Flags of a sub-table, all but SINGLE_VALUE only appear in DTZ tables
+pub(super) const STM: u8 = 1;
+pub(super) const MAPPED: u8 = 2;
+pub(super) const WIN_PLIES: u8 = 4;
+pub(super) const LOSS_PLIES: u8 = 8;
+pub(super) const WIDE: u8 = 16;
+pub(super) const SINGLE_VALUE: u8 = 128;
+
+/// Right child of a leaf in the symbol tree.
+pub(super) const LEAF: u16 = 0xFFF;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+impl TableKind {
+    pub(super) fn magic(self) -> [u8; 4] {
+        match self {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        }
+    }
+}
+
+/// Piece code used in the files: white pawn to king are 1 to 6, black ones 9 to 14.
+pub(super) fn piece_code(color: usize, piece_type: usize) -> u8 {
+    (color * 8 + piece_type + 1) as u8
+}
+
+/// Material of a table, the side named first in the file name is white.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct Material {
+    /// Pieces of both sides, indexed pawn to king.
+    pub(super) counts: [[u8; 6]; 2],
+}
+
+impl Material {
+    /// Material of a name like `KRPvKR`.
+    pub(super) fn from_name(name: &str) -> Option<Material> {
+        let (strong, weak) = name.split_once('v')?;
+        let mut counts = [[0u8; 6]; 2];
+        for (side, letters) in [strong, weak].iter().enumerate() {
+            for letter in letters.chars() {
+                let (_, piece_type) = NAME_ORDER.iter().find(|(l, _)| *l == letter)?;
+                counts[side][*piece_type] += 1;
+            }
+            if counts[side][5] != 1 {
+                return None;
+            }
+        }
+        Some(Material { counts })
+    }
+
+    /// Material of `board` with white named first.
+    pub(super) fn of_board(board: &Board) -> Material {
+        let mut counts = [[0u8; 6]; 2];
+        for (side, side_counts) in counts.iter_mut().enumerate() {
+            for (piece_type, count) in side_counts.iter_mut().enumerate() {
+                *count = board.pieces[side * 6 + piece_type].count_set() as u8;
+            }
+        }
+        Material { counts }
+    }
+
+    pub(super) fn name(&self) -> String {
+        let side = |counts: &[u8; 6]| -> String {
+            NAME_ORDER.iter().map(|(letter, t)| letter.to_string().repeat(counts[*t] as usize)).collect()
+        };
+        format!("{}v{}", side(&self.counts[0]), side(&self.counts[1]))
+    }
+
+    /// Unique number of the material, depends on which side is named first.
+    pub(super) fn key(&self) -> u64 {
+        self.counts.iter().flatten().fold(0, |key, count| key << 4 | *count as u64)
+    }
+
+    pub(super) fn mirror(&self) -> Material {
+        Material { counts: [self.counts[1], self.counts[0]] }
+    }
+
+    /// Both sides have the same pieces.
+    pub(super) fn symmetric(&self) -> bool {
+        self.counts[0] == self.counts[1]
+    }
+
+    pub(super) fn piece_count(&self) -> usize {
+        self.counts.iter().flatten().map(|c| *c as usize).sum()
+    }
+
+    pub(super) fn has_pawns(&self) -> bool {
+        self.counts[0][0] + self.counts[1][0] > 0
+    }
+
+    /// Some piece other than a king is the only one of its kind and color.
+    pub(super) fn has_unique_pieces(&self) -> bool {
+        self.counts.iter().any(|side| side[..5].contains(&1))
+    }
+
+    /// Color of the leading pawns, the side with fewer pawns if both have some.
+    pub(super) fn lead_color(&self) -> usize {
+        let (white, black) = (self.counts[0][0], self.counts[1][0]);
+        if black == 0 || (white > 0 && black >= white) { 0 } else { 1 }
+    }
+
+    /// Pawns of the leading color and of the other one.
+    pub(super) fn pawn_count(&self) -> [u8; 2] {
+        let lead = self.lead_color();
+        [self.counts[lead][0], self.counts[1 - lead][0]]
+    }
+}
+
+
+/// Lookup tables of the position numbering.
+struct Maps {
+    /// `binomial[k][n]` ways to pick k of n squares.
+    binomial: [[u64; 64]; TB_PIECES],
+    /// Pawn squares a2-h7 to 0..47, the leading pawn has the highest value.
+    map_pawns: [usize; 64],
+    /// Squares below the a1-h8 diagonal to 0..27.
+    map_b1h1h7: [u64; 64],
+    /// The a1-d1-d4 triangle to 0..9, diagonal squares last.
+    map_a1d1d4: [usize; 64],
+    /// The 462 placements of two kings with the first one in the triangle.
+    map_kk: [[u64; 64]; 10],
+    lead_pawn_idx: [[u64; 64]; TB_PIECES],
+    lead_pawns_size: [[u64; 4]; TB_PIECES],
+}
+
+/// Rank minus file: negative below the a1-h8 diagonal, positive above it.
+fn off_a1h8(square: usize) -> i32 {
+    (square / 8) as i32 - (square % 8) as i32
+}
+
+fn edge_distance(file: usize) -> usize {
+    file.min(7 - file)
+}
+
+static MAPS: LazyLock<Maps> = LazyLock::new(|| {
+    let mut maps = Maps {
+        binomial: [[0; 64]; TB_PIECES],
+        map_pawns: [0; 64],
+        map_b1h1h7: [0; 64],
+        map_a1d1d4: [0; 64],
+        map_kk: [[0; 64]; 10],
+        lead_pawn_idx: [[0; 64]; TB_PIECES],
+        lead_pawns_size: [[0; 4]; TB_PIECES],
+    };
+
+    let mut code = 0;
+    for square in 0..64 {
+        if off_a1h8(square) < 0 {
+            maps.map_b1h1h7[square] = code;
+            code += 1;
+        }
+    }
+
+    let mut code = 0;
+    let mut diagonal = Vec::new();
+    for square in 0..=27 {
+        if off_a1h8(square) < 0 && square % 8 <= 3 {
+            maps.map_a1d1d4[square] = code;
+            code += 1;
+        } else if off_a1h8(square) == 0 && square % 8 <= 3 {
+            diagonal.push(square);
+        }
+    }
+    for square in diagonal {
+        maps.map_a1d1d4[square] = code;
+        code += 1;
+    }
+
+    // With the first king on the diagonal the second one may not be above it,
+    // both kings on the diagonal come last
+    let adjacent = |a: usize, b: usize| (a / 8).abs_diff(b / 8) <= 1 && (a % 8).abs_diff(b % 8) <= 1;
+    let mut both_on_diagonal = Vec::new();
+    let mut code = 0;
+    for idx in 0..10 {
+        for s1 in 0..=27 {
+            // b1 is the only square of the triangle with index 0
+            if maps.map_a1d1d4[s1] != idx || (idx == 0 && s1 != 1) {
+                continue;
+            }
+            for s2 in 0..64 {
+                if adjacent(s1, s2) || (off_a1h8(s1) == 0 && off_a1h8(s2) > 0) {
+                    continue;
+                }
+                if off_a1h8(s1) == 0 && off_a1h8(s2) == 0 {
+                    both_on_diagonal.push((idx, s2));
+                } else {
+                    maps.map_kk[idx][s2] = code;
+                    code += 1;
+                }
+            }
+        }
+    }
+    for (idx, s2) in both_on_diagonal {
+        maps.map_kk[idx][s2] = code;
+        code += 1;
+    }
+
+    maps.binomial[0][0] = 1;
+    for n in 1..64 {
+        for k in 0..TB_PIECES.min(n + 1) {
+            maps.binomial[k][n] = if k > 0 { maps.binomial[k - 1][n - 1] } else { 0 }
+                + if k < n { maps.binomial[k][n - 1] } else { 0 };
+        }
+    }
+
+    let mut available = 47;
+    for lead_pawns in 1..TB_PIECES - 1 {
+        for file in 0..4 {
+            let mut idx = 0;
+            for rank in 1..7 {
+                let square = rank * 8 + file;
+                if lead_pawns == 1 {
+                    maps.map_pawns[square] = available;
+                    maps.map_pawns[square ^ 7] = available - 1;
+                    available = available.saturating_sub(2);
+                }
+                maps.lead_pawn_idx[lead_pawns][square] = idx;
+                idx += maps.binomial[lead_pawns - 1][maps.map_pawns[square]];
+            }
+            maps.lead_pawns_size[lead_pawns][file] = idx;
+        }
+    }
+    maps
+});
+
+
+/// Order and grouping of the pieces of one sub-table. Pieces of a group are
+/// numbered together, the groups are combined in the order of the file.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct Encoding {
+    pub(super) pieces: [u8; TB_PIECES],
+    group_len: [usize; TB_PIECES + 1],
+    group_idx: [u64; TB_PIECES + 1],
+}
+
+impl Encoding {
+    /// `order` gives the place of the leading group and of the remaining pawns
+    /// (0xF without) among the groups, `file` is the leading pawn's file a to d.
+    pub(super) fn new(material: &Material, pieces: &[u8], order: [u8; 2], file: usize) -> Encoding {
+        let maps = &*MAPS;
+        let mut encoding = Encoding::default();
+        encoding.pieces[..pieces.len()].copy_from_slice(pieces);
+
+        let has_pawns = material.has_pawns();
+        // The leading group: pawns, three unique pieces or the two kings
+        let mut first_len: i32 = if has_pawns { 0 } else if material.has_unique_pieces() { 3 } else { 2 };
+        let mut n = 0;
+        encoding.group_len[0] = 1;
+        for i in 1..pieces.len() {
+            first_len -= 1;
+            if first_len > 0 || pieces[i] == pieces[i - 1] {
+                encoding.group_len[n] += 1;
+            } else {
+                n += 1;
+                encoding.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        encoding.group_len[n] = 0;
+
+        let pp = has_pawns && material.pawn_count()[1] > 0;
+        let mut next = if pp { 2 } else { 1 };
+        let mut free_squares = 64 - encoding.group_len[0] - if pp { encoding.group_len[1] } else { 0 };
+        let mut idx = 1u64;
+        let mut k = 0;
+        while next < n || k == order[0] as usize || k == order[1] as usize {
+            if k == order[0] as usize {
+                encoding.group_idx[0] = idx;
+                idx *= if has_pawns {
+                    maps.lead_pawns_size[encoding.group_len[0]][file]
+                } else if material.has_unique_pieces() {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] as usize {
+                encoding.group_idx[1] = idx;
+                idx *= maps.binomial[encoding.group_len[1]][48 - encoding.group_len[0]];
+            } else {
+                encoding.group_idx[next] = idx;
+                idx *= maps.binomial[encoding.group_len[next]][free_squares];
+                free_squares -= encoding.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        encoding.group_idx[n] = idx;
+        encoding
+    }
+
+    /// Number of piece groups.
+    pub(super) fn groups(&self) -> usize {
+        self.group_len.iter().position(|len| *len == 0).unwrap_or(TB_PIECES)
+    }
+
+    /// Number of indices of the sub-table.
+    pub(super) fn size(&self) -> u64 {
+        self.group_idx[self.groups()]
+    }
+}
+
+/// How the positions of one file are numbered.
+#[derive(Debug, Clone)]
+pub(super) struct Layout {
+    pub(super) material: Material,
+    /// Sub-tables per file: one for each side to move in WDL files of unequal
+    /// material, otherwise one.
+    pub(super) sides: usize,
+    /// Encodings by side to move and by file a to d of the leading pawn.
+    pub(super) encodings: [[Encoding; 4]; 2],
+}
+
+impl Layout {
+    /// Sub-tables per side, one for each file of the leading pawn.
+    pub(super) fn files(&self) -> usize {
+        if self.material.has_pawns() { 4 } else { 1 }
+    }
+
+    /// Side to move, leading pawn file and index of `board`.
+    pub(super) fn locate(&self, board: &Board) -> (usize, usize, u64) {
+        // Tables have the side named first as white, and only white to move if
+        // both sides have the same pieces
+        let black = board.state.color == Color::Black;
+        let flip = (self.material.symmetric() && black) || Material::of_board(board) != self.material;
+
+        let mut pieces = [0u8; TB_PIECES];
+        let mut squares = [0u8; TB_PIECES];
+        let mut size = 0;
+        for square in board.occupied() {
+            let square = square as u8;
+            let piece = board.mailbox[square as usize] as usize;
+            pieces[size] = piece_code((piece / 6) ^ flip as usize, piece % 6);
+            squares[size] = square ^ if flip { 56 } else { 0 };
+            size += 1;
+        }
+        self.index(&pieces[..size], &squares[..size], black as usize ^ flip as usize)
+    }
+
+    /// Side to move, leading pawn file and index of a position given as piece
+    /// codes and squares with the side named first as white.
+    pub(super) fn index(&self, pieces_in: &[u8], squares_in: &[u8], stm: usize) -> (usize, usize, u64) {
+        let maps = &*MAPS;
+        let has_pawns = self.material.has_pawns();
+        let mut pieces = [0u8; TB_PIECES];
+        let mut squares = [0usize; TB_PIECES];
+        let mut size = 0;
+        let mut file = 0;
+
+        // With pawns the leading pawn, the one closest to the edge and then to
+        // the first rank, decides which of the four sub-tables is used
+        let lead = self.encodings[0][0].pieces[0];
+        if has_pawns {
+            for (&piece, &square) in pieces_in.iter().zip(squares_in) {
+                if piece == lead {
+                    pieces[size] = piece;
+                    squares[size] = square as usize;
+                    size += 1;
+                }
+            }
+            let leading = (0..size).max_by_key(|&i| maps.map_pawns[squares[i]]).unwrap_or(0);
+            squares.swap(0, leading);
+            file = edge_distance(squares[0] % 8);
+        }
+        let lead_pawns = size;
+        for (&piece, &square) in pieces_in.iter().zip(squares_in) {
+            if !has_pawns || piece != lead {
+                pieces[size] = piece;
+                squares[size] = square as usize;
+                size += 1;
+            }
+        }
+
+        // Put the pieces in the order of the file
+        let encoding = &self.encodings[stm % self.sides][file];
+        for i in lead_pawns..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|&j| pieces[j] == encoding.pieces[i]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        // Mirror so the first piece is on files a to d
+        if squares[0] % 8 > 3 {
+            squares[..size].iter_mut().for_each(|s| *s ^= 7);
+        }
+
+        let mut idx;
+        if has_pawns {
+            idx = maps.lead_pawn_idx[lead_pawns][squares[0]];
+            squares[1..lead_pawns].sort_by_key(|&s| maps.map_pawns[s]);
+            for (i, &square) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+                idx += maps.binomial[i][maps.map_pawns[square]];
+            }
+        } else {
+            // Without pawns also mirror so the first piece is on ranks 1 to 4 and
+            // the first piece of the leading group off the diagonal is below it
+            if squares[0] / 8 > 3 {
+                squares[..size].iter_mut().for_each(|s| *s ^= 56);
+            }
+            for i in 0..encoding.group_len[0] {
+                let off = off_a1h8(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    squares[i..size].iter_mut().for_each(|s| *s = ((*s >> 3) | (*s << 3)) & 63);
+                }
+                break;
+            }
+
+            if self.material.has_unique_pieces() {
+                let (s0, s1, s2) = (squares[0], squares[1], squares[2]);
+                let adjust1 = (s1 > s0) as u64;
+                let adjust2 = (s2 > s0) as u64 + (s2 > s1) as u64;
+                let rank = |s: usize| (s / 8) as u64;
+                idx = if off_a1h8(s0) != 0 {
+                    (maps.map_a1d1d4[s0] as u64 * 63 + (s1 as u64 - adjust1)) * 62 + s2 as u64 - adjust2
+                } else if off_a1h8(s1) != 0 {
+                    (6 * 63 + rank(s0) * 28 + maps.map_b1h1h7[s1]) * 62 + s2 as u64 - adjust2
+                } else if off_a1h8(s2) != 0 {
+                    6 * 63 * 62 + 4 * 28 * 62 + rank(s0) * 7 * 28 + (rank(s1) - adjust1) * 28 + maps.map_b1h1h7[s2]
+                } else {
+                    6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + rank(s0) * 7 * 6 + (rank(s1) - adjust1) * 6 + (rank(s2) - adjust2)
+                };
+            } else {
+                idx = maps.map_kk[maps.map_a1d1d4[squares[0]]][squares[1]];
+            }
+        }
+        idx *= encoding.group_idx[0];
+
+        // The remaining groups, each with its squares in ascending order and
+        // without the squares taken by the groups before it
+        let mut start = encoding.group_len[0];
+        let mut remaining_pawns = has_pawns && self.material.pawn_count()[1] > 0;
+        let mut next = 1;
+        while encoding.group_len[next] != 0 {
+            let len = encoding.group_len[next];
+            squares[start..start + len].sort_unstable();
+            let mut n = 0;
+            for i in 0..len {
+                let square = squares[start + i];
+                let adjust = squares[..start].iter().filter(|&&s| square > s).count();
+                n += maps.binomial[i + 1][square - adjust - 8 * remaining_pawns as usize];
+            }
+            remaining_pawns = false;
+            idx += n * encoding.group_idx[next];
+            start += len;
+            next += 1;
+        }
+        (stm, file, idx)
+    }
+}
+
+
+fn read_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Big endian bits of the compressed data, zero past the end of the file.
+fn read_bits(bytes: &[u8], at: usize, count: usize) -> u64 {
+    (0..count).fold(0, |bits, i| bits << 8 | *bytes.get(at + i).unwrap_or(&0) as u64)
+}
+
+/// Left and right child of a symbol in the tree of symbol pairs.
+fn children(bytes: &[u8], btree: usize, symbol: usize) -> Option<(usize, usize)> {
+    let lr = bytes.get(btree + 3 * symbol..btree + 3 * symbol + 3)?;
+    let left = ((lr[1] as usize & 0xF) << 8) | lr[0] as usize;
+    let right = ((lr[2] as usize) << 4) | (lr[1] as usize >> 4);
+    Some((left, right))
+}
+
+/// Compression data of one sub-table.
+///
+/// The values are split into blocks of canonical Huffman codes. Every symbol
+/// stands for a value or for a pair of other symbols, so one symbol can expand
+/// to up to 256 values. A sparse index points into the list of block lengths
+/// every `span` values.
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    flags: u8,
+    /// Shortest symbol length in bits, the value itself for single value tables.
+    min_sym_len: u8,
+    block_size: usize,
+    span: u64,
+    num_blocks: usize,
+    /// Offsets into the file
+    lowest_sym: usize,
+    btree: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    block_length: usize,
+    block_length_size: usize,
+    data: usize,
+    /// Lowest code of every length, left aligned to 64 bits.
+    base64: Vec<u64>,
+    /// Values a symbol expands to, minus one.
+    symlen: Vec<u8>,
+    /// Start of the value maps of a DTZ table for win, loss, cursed win and blessed loss.
+    map_idx: [usize; 4],
+}
+
+impl PairsData {
+    /// Reads the sizes section at `*at` of a sub-table with `size` indices.
+    fn parse(bytes: &[u8], at: &mut usize, size: u64) -> Option<PairsData> {
+        let mut d = PairsData { flags: *bytes.get(*at)?, ..PairsData::default() };
+        if d.flags & SINGLE_VALUE != 0 {
+            d.min_sym_len = *bytes.get(*at + 1)?;
+            *at += 2;
+            return Some(d);
+        }
+
+        let header = bytes.get(*at + 1..*at + 10)?;
+        if header[0] >= 32 || header[1] >= 32 {
+            return None;
+        }
+        d.block_size = 1 << header[0];
+        d.span = 1 << header[1];
+        d.sparse_index_size = size.div_ceil(d.span) as usize;
+        d.num_blocks = read_u32(header, 3)? as usize;
+        d.block_length_size = d.num_blocks + header[2] as usize;
+        let (max_sym_len, min_sym_len) = (header[7], header[8]);
+        if min_sym_len == 0 || min_sym_len > max_sym_len || max_sym_len > 32 {
+            return None;
+        }
+        d.min_sym_len = min_sym_len;
+        d.lowest_sym = *at + 10;
+
+        // Longer codes have lower values, so the lowest code of a length is half
+        // of the code after the last one of the next longer length
+        let lengths = (max_sym_len - min_sym_len + 1) as usize;
+        let lowest = |i: usize| read_u16(bytes, d.lowest_sym + 2 * i).map(|s| s as u64);
+        d.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            d.base64[i] = d.base64[i + 1].wrapping_add(lowest(i)?).wrapping_sub(lowest(i + 1)?) / 2;
+        }
+        for (i, base) in d.base64.iter_mut().enumerate() {
+            *base <<= 64 - i - min_sym_len as usize;
+        }
+
+        let symbols_at = d.lowest_sym + 2 * lengths;
+        let symbols = read_u16(bytes, symbols_at)? as usize;
+        d.btree = symbols_at + 2;
+        bytes.get(d.btree..d.btree + 3 * symbols)?;
+        d.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for symbol in 0..symbols {
+            if !visited[symbol] {
+                d.symlen[symbol] = d.set_symlen(bytes, symbol, &mut visited)?;
+            }
+        }
+        *at = d.btree + 3 * symbols + (symbols & 1);
+        Some(d)
+    }
+
+    fn set_symlen(&mut self, bytes: &[u8], symbol: usize, visited: &mut [bool]) -> Option<u8> {
+        visited[symbol] = true;
+        let (left, right) = children(bytes, self.btree, symbol)?;
+        if right == LEAF as usize {
+            return Some(0);
+        }
+        for child in [left, right] {
+            if !*visited.get(child)? {
+                self.symlen[child] = self.set_symlen(bytes, child, visited)?;
+            }
+        }
+        self.symlen[left].checked_add(self.symlen[right])?.checked_add(1)
+    }
+
+    /// Value at `idx`, `None` if the data is damaged.
+    fn decompress(&self, bytes: &[u8], idx: u64) -> Option<u16> {
+        if self.flags & SINGLE_VALUE != 0 {
+            return Some(self.min_sym_len as u16);
+        }
+
+        // The sparse index gives the block and offset of the value in the
+        // middle of every span, walk the block lengths from there
+        let entry = self.sparse_index + 6 * (idx / self.span) as usize;
+        if idx / self.span >= self.sparse_index_size as u64 {
+            return None;
+        }
+        let mut block = read_u32(bytes, entry)? as usize;
+        let mut offset = read_u16(bytes, entry + 4)? as i64;
+        offset += (idx % self.span) as i64 - (self.span / 2) as i64;
+        let block_length = |block: usize| -> Option<i64> {
+            if block >= self.block_length_size {
+                return None;
+            }
+            read_u16(bytes, self.block_length + 2 * block).map(|l| l as i64)
+        };
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+        if block >= self.num_blocks {
+            return None;
+        }
+
+        // Decode symbols until the one that covers the offset
+        let mut at = self.data + block * self.block_size;
+        let mut buffer = read_bits(bytes, at, 8);
+        at += 8;
+        let mut buffer_size = 64;
+        let min_sym_len = self.min_sym_len as usize;
+        let mut symbol;
+        loop {
+            let mut len = 0;
+            while buffer < *self.base64.get(len)? {
+                len += 1;
+            }
+            symbol = ((buffer - self.base64[len]) >> (64 - len - min_sym_len)) as usize;
+            symbol += read_u16(bytes, self.lowest_sym + 2 * len)? as usize;
+            let values = *self.symlen.get(symbol)? as i64 + 1;
+            if offset < values {
+                break;
+            }
+            offset -= values;
+            buffer <<= len + min_sym_len;
+            buffer_size -= len + min_sym_len;
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= read_bits(bytes, at, 4) << (64 - buffer_size);
+                at += 4;
+            }
+        }
+
+        // Expand the pairs down to the value
+        while self.symlen[symbol] != 0 {
+            let (left, right) = children(bytes, self.btree, symbol)?;
+            let left_values = *self.symlen.get(left)? as i64 + 1;
+            if offset < left_values {
+                symbol = left;
+            } else {
+                offset -= left_values;
+                symbol = right;
+            }
+            self.symlen.get(symbol)?;
+        }
+        children(bytes, self.btree, symbol).map(|(value, _)| value as u16)
+    }
+}
+
+
+/// Read only memory map of a whole file.
+#[cfg(unix)]
+struct Mapping {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+// The mapping is read only and lives as long as the struct
+#[cfg(unix)]
+unsafe impl Send for Mapping {}
+#[cfg(unix)]
+unsafe impl Sync for Mapping {}
+
+#[cfg(unix)]
+impl Mapping {
+    fn open(path: &Path) -> io::Result<Self> {
+        use std::os::unix::io::AsRawFd;
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        if len == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "empty file"));
+        }
+        // SAFETY: a fresh private read only mapping of an open file, checked for failure below
+        let ptr = unsafe { libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, file.as_raw_fd(), 0) };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mapping { ptr, len })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        // SAFETY: ptr points to len mapped, readable bytes until drop
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        // SAFETY: ptr and len come from a successful mmap
+        unsafe { libc::munmap(self.ptr, self.len) };
+    }
+}
+
+#[cfg(not(unix))]
+struct Mapping(Vec<u8>);
+
+#[cfg(not(unix))]
+impl Mapping {
+    fn open(path: &Path) -> io::Result<Self> {
+        use std::io::Read;
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        Ok(Mapping(bytes))
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Mapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Mapping({} bytes)", self.bytes().len())
+    }
+}
+
+
+/// Entry of a DTZ table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Dtz {
+    /// Plies to the next zeroing move, without sign.
+    Plies(i32),
+    /// The table only stores the other side to move.
+    OtherSide,
+}
+
+/// A mapped `.rtbw` or `.rtbz` file.
+#[derive(Debug)]
+pub(super) struct Table {
+    mapping: Mapping,
+    layout: Layout,
+    kind: TableKind,
+    /// By side to move and leading pawn file, like the encodings.
+    pairs: [[PairsData; 4]; 2],
+    /// Start of the DTZ value maps.
+    map: usize,
+}
+
+impl Table {
+    pub(super) fn open(path: &Path, material: Material, kind: TableKind) -> Result<Table, SyzygyError> {
+        let mapping = Mapping::open(path)?;
+        if !mapping.bytes().starts_with(&kind.magic()) {
+            return Err(SyzygyError::InvalidMagic(path.to_path_buf()));
+        }
+        let (layout, pairs, map) = Table::parse(mapping.bytes(), material, kind)
+            .ok_or_else(|| SyzygyError::Corrupt(path.to_path_buf()))?;
+        Ok(Table { mapping, layout, kind, pairs, map })
+    }
+
+    fn parse(bytes: &[u8], material: Material, kind: TableKind) -> Option<(Layout, [[PairsData; 4]; 2], usize)> {
+        let flags = *bytes.get(4)?;
+        if (flags & HAS_PAWNS != 0) != material.has_pawns() || (flags & SPLIT != 0) == material.symmetric() {
+            return None;
+        }
+        let sides = if kind == TableKind::Wdl && !material.symmetric() { 2 } else { 1 };
+        let mut layout = Layout { material, sides, encodings: Default::default() };
+        let pp = material.has_pawns() && material.pawn_count()[1] > 0;
+        let count = material.piece_count();
+        let nibble = |byte: u8, side: usize| if side == 0 { byte & 0xF } else { byte >> 4 };
+
+        let mut at = 5;
+        for file in 0..layout.files() {
+            let order = bytes.get(at..at + 1 + pp as usize)?;
+            let piece_bytes = bytes.get(at + order.len()..at + order.len() + count)?;
+            for side in 0..sides {
+                let pieces: Vec<u8> = piece_bytes.iter().map(|b| nibble(*b, side)).collect();
+                if !same_pieces(&material, &pieces) {
+                    return None;
+                }
+                let order = [nibble(order[0], side), if pp { nibble(order[1], side) } else { 0xF }];
+                layout.encodings[side][file] = Encoding::new(&material, &pieces, order, file);
+            }
+            at += order.len() + count;
+        }
+        at += at & 1;
+
+        let mut pairs: [[PairsData; 4]; 2] = Default::default();
+        for file in 0..layout.files() {
+            for (side_pairs, encodings) in pairs.iter_mut().zip(&layout.encodings).take(sides) {
+                side_pairs[file] = PairsData::parse(bytes, &mut at, encodings[file].size())?;
+            }
+        }
+
+        let map = at;
+        if kind == TableKind::Dtz {
+            for d in pairs[0][..layout.files()].iter_mut().filter(|d| d.flags & MAPPED != 0) {
+                if d.flags & WIDE != 0 {
+                    at += at & 1;
+                    for i in 0..4 {
+                        d.map_idx[i] = (at - map) / 2 + 1;
+                        at += 2 * read_u16(bytes, at)? as usize + 2;
+                    }
+                } else {
+                    for i in 0..4 {
+                        d.map_idx[i] = at - map + 1;
+                        at += *bytes.get(at)? as usize + 1;
+                    }
+                }
+            }
+            at += at & 1;
+        }
+
+        for file in 0..layout.files() {
+            for d in pairs[..sides].iter_mut().map(|side| &mut side[file]) {
+                d.sparse_index = at;
+                at += 6 * d.sparse_index_size;
+            }
+        }
+        for file in 0..layout.files() {
+            for d in pairs[..sides].iter_mut().map(|side| &mut side[file]) {
+                d.block_length = at;
+                at += 2 * d.block_length_size;
+            }
+        }
+        for file in 0..layout.files() {
+            for d in pairs[..sides].iter_mut().map(|side| &mut side[file]) {
+                at = (at + 63) & !63;
+                d.data = at;
+                at += d.num_blocks * d.block_size;
+            }
+        }
+        (at <= bytes.len()).then_some((layout, pairs, map))
+    }
+
+    /// Stored result of `board`. Positions where a capture is at least as good
+    /// may hold any value, the caller has to search the captures.
+    pub(super) fn wdl(&self, board: &Board) -> Option<Wdl> {
+        debug_assert_eq!(self.kind, TableKind::Wdl);
+        let (stm, file, idx) = self.layout.locate(board);
+        let value = self.pairs[stm % self.layout.sides][file].decompress(self.mapping.bytes(), idx)?;
+        Wdl::from_i8(value as i8 - 2)
+    }
+
+    /// Stored distance to zeroing of `board`, whose result is `wdl`. Positions
+    /// where a zeroing move is best may hold any value.
+    pub(super) fn dtz(&self, board: &Board, wdl: Wdl) -> Option<Dtz> {
+        debug_assert_eq!(self.kind, TableKind::Dtz);
+        let (stm, file, idx) = self.layout.locate(board);
+        let d = &self.pairs[0][file];
+        let symmetric = self.layout.material.symmetric() && !self.layout.material.has_pawns();
+        if (d.flags & STM) as usize != stm && !symmetric {
+            return Some(Dtz::OtherSide);
+        }
+
+        let bytes = self.mapping.bytes();
+        let mut value = d.decompress(bytes, idx)? as usize;
+        if d.flags & MAPPED != 0 {
+            let map = match wdl {
+                Wdl::Win | Wdl::Draw => d.map_idx[0],
+                Wdl::Loss => d.map_idx[1],
+                Wdl::CursedWin => d.map_idx[2],
+                Wdl::BlessedLoss => d.map_idx[3],
+            };
+            value = if d.flags & WIDE != 0 {
+                read_u16(bytes, self.map + 2 * (map + value))? as usize
+            } else {
+                *bytes.get(self.map + map + value)? as usize
+            };
+        }
+
+        // Tables store moves instead of plies where that is exact enough
+        let moves = match wdl {
+            Wdl::Win => d.flags & WIN_PLIES == 0,
+            Wdl::Loss => d.flags & LOSS_PLIES == 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => true,
+            Wdl::Draw => false,
+        };
+        let plies = if moves { 2 * value } else { value };
+        Some(Dtz::Plies(plies as i32 + 1))
+    }
+}
+
+/// Whether `pieces` are the pieces of `material`.
+fn same_pieces(material: &Material, pieces: &[u8]) -> bool {
+    let mut counts = [[0u8; 6]; 2];
+    for &piece in pieces {
+        let (color, piece_type) = ((piece >> 3) as usize, (piece & 7) as usize);
+        if color > 1 || !(1..=6).contains(&piece_type) {
+            return false;
+        }
+        counts[color][piece_type - 1] += 1;
+    }
+    counts == material.counts
+}
@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+use crate::engine::eval::{Evaluate, HandcraftedEval};
+use crate::engine::search::{negamax, SearchControl};
+use crate::types::board::board::Board;
+use crate::types::board::epd::EpdOperations;
+use crate::types::board::transposition::TranspositionTable;
+use crate::types::moves::Move;
+
+const MAX_DEPTH: i8 = 64;
+// Scores within this distance of the maximum are mate scores
+const MATE_BOUND: i32 = 512;
+
+/// Limits of the search on every position, the search stops at whichever is reached first.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchLimit {
+    pub time: Option<Duration>,
+    pub nodes: Option<u64>,
+    pub depth: Option<i8>,
+}
+
+/// Outcome of searching one test position.
+#[derive(Debug, Clone)]
+pub struct PositionResult {
+    pub best_move: Option<Move>,
+    /// Score relative to the side to move.
+    pub score: i32,
+    pub depth: i8,
+    pub nodes: u64,
+    pub solved: bool,
+}
+
+impl PositionResult {
+    /// Number of moves until mate if the score is a mate score, negative if the side to move gets mated.
+    pub fn mate_in(&self) -> Option<i32> {
+        if self.score >= i32::MAX - MATE_BOUND {
+            Some((i32::MAX - self.score + 1) / 2)
+        } else if self.score <= -i32::MAX + MATE_BOUND {
+            Some(-(self.score + i32::MAX) / 2)
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterative deepening within `limit`, only iterations finished in time count.
+/// Returns the score, best move, reached depth and searched nodes.
+pub fn search_with_limit<E: Evaluate>(board: &mut Board, evaluator: &mut E, tt: &mut TranspositionTable, limit: SearchLimit) -> (i32, Option<Move>, i8, u64) {
+    let start = Instant::now();
+    // Without any limit a test position would never finish
+    let end_time = start + limit.time.unwrap_or(if limit.nodes.is_none() && limit.depth.is_none() {
+        Duration::from_secs(1)
+    } else {
+        Duration::from_secs(3600)
+    });
+    let control = SearchControl::until(end_time);
+    evaluator.refresh(board);
+
+    let mut best = (0, None, 0, 0);
+    let mut nodes = 0;
+    for depth in 1..=limit.depth.unwrap_or(MAX_DEPTH).min(MAX_DEPTH) {
+        let (score, mv, num, _) = negamax(board, evaluator, tt, depth, depth, -i32::MAX, i32::MAX, &control);
+        nodes += num;
+        // An interrupted iteration has no reliable result
+        if Instant::now() >= end_time && best.1.is_some() {
+            break;
+        }
+        if mv.is_some() {
+            best = (score, mv, depth, nodes);
+        }
+        if limit.nodes.is_some_and(|n| nodes >= n) || Instant::now() >= end_time {
+            break;
+        }
+    }
+    best.3 = nodes;
+    best
+}
+
+/// Searches `board` within `limit` and checks the result against the `bm`, `am` and `dm` operations.
+/// `tt` can be shared between positions, entries are keyed by the full hash.
+pub fn run_position(board: &Board, ops: &EpdOperations, tt: &mut TranspositionTable, limit: SearchLimit) -> PositionResult {
+    let mut board = board.clone();
+    let (score, best_move, depth, nodes) = search_with_limit(&mut board, &mut HandcraftedEval, tt, limit);
+
+    let mut result = PositionResult { best_move, score, depth, nodes, solved: false };
+    let moves_ok = best_move.is_some_and(|mv| ops.is_solved_by(&mv));
+    let mate_ok = match ops.direct_mate {
+        Some(n) => result.mate_in().is_some_and(|m| m > 0 && m <= n as i32),
+        None => true,
+    };
+    result.solved = moves_ok && mate_ok;
+    result
+}
+
+
+
+
+/// ######################################################
+/// ################### TESTING ##########################
+/// ######################################################
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solves_simple_positions() {
+        let limit = SearchLimit { depth: Some(3), ..SearchLimit::default() };
+        let mut tt = TranspositionTable::new(1);
+        for epd in [
+            "6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8; dm 1; id \"back rank\";",
+            "4k3/8/8/3q4/8/4N3/8/4K3 w - - bm Nxd5; id \"free queen\";",
+            "4k3/8/8/8/1n6/8/8/3QK3 w - - am Qd3 Qc2; id \"no hanging queen\";",
+        ] {
+            let (board, ops) = Board::from_epd(epd).unwrap();
+            let result = run_position(&board, &ops, &mut tt, limit);
+            assert!(result.solved, "{} {:?}", epd, result.best_move.map(|mv| mv.to_san(&board)));
+        }
+
+        // Mate scores do not survive the TT, so search without earlier entries
+        let (board, ops) = Board::from_epd("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8; dm 1;").unwrap();
+        let result = run_position(&board, &ops, &mut TranspositionTable::new(1), limit);
+        assert_eq!(result.mate_in(), Some(1));
+    }
+
+    #[test]
+    fn test_wrong_move_fails() {
+        let (board, ops) = Board::from_epd("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra2;").unwrap();
+        let result = run_position(&board, &ops, &mut TranspositionTable::new(1), SearchLimit { depth: Some(2), ..SearchLimit::default() });
+        assert!(!result.solved);
+    }
+}
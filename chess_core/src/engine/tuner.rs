@@ -0,0 +1,427 @@
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::engine::eval::{
+    game_phase, get_double_pawns, get_isolated_pawns, mobility_counts, pawn_shield, HandcraftedEval,
+    DOUBLED_PAWN, ISOLATED_PAWN, MAX_PHASE, MOBILITY, PAWN_SHIELD, PIECE_VALUES, TEMPO,
+};
+use crate::engine::piece_square_tables::{flip_square, PSTS};
+use crate::engine::search::{quiescence_search, SearchControl};
+use crate::types::board::board::Board;
+use crate::types::color::Color;
+
+// Layout of the parameter vector
+const MATERIAL: usize = 0;
+const PST: usize = MATERIAL + 5;
+const PST_NAMES: [&str; 7] = ["PAWN_PST", "KNIGHT_PST", "BISHOP_PST", "ROOK_PST", "QUEEN_PST", "KING_MG_PST", "KING_EG_PST"];
+const KING_MG: usize = 5;
+const KING_EG: usize = 6;
+const DOUBLED: usize = PST + PST_NAMES.len() * 64;
+const ISOLATED: usize = DOUBLED + 1;
+const SHIELD: usize = ISOLATED + 1;
+const MOBILITY_TERMS: usize = SHIELD + 1;
+const TEMPO_TERM: usize = MOBILITY_TERMS + 4;
+/// Number of tunable parameters of the handcrafted evaluation.
+pub const NUM_PARAMS: usize = TEMPO_TERM + 1;
+
+const MAX_RESOLVE_PLY: usize = 32;
+
+/// A labeled position, reduced to the coefficients of every parameter in the
+/// white relative evaluation. The evaluation is linear in its parameters, so
+/// `eval = sum(coefficient * parameter)`.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub features: Vec<(u16, f32)>,
+    /// Game result from white's point of view: 1.0 win, 0.5 draw, 0.0 loss.
+    pub result: f64,
+}
+
+/// All handcrafted evaluation parameters as one flat vector.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Params(pub Vec<f64>);
+
+impl Params {
+    /// The parameters the evaluation currently uses.
+    pub fn current() -> Self {
+        let mut params = vec![0f64; NUM_PARAMS];
+        for (i, value) in PIECE_VALUES.iter().enumerate() {
+            params[MATERIAL + i] = *value as f64;
+        }
+        let tables = [PSTS.pawn, PSTS.knight, PSTS.bishop, PSTS.rook, PSTS.queen, PSTS.king_mg, PSTS.king_eg];
+        for (t, table) in tables.iter().enumerate() {
+            for (sq, value) in table.iter().enumerate() {
+                params[PST + t * 64 + sq] = *value as f64;
+            }
+        }
+        params[DOUBLED] = DOUBLED_PAWN as f64;
+        params[ISOLATED] = ISOLATED_PAWN as f64;
+        params[SHIELD] = PAWN_SHIELD as f64;
+        for (i, value) in MOBILITY.iter().enumerate() {
+            params[MOBILITY_TERMS + i] = *value as f64;
+        }
+        params[TEMPO_TERM] = TEMPO as f64;
+        Params(params)
+    }
+
+    /// White relative evaluation of an entry.
+    pub fn evaluate(&self, entry: &Entry) -> f64 {
+        entry.features.iter().map(|&(i, coefficient)| coefficient as f64 * self.0[i as usize]).sum()
+    }
+
+    fn rounded(&self, index: usize) -> i32 {
+        self.0[index].round() as i32
+    }
+
+    /// Rust source for the piece-square tables, a drop-in replacement of `piece_square_tables.rs`.
+    pub fn to_pst_source(&self) -> String {
+        let mut out = String::new();
+        for (t, name) in PST_NAMES.iter().enumerate() {
+            writeln!(out, "const {}: [i32; 64] = [", name).unwrap();
+            for rank in 0..8 {
+                let row: Vec<String> = (0..8).map(|file| format!("{:>4}", self.rounded(PST + t * 64 + rank * 8 + file))).collect();
+                let separator = if rank < 7 { "," } else { "" };
+                writeln!(out, "    {}{} // Rank {}", row.join(","), separator, rank + 1).unwrap();
+            }
+            writeln!(out, "];\n").unwrap();
+        }
+        out.push_str(PST_FOOTER);
+        out
+    }
+
+    /// Rust source for the scalar terms, matching the constants in `eval.rs`.
+    pub fn to_eval_source(&self) -> String {
+        let list = |start: usize, len: usize| (start..start + len).map(|i| self.rounded(i).to_string()).collect::<Vec<_>>().join(", ");
+        let mut out = String::new();
+        writeln!(out, "pub(crate) const PIECE_VALUES: [i32; 5] = [{}];", list(MATERIAL, 5)).unwrap();
+        writeln!(out, "pub(crate) const TEMPO: i32 = {};", self.rounded(TEMPO_TERM)).unwrap();
+        writeln!(out, "pub(crate) const DOUBLED_PAWN: i32 = {};", self.rounded(DOUBLED)).unwrap();
+        writeln!(out, "pub(crate) const ISOLATED_PAWN: i32 = {};", self.rounded(ISOLATED)).unwrap();
+        writeln!(out, "pub(crate) const PAWN_SHIELD: i32 = {};", self.rounded(SHIELD)).unwrap();
+        writeln!(out, "pub(crate) const MOBILITY: [i32; 4] = [{}];", list(MOBILITY_TERMS, 4)).unwrap();
+        out
+    }
+}
+
+const PST_FOOTER: &str = "#[inline(always)]
+pub fn flip_square(sq: usize) -> usize {
+    sq ^ 56 // XOR with 0b111000, effectively (7-rank)*8 + file
+}
+
+pub struct PieceSquareTables {
+    pub pawn: &'static [i32; 64],
+    pub knight: &'static [i32; 64],
+    pub bishop: &'static [i32; 64],
+    pub rook: &'static [i32; 64],
+    pub queen: &'static [i32; 64],
+    pub king_mg: &'static [i32; 64],
+    pub king_eg: &'static [i32; 64],
+}
+
+pub const PSTS: PieceSquareTables = PieceSquareTables {
+    pawn: &PAWN_PST,
+    knight: &KNIGHT_PST,
+    bishop: &BISHOP_PST,
+    rook: &ROOK_PST,
+    queen: &QUEEN_PST,
+    king_mg: &KING_MG_PST,
+    king_eg: &KING_EG_PST,
+};";
+
+/// Coefficients of every parameter in the white relative evaluation of `board`.
+pub fn extract_features(board: &Board) -> Vec<(u16, f32)> {
+    let mut coefficients = [0f32; NUM_PARAMS];
+    let phase = game_phase(board);
+    let mg = phase as f32 / MAX_PHASE as f32;
+    let eg = 1.0 - mg;
+
+    for (color, sign) in [(Color::White, 1f32), (Color::Black, -1f32)] {
+        let color_index = color as usize * 6;
+        for piece in 0..6 {
+            for sq in board.pieces[color_index + piece] {
+                let index = match color {
+                    Color::White => sq.to_index() as usize,
+                    _ => flip_square(sq.to_index() as usize),
+                };
+                if piece < 5 {
+                    coefficients[MATERIAL + piece] += sign;
+                    coefficients[PST + piece * 64 + index] += sign;
+                } else {
+                    coefficients[PST + KING_MG * 64 + index] += sign * mg;
+                    coefficients[PST + KING_EG * 64 + index] += sign * eg;
+                }
+            }
+        }
+        coefficients[DOUBLED] += sign * get_double_pawns(board, color) as f32;
+        coefficients[ISOLATED] += sign * get_isolated_pawns(board, color) as f32;
+        coefficients[SHIELD] += sign * pawn_shield(board, color) as f32 * mg;
+        for (i, count) in mobility_counts(board, color).iter().enumerate() {
+            coefficients[MOBILITY_TERMS + i] += sign * *count as f32;
+        }
+    }
+    coefficients[TEMPO_TERM] = match board.state.color {
+        Color::White => 1.0,
+        Color::Black => -1.0,
+        _ => 0.0,
+    };
+
+    coefficients
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| **c != 0.0)
+        .map(|(i, c)| (i as u16, *c))
+        .collect()
+}
+
+/// Plays out the principal variation of the quiescence search so the static
+/// evaluation of the resulting position is meaningful.
+pub fn resolve(board: &mut Board) {
+    let control = SearchControl::until(Instant::now() + Duration::from_secs(3600));
+    for _ in 0..MAX_RESOLVE_PLY {
+        match quiescence_search(board, &mut HandcraftedEval, -i32::MAX, i32::MAX, 0, &control).1 {
+            Some(mv) => board.apply_move(&mv),
+            None => break,
+        }
+    }
+}
+
+/// Parses a FEN or EPD line labeled with a game result.
+///
+/// Accepted labels are `1-0`, `0-1` and `1/2-1/2` (optionally quoted, as in an
+/// EPD `c9` opcode), `[1.0]`, `[0.5]`, `[0.0]` and the `FEN | score | result`
+/// lines written by `datagen`.
+pub fn parse_labeled_position(line: &str) -> Option<(Board, f64)> {
+    if line.contains('|') {
+        let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+        let result = fields.last()?.parse::<f64>().ok()?;
+        let board = Board::from_str(fields[0]).ok()?;
+        return Some((board, result));
+    }
+    let result = if line.contains("1/2-1/2") || line.contains("[0.5]") {
+        0.5
+    } else if line.contains("1-0") || line.contains("[1.0]") {
+        1.0
+    } else if line.contains("0-1") || line.contains("[0.0]") {
+        0.0
+    } else {
+        return None;
+    };
+
+    let mut fields: Vec<&str> = line.split_whitespace().take(6).collect();
+    if fields.len() < 4 {
+        return None;
+    }
+    // EPD lines carry opcodes instead of the move counters
+    if fields.len() == 6 && !(fields[4].parse::<u32>().is_ok() && fields[5].parse::<u32>().is_ok()) {
+        fields.truncate(4);
+    }
+    let board = Board::from_str(&fields.join(" ")).ok()?;
+    Some((board, result))
+}
+
+/// Reads every labeled position of `path`, resolving each with a quiescence search.
+pub fn load_entries(path: &Path) -> io::Result<Vec<Entry>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        if let Some((mut board, result)) = parse_labeled_position(&line?) {
+            resolve(&mut board);
+            entries.push(Entry { features: extract_features(&board), result });
+        }
+    }
+    Ok(entries)
+}
+
+fn sigmoid(k: f64, eval: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval / 400.0))
+}
+
+/// Texel tuning: minimizes the mean squared error between the game results and
+/// `sigmoid(K * eval)` with Adam.
+pub struct Tuner {
+    pub entries: Vec<Entry>,
+    pub params: Params,
+    pub k: f64,
+    pub learning_rate: f64,
+    pub threads: usize,
+    momentum: Vec<f64>,
+    velocity: Vec<f64>,
+    step: i32,
+}
+
+impl Tuner {
+    pub fn new(entries: Vec<Entry>, params: Params) -> Self {
+        Tuner {
+            entries,
+            params,
+            k: 1.0,
+            learning_rate: 1.0,
+            threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            momentum: vec![0.0; NUM_PARAMS],
+            velocity: vec![0.0; NUM_PARAMS],
+            step: 0,
+        }
+    }
+
+    fn chunk_size(&self) -> usize {
+        self.entries.len().div_ceil(self.threads.max(1)).max(1)
+    }
+
+    /// Mean squared error over all entries for scaling constant `k`.
+    pub fn error(&self, k: f64) -> f64 {
+        if self.entries.is_empty() {
+            return 0.0;
+        }
+        let params = &self.params;
+        let total: f64 = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .entries
+                .chunks(self.chunk_size())
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk.iter().map(|e| (e.result - sigmoid(k, params.evaluate(e))).powi(2)).sum::<f64>()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).sum()
+        });
+        total / self.entries.len() as f64
+    }
+
+    /// Finds the `k` that fits the current parameters best, by narrowing a scan.
+    pub fn optimize_k(&mut self) -> f64 {
+        let (mut low, mut high) = (0.0f64, 4.0f64);
+        for _ in 0..5 {
+            let step = (high - low) / 10.0;
+            let best = (0..=10)
+                .map(|i| low + step * i as f64)
+                .min_by(|a, b| self.error(*a).total_cmp(&self.error(*b)))
+                .unwrap();
+            low = (best - step).max(0.0);
+            high = best + step;
+        }
+        self.k = (low + high) / 2.0;
+        self.k
+    }
+
+    fn gradient(&self) -> Vec<f64> {
+        let params = &self.params;
+        let k = self.k;
+        let scale = k * std::f64::consts::LN_10 / 400.0;
+        let mut gradient = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .entries
+                .chunks(self.chunk_size())
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        let mut gradient = vec![0f64; NUM_PARAMS];
+                        for entry in chunk {
+                            let s = sigmoid(k, params.evaluate(entry));
+                            let factor = -2.0 * (entry.result - s) * s * (1.0 - s) * scale;
+                            for &(i, coefficient) in entry.features.iter() {
+                                gradient[i as usize] += factor * coefficient as f64;
+                            }
+                        }
+                        gradient
+                    })
+                })
+                .collect();
+            handles.into_iter().fold(vec![0f64; NUM_PARAMS], |mut sum, h| {
+                for (s, g) in sum.iter_mut().zip(h.join().unwrap()) {
+                    *s += g;
+                }
+                sum
+            })
+        });
+        let n = self.entries.len().max(1) as f64;
+        gradient.iter_mut().for_each(|g| *g /= n);
+        gradient
+    }
+
+    /// One Adam step over the whole data set, returns the error afterwards.
+    pub fn epoch(&mut self) -> f64 {
+        const BETA1: f64 = 0.9;
+        const BETA2: f64 = 0.999;
+        const EPSILON: f64 = 1e-8;
+
+        let gradient = self.gradient();
+        self.step += 1;
+        let state = self.params.0.iter_mut().zip(&mut self.momentum).zip(&mut self.velocity);
+        for (((param, momentum), velocity), g) in state.zip(&gradient) {
+            *momentum = BETA1 * *momentum + (1.0 - BETA1) * g;
+            *velocity = BETA2 * *velocity + (1.0 - BETA2) * g * g;
+            let m = *momentum / (1.0 - BETA1.powi(self.step));
+            let v = *velocity / (1.0 - BETA2.powi(self.step));
+            *param -= self.learning_rate * m / (v.sqrt() + EPSILON);
+        }
+        self.error(self.k)
+    }
+}
+
+
+
+
+/// ######################################################
+/// ################### TESTING ##########################
+/// ######################################################
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_features_reproduce_handcrafted_eval() {
+        let params = Params::current();
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 0 1",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 2 3",
+        ] {
+            let board = Board::from_str(fen).unwrap();
+            let entry = Entry { features: extract_features(&board), result: 0.5 };
+            let expected = HandcraftedEval.trace(&board).white_relative() as f64;
+            // The integer division of the tapered terms may round differently
+            assert!((params.evaluate(&entry) - expected).abs() <= 4.0, "{}: {} vs {}", fen, params.evaluate(&entry), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_labeled_position() {
+        let (board, result) = parse_labeled_position("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - c9 \"1/2-1/2\";").unwrap();
+        assert_eq!(result, 0.5);
+        assert_eq!(board.state.color, Color::Black);
+        let (_, result) = parse_labeled_position("8/8/8/8/8/8/k7/K7 w - - 0 1 [0.0]").unwrap();
+        assert_eq!(result, 0.0);
+        assert!(parse_labeled_position("8/8/8/8/8/8/k7/K7 w - - 0 1").is_none());
+        let (_, result) = parse_labeled_position("8/8/8/8/8/8/k7/K7 w - - 0 1 | -35 | 1.0").unwrap();
+        assert_eq!(result, 1.0);
+    }
+
+    #[test]
+    fn test_epoch_reduces_error() {
+        let entries: Vec<Entry> = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1 [0.0]",
+            "rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 [1.0]",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 [0.5]",
+        ]
+        .iter()
+        .map(|line| {
+            let (board, result) = parse_labeled_position(line).unwrap();
+            Entry { features: extract_features(&board), result }
+        })
+        .collect();
+        let mut tuner = Tuner::new(entries, Params::current());
+        tuner.threads = 2;
+        let before = tuner.error(tuner.k);
+        let mut after = before;
+        for _ in 0..10 {
+            after = tuner.epoch();
+        }
+        assert!(after < before);
+        assert!(tuner.params.to_pst_source().contains("const KING_EG_PST: [i32; 64] = ["));
+    }
+}
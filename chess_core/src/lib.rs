@@ -4,4 +4,4 @@ pub mod engine;
 pub mod stockfish;
 pub mod data;
 pub mod performance;
-
+pub mod training;
\ No newline at end of file
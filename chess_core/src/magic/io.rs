@@ -1,32 +1,24 @@
-use crate::magic::magicgen::generate_magics_attack_tables;
+use crate::magic::magicgen::{MagicEntry, MagicSearch};
 use std::fs::{File};
 use std::io::{BufWriter, Write};
 use std::path::Path;
 
-const MAGIC_FILE_PATH: &str = "/Users/dominik/Documents/code/chess/blaze/src/data/magics.rs";
-const ATTACK_FILE_PATH: &str = "/Users/dominik/Documents/code/chess/blaze/src/data/attacks.rs";
-
-pub fn precalc_magics_to_file() -> std::io::Result<()> {
-    // Generate and write ROOK_MAGICS to the file.
-    let rook_magics_attacks = generate_magics_attack_tables(true);
-    let rook_magics = rook_magics_attacks.0; 
-    let rook_attacks = rook_magics_attacks.1; 
-
-     // Generate and write BISHOP_MAGICS to the file.
-    let bishop_magics_attacks = generate_magics_attack_tables(false);
-    let bishop_magics = bishop_magics_attacks.0; 
-    let bishop_attacks = bishop_magics_attacks.1; 
-
+/// Writes `magics.rs` in the format of `data/` into `dir`. The attack tables are
+/// not written, build.rs derives them from the magics.
+pub fn write_magics_file(dir: &Path, rook_magics: &[MagicEntry], bishop_magics: &[MagicEntry], search: &MagicSearch) -> std::io::Result<()> {
+    let mut command = format!("generate_magic --out DIR --seed {}", search.seed);
+    if search.dense {
+        command += &format!(" --dense --attempts {}", search.attempts);
+    }
 
     // Create or truncate the output file.
-    let path = Path::new(MAGIC_FILE_PATH);
-    let file = File::create(path)?;
+    let file = File::create(dir.join("magics.rs"))?;
     let mut writer = BufWriter::new(file); // Use a buffered writer for efficiency
 
 
     // Write the header and MagicEntry struct definition to the file.
     writeln!(writer, "// This file is auto-generated. Do not edit manually.")?;
-    writeln!(writer, "// Run the program containing precalc_magics_to_file to regenerate it.")?;
+    writeln!(writer, "// Regenerate it with `{}`.", command)?;
     writeln!(writer)?;
     writeln!(writer, "#[derive(Debug, Clone, Copy)]")?;
     writeln!(writer, "#[repr(C)] // Optional: for layout guarantees")?;
@@ -81,37 +73,5 @@ pub fn precalc_magics_to_file() -> std::io::Result<()> {
     writeln!(writer, "];")?;
 
     // Ensure all buffered data is written to the file.
-    writer.flush()?;
-
-
-    let path = Path::new(ATTACK_FILE_PATH);
-    let file = File::create(path)?;
-    let mut writer = BufWriter::new(file); // Use a buffered writer for efficiency
-
-
-    // Write Bishop attacks
-    writeln!(writer, "// This file is auto-generated. Do not edit manually.")?;
-    writeln!(writer, "// Run the program containing precalc_magics_to_file to regenerate it.")?;
-    writeln!(writer)?;
-    writeln!(writer, "pub static BISHOP_ATTACKS: [u64; {}] = [", bishop_attacks.len())?;
-    for (_index, entry) in bishop_attacks.iter().enumerate() {
-        writeln!(
-            writer,
-            "    {:#018X},",
-            entry.0
-        )?;
-    }
-    writeln!(writer, "];\n")?;
-
-    writeln!(writer)?;
-    writeln!(writer, "pub static ROOK_ATTACKS: [u64; {}] = [", rook_attacks.len())?;
-    for (_index, entry) in rook_attacks.iter().enumerate() {
-        writeln!(
-            writer,
-            "    {:#018X},",
-            entry.0
-        )?;
-    }
-    writeln!(writer, "];\n")?;
-    Ok(())
+    writer.flush()
 }
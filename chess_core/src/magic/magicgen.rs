@@ -1,6 +1,8 @@
 use crate::types::bitboard::Bitboard;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Serialize, Deserialize};
+use std::fmt;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub struct MagicEntry {
@@ -127,7 +129,7 @@ pub fn calculate_rook_attacks(sq: usize, blockers: Bitboard) -> Bitboard {
     attacks
 }
 
-fn calculate_bishop_attacks(sq: usize, blockers: Bitboard) -> Bitboard {
+pub fn calculate_bishop_attacks(sq: usize, blockers: Bitboard) -> Bitboard {
     let mut attacks = Bitboard::new(0);
     let r_start = sq / 8;
     let f_start = sq % 8;
@@ -154,155 +156,204 @@ fn calculate_bishop_attacks(sq: usize, blockers: Bitboard) -> Bitboard {
 }
 
 
-pub fn generate_magic(sq:u8, is_rook:bool, num_index_bits:u8, offset: usize) -> Option<MagicEntry>{
-    let blockers_subset = if is_rook {
-        get_blocker_subsets(generate_rook_blocker_mask(sq))
-    } else {
-        get_blocker_subsets(generate_bishop_blocker_mask(sq))
-    };
-
-    let blocker_mask = if is_rook {
-        generate_rook_blocker_mask(sq)
-    } else {
-        generate_bishop_blocker_mask(sq)
-    };
-
-    let mut attacks = Vec::<Bitboard>::new();
-    for &blockers in &blockers_subset{
-        if is_rook{
-            attacks.push(calculate_rook_attacks(sq as usize, blockers));
-        }else{
-            attacks.push(calculate_bishop_attacks(sq as usize, blockers));
+/// How to search for magics. The same settings always give the same tables.
+#[derive(Debug, Clone, Copy)]
+pub struct MagicSearch {
+    pub seed: u64,
+    /// Keep searching after the first working magic for one whose highest used
+    /// index is lower, and let the tables of different squares overlap.
+    pub dense: bool,
+    /// Candidates tried per square in a dense search.
+    pub attempts: u64,
+}
+
+impl Default for MagicSearch {
+    fn default() -> Self {
+        MagicSearch { seed: 0, dense: false, attempts: 100_000 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MagicError {
+    /// No candidate worked for the square.
+    NotFound { sq: u8, is_rook: bool },
+    /// A table entry differs from the calculated attacks.
+    WrongAttacks { sq: u8, is_rook: bool, blockers: Bitboard },
+}
+
+impl fmt::Display for MagicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let piece = |is_rook: bool| if is_rook { "rook" } else { "bishop" };
+        match self {
+            MagicError::NotFound { sq, is_rook } => write!(f, "no {} magic found for square {}", piece(*is_rook), sq),
+            MagicError::WrongAttacks { sq, is_rook, blockers } => {
+                write!(f, "wrong {} attacks on square {} for blockers {:#018x}", piece(*is_rook), sq, blockers.0)
+            }
         }
     }
+}
+
+fn calculate_attacks(sq: usize, blockers: Bitboard, is_rook: bool) -> Bitboard {
+    if is_rook { calculate_rook_attacks(sq, blockers) } else { calculate_bishop_attacks(sq, blockers) }
+}
 
-    let mut rng = rand::rng();
-    let max_len = 1u64<<(num_index_bits);
-    let mut best_len = max_len as usize;
-    let mut magic_entry: MagicEntry = MagicEntry {mask: blocker_mask,magic: 0, shift: 0, offset: offset, size: 0};
-    for _attempt in 0..1_000_000_000{
+/// Finds a magic for `sq` indexing into at most `1 << num_index_bits` entries. Blocker
+/// sets with the same attacks may share an entry. `offset` and `size` are set later by
+/// `reformat_magics_attack_tables`.
+pub fn generate_magic(sq: u8, is_rook: bool, num_index_bits: u8, rng: &mut StdRng, search: &MagicSearch) -> Option<MagicEntry> {
+    let blocker_mask = if is_rook { generate_rook_blocker_mask(sq) } else { generate_bishop_blocker_mask(sq) };
+    let blockers_subset = get_blocker_subsets(blocker_mask);
+    let attacks: Vec<Bitboard> = blockers_subset.iter().map(|&blockers| calculate_attacks(sq as usize, blockers, is_rook)).collect();
+
+    let shift = 64 - num_index_bits;
+    let mut used = vec![Bitboard::new(0); 1 << num_index_bits];
+    let mut best: Option<MagicEntry> = None;
+    let attempts = if search.dense { search.attempts } else { u64::MAX };
+    for _attempt in 0..attempts {
         let magic_candidate: u64 = rng.random::<u64>() & rng.random::<u64>() & rng.random::<u64>(); // "Sparse" random numbers often work better
-        let mut used_indices = vec![0u64; 1 << num_index_bits];
-        let mut occupied = vec![false; 1 << num_index_bits];
+        used.fill(Bitboard::new(0));
+        let mut len = 0;
         let mut possible = true;
-
-        for n in 0..blockers_subset.len(){
-            let blocker = blockers_subset[n];
-            let attack = attacks[n];
-            let index = ((blocker * magic_candidate).0 >> 64-num_index_bits) as usize;
-            if occupied[index]{
+        for (&blocker, &attack) in blockers_subset.iter().zip(attacks.iter()) {
+            let index = ((blocker * magic_candidate).0 >> shift) as usize;
+            // Attacks are never empty, so an empty entry is unused
+            if used[index] != 0 && used[index] != attack {
                 possible = false;
                 break;
             }
-            occupied[index] = true;
-            used_indices[index] = attack.0;
+            used[index] = attack;
+            len = len.max(index + 1);
         }
-        if possible{
-            let len = occupied.iter().rposition(|&x| x != false).unwrap_or(0) + 1;
-            if len < best_len{
-                println!("Size ({}): {} of {}, optimal: {}, attempt: {}", sq, len, max_len, attacks.len(), _attempt);
-                magic_entry = MagicEntry {
-                    mask: blocker_mask,
-                    magic: magic_candidate,
-                    shift: 64 - num_index_bits,
-                    offset: offset,
-                    size: len,
-                };
-                best_len = len;
-                if len == attacks.len(){
-                    break;
-                }
+        if possible && best.is_none_or(|best| len < best.size) {
+            best = Some(MagicEntry { mask: blocker_mask, magic: magic_candidate, shift, offset: 0, size: len });
+            if !search.dense {
+                break;
             }
-        } 
-    }  
-    if best_len < max_len as usize{
-        Some(magic_entry)
-    }else{
-        println!("No magic found for square {}, {}", sq, attacks.len());
-        None
+        }
     }
+    best
 }
 
-pub fn generate_type_magics(index_bits: u8, is_rook: bool, offset: usize) -> Vec<MagicEntry>{
-    let mut magics = Vec::<MagicEntry>::new();
-    for sq in 0..64u8{
-        if is_rook{
-            if let Some(magic) = generate_magic(sq, true, index_bits, offset){
-                magics.push(magic);
-            }else{
-                panic!("No magic found for square {}", sq);
-            }
-        } else{
-            if let Some(magic) = generate_magic(sq, false, index_bits, offset){
-                magics.push(magic);
-             }else{
-            panic!("No magic found for square {}", sq);
-        }
-    }}
-    println!("Found {} magics", magics.len());
-    magics
+pub fn generate_type_magics(index_bits: u8, is_rook: bool, rng: &mut StdRng, search: &MagicSearch) -> Result<Vec<MagicEntry>, MagicError> {
+    (0..64u8)
+        .map(|sq| generate_magic(sq, is_rook, index_bits, rng, search).ok_or(MagicError::NotFound { sq, is_rook }))
+        .collect()
 }
 
-
-
-pub fn generate_type_attack_tables(magics: &Vec<MagicEntry>, is_rook: bool) -> Vec<Vec<Bitboard>>{
-    println!("magics len {}", magics.len());
-    let mut attack_tables = Vec::<Vec<Bitboard>>::new();
-        if is_rook{
-            for i in 0..64usize{
-                let magic = magics[i];
-                let blockers_subset = get_blocker_subsets(magic.mask);
-                let mut table = vec![Bitboard::new(0); magic.size];
-                for &blockers in &blockers_subset{
-                    let attacks = calculate_rook_attacks(i, blockers);
-                    let index = (blockers * magic.magic).0 >> magic.shift;
-                    table[index as usize] = attacks;
-                }
-                attack_tables.push(table);
+/// The attack table of every square, unused entries are empty.
+pub fn generate_type_attack_tables(magics: &[MagicEntry], is_rook: bool) -> Vec<Vec<Bitboard>> {
+    magics
+        .iter()
+        .enumerate()
+        .map(|(sq, magic)| {
+            let mut table = vec![Bitboard::new(0); magic.size];
+            for blockers in get_blocker_subsets(magic.mask) {
+                let index = (blockers * magic.magic).0 >> magic.shift;
+                table[index as usize] = calculate_attacks(sq, blockers, is_rook);
             }
-        }else{
-            for i in 0..64usize{
-                let magic = magics[i];
-                let blockers_subset = get_blocker_subsets(magic.mask);
-                let mut table = vec![Bitboard::new(0); magic.size];
-                for &blockers in &blockers_subset{
-                    let attacks = calculate_bishop_attacks(i, blockers);
-                    let index = (blockers * magic.magic).0 >> magic.shift;
-                    table[index as usize] = attacks;
-                }
-                if let Some(last_nonzero_index) = table.iter().rposition(|&x| x != 0) {
-                    let new_length = last_nonzero_index + 1;
-                    table.truncate(new_length);
-                } 
-                attack_tables.push(table);
+            table
+        })
+        .collect()
+}
+
+/// Puts the tables of all squares into one and sets the offsets. With `overlap` a table
+/// starts at the first offset where it agrees with everything already placed, so the
+/// unused entries of one square can hold attacks of another.
+pub fn reformat_magics_attack_tables(mut magics: Vec<MagicEntry>, attack_tables: Vec<Vec<Bitboard>>, overlap: bool)
+ -> (Vec<MagicEntry>, Vec<Bitboard>) {
+    let mut flat: Vec<Bitboard> = Vec::new();
+    for (magic, table) in magics.iter_mut().zip(attack_tables.iter()) {
+        let fits = |offset: usize| {
+            table.iter().enumerate().all(|(i, &attack)| {
+                attack == 0 || flat.get(offset + i).is_none_or(|&placed| placed == 0 || placed == attack)
+            })
+        };
+        let offset = if overlap { (0..=flat.len()).find(|&offset| fits(offset)).unwrap() } else { flat.len() };
+        if flat.len() < offset + table.len() {
+            flat.resize(offset + table.len(), Bitboard::new(0));
+        }
+        for (i, &attack) in table.iter().enumerate() {
+            if attack != 0 {
+                flat[offset + i] = attack;
             }
         }
-    attack_tables
+        magic.offset = offset;
+        magic.size = table.len();
+    }
+    (magics, flat)
 }
 
-pub fn reformat_magics_attack_tables(mut magics: Vec<MagicEntry>, attack_tables: Vec<Vec<Bitboard>>)
- -> (Vec<MagicEntry>, Vec<Bitboard>) {
-    let mut offset = 0;
-    for i in 0..magics.len(){
-        magics[i].offset = offset;
-        magics[i].size = attack_tables[i].len();
-        offset += attack_tables[i].len();
+/// Checks the table entry of every blocker set on every square against the calculated attacks.
+pub fn verify_magics(magics: &[MagicEntry], table: &[Bitboard], is_rook: bool) -> Result<(), MagicError> {
+    for (sq, magic) in magics.iter().enumerate() {
+        for blockers in get_blocker_subsets(magic.mask) {
+            let index = ((blockers * magic.magic).0 >> magic.shift) as usize;
+            let found = if index < magic.size { table.get(magic.offset + index).copied() } else { None };
+            if found != Some(calculate_attacks(sq, blockers, is_rook)) {
+                return Err(MagicError::WrongAttacks { sq: sq as u8, is_rook, blockers });
+            }
+        }
     }
-    let table: Vec<Bitboard> = attack_tables.into_iter().flatten().collect();
-    (magics, table)
+    Ok(())
+}
+
+/// Magics and the verified flat attack table for rooks (13 index bits) or bishops (12).
+pub fn generate_magics_attack_tables(is_rook: bool, search: &MagicSearch) -> Result<(Vec<MagicEntry>, Vec<Bitboard>), MagicError> {
+    // Separate streams, so rooks and bishops do not depend on each other
+    let mut rng = StdRng::seed_from_u64(search.seed ^ if is_rook { 0x524F_4F4B } else { 0x4249_5348 });
+    let index_bits = if is_rook { 13 } else { 12 };
+    let magics = generate_type_magics(index_bits, is_rook, &mut rng, search)?;
+    let attack_tables = generate_type_attack_tables(&magics, is_rook);
+    let (magics, table) = reformat_magics_attack_tables(magics, attack_tables, search.dense);
+    verify_magics(&magics, &table, is_rook)?;
+    Ok((magics, table))
+}
+
+
+
+/// Entry of a PEXT indexed attack table. Extracting the `mask` bits of the blockers
+/// gives a dense index, so no magic is needed and the tables have no holes.
+#[derive(Debug, Clone, Copy)]
+pub struct PextEntry {
+    pub mask: Bitboard,
+    pub offset: usize,
 }
 
+/// Software version of the BMI2 `pext` instruction, only used to fill the tables.
+pub fn pext_software(value: u64, mut mask: u64) -> u64 {
+    let mut result = 0;
+    let mut bit = 1;
+    while mask != 0 {
+        let lowest = mask & mask.wrapping_neg();
+        if value & lowest != 0 {
+            result |= bit;
+        }
+        mask ^= lowest;
+        bit <<= 1;
+    }
+    result
+}
 
-pub fn generate_magics_attack_tables(is_rook: bool) -> (Vec<MagicEntry>, Vec<Bitboard>){
-    if is_rook{
-        let rook_magics = generate_type_magics(13, true, 0);
-        let rook_attack_table = generate_type_attack_tables(&rook_magics, true);
-        return reformat_magics_attack_tables(rook_magics, rook_attack_table)
-    }else{
-        let bishop_magics = generate_type_magics(12, false, 0);
-        let bishop_attack_table = generate_type_attack_tables(&bishop_magics, false);
-        reformat_magics_attack_tables(bishop_magics, bishop_attack_table)
+/// Masks and the flat attack table for PEXT lookups, built from the same blocker
+/// masks and attack calculation as the magics.
+pub fn generate_pext_attack_tables(is_rook: bool) -> (Vec<PextEntry>, Vec<Bitboard>) {
+    let mut entries = Vec::with_capacity(64);
+    let mut table = Vec::new();
+    for sq in 0..64u8 {
+        let mask = if is_rook { generate_rook_blocker_mask(sq) } else { generate_bishop_blocker_mask(sq) };
+        let offset = table.len();
+        table.resize(offset + (1 << mask.count_set()), Bitboard::new(0));
+        for blockers in get_blocker_subsets(mask) {
+            let attacks = if is_rook {
+                calculate_rook_attacks(sq as usize, blockers)
+            } else {
+                calculate_bishop_attacks(sq as usize, blockers)
+            };
+            table[offset + pext_software(blockers.0, mask.0) as usize] = attacks;
+        }
+        entries.push(PextEntry { mask, offset });
     }
+    (entries, table)
 }
 
 
@@ -340,6 +391,46 @@ use super::*;
         assert_eq!(blockers.len(), 4096);
     }
 
+    #[test]
+    fn test_checked_in_magics_are_correct() {
+        use crate::data::magics::{BISHOP_MAGICS, ROOK_MAGICS};
+        use crate::data::attacks::{BISHOP_ATTACKS, ROOK_ATTACKS};
+        let convert = |magics: &[crate::data::magics::MagicEntry]| -> Vec<MagicEntry> {
+            magics.iter().map(|m| MagicEntry { mask: Bitboard(m.mask), magic: m.magic, shift: m.shift, offset: m.offset, size: m.size }).collect()
+        };
+        let table = |attacks: &[u64]| -> Vec<Bitboard> { attacks.iter().map(|&a| Bitboard(a)).collect() };
+        assert_eq!(verify_magics(&convert(&ROOK_MAGICS), &table(&ROOK_ATTACKS), true), Ok(()));
+        assert_eq!(verify_magics(&convert(&BISHOP_MAGICS), &table(&BISHOP_ATTACKS), false), Ok(()));
+    }
+
+    #[test]
+    fn test_generation_is_reproducible() {
+        let search = MagicSearch { seed: 3, ..MagicSearch::default() };
+        let (magics, table) = generate_magics_attack_tables(true, &search).unwrap();
+        let (again, _) = generate_magics_attack_tables(true, &search).unwrap();
+        assert!(magics.iter().zip(again.iter()).all(|(a, b)| a.magic == b.magic && a.offset == b.offset));
+
+        let mut broken = table.clone();
+        broken[magics[5].offset] = Bitboard(0);
+        assert!(matches!(verify_magics(&magics, &broken, true), Err(MagicError::WrongAttacks { sq: 5, is_rook: true, .. })));
+
+        let sparse = generate_magics_attack_tables(false, &search).unwrap().1;
+        let dense = generate_magics_attack_tables(false, &MagicSearch { dense: true, attempts: 200, ..search }).unwrap().1;
+        assert!(dense.len() < sparse.len());
+    }
+
+    #[test]
+    fn test_pext_tables_are_dense() {
+        assert_eq!(pext_software(0b1011_0100, 0b1111_0000), 0b1011);
+        assert_eq!(pext_software(0b0100_0101, 0b0101_0101), 0b1011);
+        let (rooks, rook_table) = generate_pext_attack_tables(true);
+        let (bishops, bishop_table) = generate_pext_attack_tables(false);
+        assert_eq!(rook_table.len(), 102_400);
+        assert_eq!(bishop_table.len(), 5_248);
+        assert_eq!(rooks[63].offset + 4096, rook_table.len());
+        assert_eq!(bishops[1].offset, 64);
+    }
+
     #[test]
     fn test_number_blockers_bishop() {
         let mask_bishop = generate_bishop_blocker_mask(0);
@@ -42,6 +42,6 @@ fn main() {
 
     //println!("Searched {} Nodes in {}s ({}kN/s)", num_nodes,  st.elapsed().as_secs_f32(), num_nodes as u128 / std::cmp::max(st.elapsed().as_millis(), 1));
     //let path = Path::new("/Users/dominik/Documents/code/chess/blaze/src/nn/training/data/lichess_db_standard_rated_2013-01.pgn");
-    //let games = load_pgn_games_from_file(path).expect("Failed to load PGN games");
+    //let games = PgnReader::open(path).expect("Failed to open PGN file");
     // You can now use `games` as needed, or remove this line if not needed.
 }   
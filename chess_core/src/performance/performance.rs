@@ -1,4 +1,8 @@
 use crate::types::board::board::Board;
+use crate::types::board::transposition::TranspositionTable;
+use crate::engine::eval::HandcraftedEval;
+use crate::engine::perft::perft;
+use crate::engine::search::{negamax, SearchControl};
 use std::{str::FromStr, time::{Duration, Instant}};
 
 #[derive(Debug)]
@@ -66,6 +70,24 @@ fn run_benchmark_for_fen(fen: &str) -> Option<BenchmarkResult> {
     })
 }
 
+/// Nodes per second of a perft to `depth`, which is nothing but make/unmake and move generation.
+pub fn perft_nps(fen: &str, depth: u8) -> (u64, f64) {
+    let mut board = Board::from_str(fen).unwrap();
+    let start_time = Instant::now();
+    let nodes = perft(&mut board, depth);
+    (nodes, nodes as f64 / start_time.elapsed().as_secs_f64())
+}
+
+/// Nodes per second of a fixed depth search with the handcrafted evaluation.
+pub fn search_nps(fen: &str, depth: i8) -> (u64, f64) {
+    let mut board = Board::from_str(fen).unwrap();
+    let mut tt = TranspositionTable::new(16);
+    let start_time = Instant::now();
+    let control = SearchControl::until(start_time + Duration::from_secs(3600));
+    let (_, _, nodes, _) = negamax(&mut board, &mut HandcraftedEval, &mut tt, depth, depth, i32::MIN + 1, i32::MAX, &control);
+    (nodes, nodes as f64 / start_time.elapsed().as_secs_f64())
+}
+
 pub fn test() {
     println!("--- Chess Engine Performance Benchmark ---");
 
@@ -118,4 +140,26 @@ pub fn test() {
     } else {
         println!("No FENs were successfully benchmarked.");
     }
+
+    println!();
+    println!("--- Nodes Per Second ---");
+    let (mut total_perft_nodes, mut total_perft_time) = (0u64, 0f64);
+    let (mut total_search_nodes, mut total_search_time) = (0u64, 0f64);
+    for (fen, perft_depth, search_depth) in [
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 5, 6),
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 4, 4),
+        ("8/2p5/3p4/KP5r/1P3p1k/8/4P1P1/8 w - - 0 1", 6, 8),
+    ] {
+        let (nodes, nps) = perft_nps(fen, perft_depth);
+        total_perft_nodes += nodes;
+        total_perft_time += nodes as f64 / nps;
+        let (search_nodes, search_nps) = search_nps(fen, search_depth);
+        total_search_nodes += search_nodes;
+        total_search_time += search_nodes as f64 / search_nps;
+        println!("{}", fen);
+        println!("  Perft {}: {} nodes, {:.0} nps", perft_depth, nodes, nps);
+        println!("  Search {}: {} nodes, {:.0} nps", search_depth, search_nodes, search_nps);
+    }
+    println!("Perft: {:.0} nps", total_perft_nodes as f64 / total_perft_time);
+    println!("Search: {:.0} nps", total_search_nodes as f64 / total_search_time);
 }
\ No newline at end of file
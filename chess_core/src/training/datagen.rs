@@ -0,0 +1,295 @@
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::engine::eval::HandcraftedEval;
+use crate::engine::search::{negamax, SearchControl};
+use crate::types::board::board::Board;
+use crate::types::board::outcome::{Outcome, Termination};
+use crate::types::board::transposition::TranspositionTable;
+use crate::types::color::Color;
+use crate::types::moves::Move;
+use super::packed::{PackedBoard, WdlResult};
+
+const MAX_DEPTH: i8 = 64;
+// Scores beyond this are mate scores and are not written out
+const MATE_BOUND: i32 = 30_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// `FEN | score | result` lines, score and result from white's point of view.
+    Text,
+    /// `PackedBoard`s back to back.
+    Packed,
+}
+
+#[derive(Debug, Clone)]
+pub struct DatagenConfig {
+    pub games: u64,
+    pub threads: usize,
+    pub seed: u64,
+    /// Soft node limit of the search for every move.
+    pub nodes: u64,
+    /// Random plies played from the start position before the engine takes over.
+    pub random_plies: usize,
+    /// Games still running after this many plies are scored as draws.
+    pub max_plies: usize,
+    /// A game is won once the score stays above `win_score` for `win_plies` plies.
+    pub win_score: i32,
+    pub win_plies: usize,
+    /// A game is drawn once the score stays within `draw_score` for `draw_plies`
+    /// plies, but not before `draw_min_ply`.
+    pub draw_score: i32,
+    pub draw_plies: usize,
+    pub draw_min_ply: usize,
+    pub format: OutputFormat,
+}
+
+impl Default for DatagenConfig {
+    fn default() -> Self {
+        DatagenConfig {
+            games: 1000,
+            threads: 1,
+            seed: 0,
+            nodes: 5000,
+            random_plies: 8,
+            max_plies: 400,
+            win_score: 1000,
+            win_plies: 4,
+            draw_score: 10,
+            draw_plies: 10,
+            draw_min_ply: 60,
+            format: OutputFormat::Text,
+        }
+    }
+}
+
+/// Writes `position` in `format` with the final game `result`.
+pub fn write_position<W: Write>(out: &mut W, position: &PackedBoard, format: OutputFormat, result: WdlResult) -> io::Result<()> {
+    let mut packed = *position;
+    packed.result = result as u8;
+    match format {
+        OutputFormat::Text => writeln!(out, "{}", packed.to_text()),
+        OutputFormat::Packed => out.write_all(&packed.to_bytes()),
+    }
+}
+
+/// Seed of game `index`, so every game can be replayed on its own.
+pub fn game_seed(seed: u64, index: u64) -> u64 {
+    // SplitMix64 finalizer
+    let mut z = seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9E3779B97F4A7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// Searches with iterative deepening until `nodes` is reached, returns the
+/// score relative to the side to move and the best move.
+pub fn search_nodes(board: &mut Board, tt: &mut TranspositionTable, nodes: u64) -> (i32, Option<Move>) {
+    let control = SearchControl::until(Instant::now() + Duration::from_secs(3600));
+    let mut evaluator = HandcraftedEval;
+    let mut searched = 0u64;
+    let mut best = (0, None);
+    for depth in 1..=MAX_DEPTH {
+        let (score, mv, num, _) = negamax(board, &mut evaluator, tt, depth, depth, -i32::MAX, i32::MAX, &control);
+        searched += num;
+        if mv.is_some() {
+            best = (score, mv);
+        }
+        if searched >= nodes {
+            break;
+        }
+    }
+    best
+}
+
+/// Plays random plies from the start position, returns `None` if the game ended on the way.
+fn random_opening(rng: &mut StdRng, plies: usize) -> Option<Board> {
+    let mut board = Board::start();
+    for _ in 0..plies {
+        let moves = board.generate_all_moves();
+        if moves.is_empty() {
+            return None;
+        }
+        let mv = *moves.get_index(rng.random_range(0..moves.len())).unwrap();
+        board.apply_move(&mv);
+    }
+    let moves = board.generate_all_moves();
+    if moves.is_empty() { None } else { Some(board) }
+}
+
+/// Plays one self-play game, returns the quiet positions it went through and how it ended.
+/// `tt` is cleared first so the game only depends on `rng`.
+pub fn play_game(rng: &mut StdRng, tt: &mut TranspositionTable, config: &DatagenConfig) -> (Vec<PackedBoard>, Outcome) {
+    tt.clear();
+    let mut board = loop {
+        if let Some(board) = random_opening(rng, config.random_plies) {
+            break board;
+        }
+    };
+
+    let mut samples = Vec::new();
+    let mut win_streak = (Color::None, 0usize);
+    let mut draw_streak = 0usize;
+
+    for ply in 0.. {
+        if let Some(outcome) = board.outcome() {
+            return (samples, outcome);
+        }
+        if ply >= config.max_plies {
+            return (samples, Outcome::draw(Termination::Adjudication));
+        }
+
+        let (score, best_move) = search_nodes(&mut board, tt, config.nodes);
+        let Some(best_move) = best_move else {
+            return (samples, Outcome::draw(Termination::Adjudication));
+        };
+        let white_score = if board.state.color == Color::Black { -score } else { score };
+
+        // Adjudication
+        if white_score.abs() >= config.win_score {
+            let leader = if white_score > 0 { Color::White } else { Color::Black };
+            win_streak = if win_streak.0 == leader { (leader, win_streak.1 + 1) } else { (leader, 1) };
+            if win_streak.1 >= config.win_plies {
+                return (samples, Outcome::win(leader, Termination::Adjudication));
+            }
+        } else {
+            win_streak = (Color::None, 0);
+        }
+        if white_score.abs() <= config.draw_score {
+            draw_streak += 1;
+            if draw_streak >= config.draw_plies && ply >= config.draw_min_ply {
+                return (samples, Outcome::draw(Termination::Adjudication));
+            }
+        } else {
+            draw_streak = 0;
+        }
+
+        // Only quiet positions are useful training targets
+        let in_check = board.get_checker().1 > 0;
+        let noisy = best_move.is_capture() || best_move.is_promotion();
+        if !in_check && !noisy && white_score.abs() < MATE_BOUND {
+            samples.push(PackedBoard::from_board(&board, white_score as i16, WdlResult::Draw));
+        }
+
+        board.apply_move(&best_move);
+    }
+    unreachable!()
+}
+
+/// Plays `config.games` games on `config.threads` threads and writes every sample to `out`.
+///
+/// Game `i` is played by thread `i % threads` with the seed `game_seed(seed, i)`,
+/// so a run is reproducible for the same seed and thread count. Games are
+/// written in the order they finish. Returns the number of written positions.
+pub fn run<W: Write + Send>(config: &DatagenConfig, out: W) -> io::Result<u64> {
+    let out = Mutex::new(out);
+    let positions = AtomicU64::new(0);
+    let games_done = AtomicU64::new(0);
+    let threads = config.threads.max(1) as u64;
+    let start = Instant::now();
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|thread_index| {
+                let (out, positions, games_done) = (&out, &positions, &games_done);
+                scope.spawn(move || -> io::Result<()> {
+                    let mut tt = TranspositionTable::new(16);
+                    for game in (thread_index..config.games).step_by(threads as usize) {
+                        let mut rng = StdRng::seed_from_u64(game_seed(config.seed, game));
+                        let (samples, outcome) = play_game(&mut rng, &mut tt, config);
+                        let result = WdlResult::from(outcome);
+
+                        let mut buffer = Vec::new();
+                        for sample in samples.iter() {
+                            write_position(&mut buffer, sample, config.format, result)?;
+                        }
+                        out.lock().unwrap().write_all(&buffer)?;
+
+                        let total = positions.fetch_add(samples.len() as u64, Ordering::Relaxed) + samples.len() as u64;
+                        let done = games_done.fetch_add(1, Ordering::Relaxed) + 1;
+                        if done % 100 == 0 {
+                            let elapsed = start.elapsed().as_secs_f32();
+                            eprintln!("{} games, {} positions, {:.0} pos/s", done, total, total as f32 / elapsed);
+                        }
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        handles.into_iter().try_for_each(|h| h.join().unwrap())
+    })?;
+
+    out.into_inner().unwrap().flush()?;
+    Ok(positions.load(Ordering::Relaxed))
+}
+
+
+
+
+/// ######################################################
+/// ################### TESTING ##########################
+/// ######################################################
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quick_config() -> DatagenConfig {
+        DatagenConfig {
+            games: 2,
+            threads: 2,
+            seed: 42,
+            nodes: 200,
+            max_plies: 40,
+            ..DatagenConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_datagen_is_reproducible() {
+        let config = quick_config();
+        let play = |index| {
+            let mut rng = StdRng::seed_from_u64(game_seed(config.seed, index));
+            let mut tt = TranspositionTable::new(1);
+            let (samples, result) = play_game(&mut rng, &mut tt, &config);
+            (samples.iter().map(|s| s.to_fen()).collect::<Vec<_>>(), result)
+        };
+        assert_eq!(play(0), play(0));
+        assert_ne!(play(0).0, play(1).0);
+    }
+
+    #[test]
+    fn test_game_independent_of_previous_games() {
+        let config = quick_config();
+        let play = |tt: &mut TranspositionTable, index| {
+            let mut rng = StdRng::seed_from_u64(game_seed(config.seed, index));
+            let (samples, result) = play_game(&mut rng, tt, &config);
+            (samples.iter().map(|s| s.to_fen()).collect::<Vec<_>>(), result)
+        };
+        let alone = play(&mut TranspositionTable::new(1), 1);
+        // A replay of the same game would hit every entry the first one left behind
+        let mut shared = TranspositionTable::new(1);
+        play(&mut shared, 0);
+        assert_eq!(play(&mut shared, 1), alone);
+        assert_eq!(play(&mut shared, 1), alone);
+    }
+
+    #[test]
+    fn test_text_output() {
+        let mut out = Vec::new();
+        let positions = run(&quick_config(), &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.lines().count() as u64, positions);
+        for line in text.lines() {
+            let fields: Vec<&str> = line.split(" | ").collect();
+            assert_eq!(fields.len(), 3, "{}", line);
+            assert!(fields[1].parse::<i32>().is_ok());
+            assert!(["0.0", "0.5", "1.0"].contains(&fields[2]));
+        }
+    }
+}
@@ -0,0 +1,2 @@
+pub mod datagen;
+pub mod packed;
\ No newline at end of file
@@ -0,0 +1,486 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+use std::str::FromStr;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use crate::types::board::board::{Board, FenError};
+use crate::types::board::outcome::Outcome;
+use crate::types::color::Color;
+use crate::types::piece::Piece;
+use crate::types::square::Square;
+
+/// Nibble of a rook that still has its castling right.
+const UNMOVED_ROOK: u8 = 6;
+const NO_EN_PASSANT: u8 = 64;
+
+/// Game result from white's point of view, as stored in a `PackedBoard`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WdlResult {
+    BlackWin = 0,
+    Draw = 1,
+    WhiteWin = 2,
+}
+
+impl From<Outcome> for WdlResult {
+    fn from(outcome: Outcome) -> Self {
+        match outcome.winner {
+            Some(Color::White) => WdlResult::WhiteWin,
+            Some(_) => WdlResult::BlackWin,
+            None => WdlResult::Draw,
+        }
+    }
+}
+
+impl WdlResult {
+    /// 1.0 for a white win, 0.5 for a draw and 0.0 for a black win.
+    pub fn as_f32(self) -> f32 {
+        self as u8 as f32 / 2.0
+    }
+
+    pub fn to_text(self) -> &'static str {
+        match self {
+            WdlResult::BlackWin => "0.0",
+            WdlResult::Draw => "0.5",
+            WdlResult::WhiteWin => "1.0",
+        }
+    }
+
+    /// Parses `1.0`/`0.5`/`0.0` as well as `1-0`/`1/2-1/2`/`0-1`.
+    pub fn from_text(s: &str) -> Option<Self> {
+        match s.trim() {
+            "1.0" | "1" | "1-0" => Some(WdlResult::WhiteWin),
+            "0.5" | "1/2-1/2" => Some(WdlResult::Draw),
+            "0.0" | "0" | "0-1" => Some(WdlResult::BlackWin),
+            _ => None,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(WdlResult::BlackWin),
+            1 => Some(WdlResult::Draw),
+            2 => Some(WdlResult::WhiteWin),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PackedError {
+    Io(io::Error),
+    InvalidFormat,
+    InvalidFen(FenError),
+    InvalidScore,
+    InvalidResult,
+    /// The board has more than 32 pieces and can not be packed.
+    TooManyPieces,
+}
+
+impl fmt::Display for PackedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackedError::Io(e) => write!(f, "{}", e),
+            PackedError::InvalidFormat => write!(f, "expected 'FEN | score | result'"),
+            PackedError::InvalidFen(e) => write!(f, "invalid FEN: {:?}", e),
+            PackedError::InvalidScore => write!(f, "invalid score"),
+            PackedError::InvalidResult => write!(f, "invalid result"),
+            PackedError::TooManyPieces => write!(f, "more than 32 pieces on the board"),
+        }
+    }
+}
+
+impl std::error::Error for PackedError {}
+
+impl From<io::Error> for PackedError {
+    fn from(e: io::Error) -> Self {
+        PackedError::Io(e)
+    }
+}
+
+/// A training position in 32 bytes.
+///
+/// | Bytes | Content                                                          |
+/// |-------|------------------------------------------------------------------|
+/// | 0-7   | occupancy bitboard                                               |
+/// | 8-23  | one nibble per occupied square in ascending square order, low    |
+/// |       | nibble first: piece type (6 = rook with castling right), bit 3   |
+/// |       | set for black                                                    |
+/// | 24    | en passant square (64 if none), bit 7 set if black is to move    |
+/// | 25    | halfmove clock                                                   |
+/// | 26-27 | fullmove number                                                  |
+/// | 28-29 | score in centipawns from white's point of view                   |
+/// | 30    | `WdlResult`                                                      |
+/// | 31    | unused                                                           |
+///
+/// All multi-byte fields are little-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PackedBoard {
+    pub occupancy: u64,
+    pub pieces: [u8; 16],
+    pub stm_ep: u8,
+    pub halfmove_clock: u8,
+    pub fullmove_number: u16,
+    pub score: i16,
+    pub result: u8,
+    pub extra: u8,
+}
+
+impl PackedBoard {
+    pub const BYTES: usize = 32;
+
+    /// Packs `board`. Boards with more than 32 pieces are rejected by `try_from_board`,
+    /// here the surplus pieces are dropped.
+    pub fn from_board(board: &Board, score: i16, result: WdlResult) -> Self {
+        let castling = board.state.castling;
+        let castle_rooks = [
+            (castling.white_king(), Square::H1, Piece::WhiteRook),
+            (castling.white_queen(), Square::A1, Piece::WhiteRook),
+            (castling.black_king(), Square::H8, Piece::BlackRook),
+            (castling.black_queen(), Square::A8, Piece::BlackRook),
+        ];
+
+        let mut occupancy = 0u64;
+        let mut pieces = [0u8; 16];
+        let mut count = 0;
+        for (sq, piece) in board.mailbox.iter().enumerate() {
+            if *piece == Piece::None {
+                continue;
+            }
+            if count == 32 {
+                break;
+            }
+            occupancy |= 1 << sq;
+            let mut nibble = *piece as u8 % 6;
+            if castle_rooks.iter().any(|&(right, rook_sq, rook)| right && rook_sq as usize == sq && rook == *piece) {
+                nibble = UNMOVED_ROOK;
+            }
+            nibble |= (*piece as u8 / 6) << 3;
+            pieces[count / 2] |= nibble << (4 * (count % 2));
+            count += 1;
+        }
+
+        let ep = match board.state.en_passant {
+            Square::None => NO_EN_PASSANT,
+            sq => sq.to_index(),
+        };
+        let stm = if board.state.color == Color::Black { 1 << 7 } else { 0 };
+
+        PackedBoard {
+            occupancy,
+            pieces,
+            stm_ep: ep | stm,
+            halfmove_clock: board.state.halfmove_clock,
+            fullmove_number: board.state.fullmove_number.min(u16::MAX as usize) as u16,
+            score,
+            result: result as u8,
+            extra: 0,
+        }
+    }
+
+    pub fn try_from_board(board: &Board, score: i16, result: WdlResult) -> Result<Self, PackedError> {
+        if board.mailbox.iter().filter(|p| **p != Piece::None).count() > 32 {
+            return Err(PackedError::TooManyPieces);
+        }
+        Ok(Self::from_board(board, score, result))
+    }
+
+    /// Pieces on their squares, castling rights included.
+    fn squares(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        let mut occupancy = self.occupancy;
+        let mut index = 0;
+        std::iter::from_fn(move || {
+            if occupancy == 0 {
+                return None;
+            }
+            let sq = occupancy.trailing_zeros() as u8;
+            occupancy &= occupancy - 1;
+            let nibble = (self.pieces[index / 2] >> (4 * (index % 2))) & 0xF;
+            index += 1;
+            Some((sq, nibble))
+        })
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        if self.stm_ep & (1 << 7) != 0 { Color::Black } else { Color::White }
+    }
+
+    pub fn wdl(&self) -> Option<WdlResult> {
+        WdlResult::from_u8(self.result)
+    }
+
+    /// The packed position as FEN.
+    pub fn to_fen(&self) -> String {
+        let mut mailbox = [None; 64];
+        let mut castling = String::new();
+        for (sq, nibble) in self.squares() {
+            let black = nibble & 0b1000 != 0;
+            let mut piece_type = nibble & 0b0111;
+            if piece_type == UNMOVED_ROOK {
+                piece_type = Piece::WhiteRook as u8;
+                castling.push(match (black, sq) {
+                    (false, 7) => 'K',
+                    (false, _) => 'Q',
+                    (true, 63) => 'k',
+                    (true, _) => 'q',
+                });
+            }
+            let piece = Piece::try_from(piece_type as usize + if black { 6 } else { 0 }).unwrap_or_default();
+            mailbox[sq as usize] = Some(piece.to_char());
+        }
+
+        let mut fen = String::with_capacity(90);
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match mailbox[rank * 8 + file] {
+                    Some(c) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(c);
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        let mut rights: Vec<char> = castling.chars().collect();
+        rights.sort_by_key(|c| "KQkq".find(*c));
+        let castling: String = if rights.is_empty() { "-".to_string() } else { rights.into_iter().collect() };
+        let ep = match self.stm_ep & 0x7F {
+            NO_EN_PASSANT => "-".to_string(),
+            sq => Square::new(sq).to_algebraic().unwrap_or_else(|| "-".to_string()),
+        };
+        format!(
+            "{} {} {} {} {} {}",
+            fen, self.side_to_move().to_string(), castling, ep, self.halfmove_clock, self.fullmove_number
+        )
+    }
+
+    pub fn to_board(&self) -> Result<Board, FenError> {
+        Board::from_str(&self.to_fen())
+    }
+
+    /// `FEN | score | result`, score and result from white's point of view.
+    pub fn to_text(&self) -> String {
+        let result = self.wdl().unwrap_or(WdlResult::Draw);
+        format!("{} | {} | {}", self.to_fen(), self.score, result.to_text())
+    }
+
+    pub fn from_text(line: &str) -> Result<Self, PackedError> {
+        let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+        if fields.len() != 3 {
+            return Err(PackedError::InvalidFormat);
+        }
+        let board = Board::from_str(fields[0]).map_err(PackedError::InvalidFen)?;
+        let score = fields[1].parse::<i16>().map_err(|_| PackedError::InvalidScore)?;
+        let result = WdlResult::from_text(fields[2]).ok_or(PackedError::InvalidResult)?;
+        Self::try_from_board(&board, score, result)
+    }
+
+    /// Identifies the position regardless of score, result and move counters.
+    pub fn position_key(&self) -> (u64, [u8; 16], u8) {
+        (self.occupancy, self.pieces, self.stm_ep)
+    }
+
+    pub fn from_bytes(bytes: &[u8; Self::BYTES]) -> Self {
+        PackedBoard {
+            occupancy: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            pieces: bytes[8..24].try_into().unwrap(),
+            stm_ep: bytes[24],
+            halfmove_clock: bytes[25],
+            fullmove_number: u16::from_le_bytes([bytes[26], bytes[27]]),
+            score: i16::from_le_bytes([bytes[28], bytes[29]]),
+            result: bytes[30],
+            extra: bytes[31],
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::BYTES] {
+        let mut bytes = [0u8; Self::BYTES];
+        bytes[0..8].copy_from_slice(&self.occupancy.to_le_bytes());
+        bytes[8..24].copy_from_slice(&self.pieces);
+        bytes[24] = self.stm_ep;
+        bytes[25] = self.halfmove_clock;
+        bytes[26..28].copy_from_slice(&self.fullmove_number.to_le_bytes());
+        bytes[28..30].copy_from_slice(&self.score.to_le_bytes());
+        bytes[30] = self.result;
+        bytes[31] = self.extra;
+        bytes
+    }
+}
+
+/// Reads `PackedBoard`s one after another from a byte stream.
+pub struct PackedReader<R: Read> {
+    inner: R,
+}
+
+impl<R: Read> PackedReader<R> {
+    pub fn new(inner: R) -> Self {
+        PackedReader { inner }
+    }
+}
+
+impl<R: Read> Iterator for PackedReader<R> {
+    type Item = io::Result<PackedBoard>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut bytes = [0u8; PackedBoard::BYTES];
+        let mut filled = 0;
+        while filled < bytes.len() {
+            match self.inner.read(&mut bytes[filled..]) {
+                Ok(0) if filled == 0 => return None,
+                Ok(0) => return Some(Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated packed position"))),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        Some(Ok(PackedBoard::from_bytes(&bytes)))
+    }
+}
+
+/// Writes `PackedBoard`s one after another to a byte stream.
+pub struct PackedWriter<W: Write> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> PackedWriter<W> {
+    pub fn new(inner: W) -> Self {
+        PackedWriter { inner, written: 0 }
+    }
+
+    pub fn write(&mut self, packed: &PackedBoard) -> io::Result<()> {
+        self.written += 1;
+        self.inner.write_all(&packed.to_bytes())
+    }
+
+    /// Number of positions written so far.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Shuffles positions reproducibly for `seed`.
+pub fn shuffle(positions: &mut [PackedBoard], seed: u64) {
+    positions.shuffle(&mut StdRng::seed_from_u64(seed));
+}
+
+/// Removes every position that already occurred earlier, keeping the first one.
+pub fn dedup(positions: &mut Vec<PackedBoard>) {
+    let mut seen = HashSet::with_capacity(positions.len());
+    positions.retain(|p| seen.insert(p.position_key()));
+}
+
+/// Converts `FEN | score | result` lines to packed positions, returns the number written.
+pub fn text_to_packed<R: BufRead, W: Write>(input: R, output: W) -> Result<u64, PackedError> {
+    let mut writer = PackedWriter::new(output);
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writer.write(&PackedBoard::from_text(&line)?)?;
+    }
+    writer.flush()?;
+    Ok(writer.written())
+}
+
+/// Converts packed positions to `FEN | score | result` lines, returns the number written.
+pub fn packed_to_text<R: Read, W: Write>(input: R, mut output: W) -> io::Result<u64> {
+    let mut written = 0;
+    for packed in PackedReader::new(input) {
+        writeln!(output, "{}", packed?.to_text())?;
+        written += 1;
+    }
+    output.flush()?;
+    Ok(written)
+}
+
+
+
+
+/// ######################################################
+/// ################### TESTING ##########################
+/// ######################################################
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FENS: [&str; 5] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w Kq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 17 60",
+        "r3k3/8/8/8/8/8/8/4K2R b Kq - 3 40",
+    ];
+
+    #[test]
+    fn test_board_round_trip() {
+        for fen in FENS {
+            let board = Board::from_str(fen).unwrap();
+            let packed = PackedBoard::from_board(&board, -123, WdlResult::BlackWin);
+            assert_eq!(packed.to_fen(), fen);
+            assert_eq!(PackedBoard::from_bytes(&packed.to_bytes()), packed);
+            assert_eq!(packed.to_board().unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let line = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 | 35 | 0.5";
+        let mut packed = Vec::new();
+        assert_eq!(text_to_packed(line.as_bytes(), &mut packed).unwrap(), 1);
+        assert_eq!(packed.len(), PackedBoard::BYTES);
+        let mut text = Vec::new();
+        assert_eq!(packed_to_text(packed.as_slice(), &mut text).unwrap(), 1);
+        assert_eq!(String::from_utf8(text).unwrap().trim(), line);
+        assert!(matches!(PackedBoard::from_text("8/8/8/8/8/8/k7/K7 w - - 0 1 | x | 0.5"), Err(PackedError::InvalidScore)));
+    }
+
+    #[test]
+    fn test_stream_shuffle_and_dedup() {
+        let mut positions: Vec<PackedBoard> = FENS
+            .iter()
+            .chain(FENS.iter())
+            .map(|fen| PackedBoard::from_board(&Board::from_str(fen).unwrap(), 0, WdlResult::Draw))
+            .collect();
+        let mut writer = PackedWriter::new(Vec::new());
+        for p in positions.iter() {
+            writer.write(p).unwrap();
+        }
+        let bytes = writer.into_inner();
+        let read: Vec<PackedBoard> = PackedReader::new(bytes.as_slice()).collect::<io::Result<_>>().unwrap();
+        assert_eq!(read, positions);
+        assert!(PackedReader::new(&bytes[..40]).nth(1).unwrap().is_err());
+
+        let mut shuffled = positions.clone();
+        shuffle(&mut shuffled, 1);
+        let mut again = positions.clone();
+        shuffle(&mut again, 1);
+        assert_eq!(shuffled, again);
+
+        dedup(&mut positions);
+        assert_eq!(positions.len(), FENS.len());
+    }
+}
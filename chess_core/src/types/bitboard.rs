@@ -1,4 +1,5 @@
 use std::ops::BitOrAssign;
+use std::ops::BitXorAssign;
 use std::ops::Sub;
 use std::ops::BitAnd;
 use std::ops::BitOr;
@@ -65,16 +66,29 @@ impl Bitboard {
         self.0.count_ones()
     }
 
-    pub fn to_squares(&self) -> Vec<Square> {
-        let mut squares = Vec::with_capacity(self.count_set() as usize); // Pre-allocate capacity
-        let mut bits = self.0; // Get the u64 representation
+    /// Removes the lowest set square and returns it, the board must not be empty.
+    pub fn pop_lsb(&mut self) -> Square {
+        let lsb = self.lsb();
+        self.0 &= self.0 - 1;
+        lsb
+    }
 
-        while bits != 0 {
-            let index = bits.trailing_zeros() as u8;
-            bits ^= 1 << index;
-            squares.push(Square::new(index));
-        }
-        squares
+    /// The highest set square, the board must not be empty.
+    pub fn msb(self) -> Square {
+        Square::new(63 - self.0.leading_zeros() as u8)
+    }
+
+    pub fn more_than_one(self) -> bool {
+        self.0 & self.0.wrapping_sub(1) != 0
+    }
+
+    pub fn is_single(self) -> bool {
+        self.0 != 0 && !self.more_than_one()
+    }
+
+    /// Squares in order from a1 to h8, without allocating.
+    pub fn iter(self) -> SquareIter {
+        SquareIter(self.0)
     }
 
     pub fn pretty_print(&self) {
@@ -97,20 +111,87 @@ impl Bitboard {
             *self >> -bits
         }
     }
+
+    // Directional shifts, squares leaving the board on a side are dropped
+    // instead of wrapping around to the other edge.
+
+    pub fn north(self) -> Bitboard {
+        Bitboard(self.0 << 8)
+    }
+
+    pub fn south(self) -> Bitboard {
+        Bitboard(self.0 >> 8)
+    }
+
+    pub fn east(self) -> Bitboard {
+        Bitboard((self.0 & !FILE_H) << 1)
+    }
+
+    pub fn west(self) -> Bitboard {
+        Bitboard((self.0 & !FILE_A) >> 1)
+    }
+
+    pub fn north_east(self) -> Bitboard {
+        Bitboard((self.0 & !FILE_H) << 9)
+    }
+
+    pub fn north_west(self) -> Bitboard {
+        Bitboard((self.0 & !FILE_A) << 7)
+    }
+
+    pub fn south_east(self) -> Bitboard {
+        Bitboard((self.0 & !FILE_H) >> 7)
+    }
+
+    pub fn south_west(self) -> Bitboard {
+        Bitboard((self.0 & !FILE_A) >> 9)
+    }
 }
 
-impl Iterator for Bitboard {
+const FILE_A: u64 = 0x0101_0101_0101_0101;
+const FILE_H: u64 = FILE_A << 7;
+
+/// Iterator over the set squares of a `Bitboard`, lowest first.
+#[derive(Copy, Clone, Debug)]
+pub struct SquareIter(u64);
+
+impl Iterator for SquareIter {
     type Item = Square;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.is_empty() {
+        if self.0 == 0 {
             None
         } else {
-            let lsb = self.lsb();
+            let lsb = Square::new(self.0.trailing_zeros() as u8);
             self.0 &= self.0 - 1;
             Some(lsb)
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for SquareIter {}
+
+impl IntoIterator for Bitboard {
+    type Item = Square;
+    type IntoIter = SquareIter;
+
+    fn into_iter(self) -> SquareIter {
+        SquareIter(self.0)
+    }
+}
+
+impl IntoIterator for &Bitboard {
+    type Item = Square;
+    type IntoIter = SquareIter;
+
+    fn into_iter(self) -> SquareIter {
+        SquareIter(self.0)
+    }
 }
 
 impl Sub for Bitboard {
@@ -186,6 +267,12 @@ impl BitOrAssign<u64> for Bitboard {
     }
 }
 
+impl BitXorAssign<u64> for Bitboard {
+    fn bitxor_assign(&mut self, rhs: u64) {
+        self.0 ^= rhs;
+    }
+}
+
 
 impl PartialEq<u64> for Bitboard {
     fn eq(&self, other_u64_value: &u64) -> bool {
@@ -212,3 +299,44 @@ impl Shr<i8> for Bitboard {
 }
 
 
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iteration_and_lsb_msb() {
+        let mut bb = Bitboard(1 << Square::A1 as u8 | 1 << Square::E4 as u8 | 1 << Square::H8 as u8);
+        assert_eq!(bb.into_iter().collect::<Vec<_>>(), vec![Square::A1, Square::E4, Square::H8]);
+        assert_eq!(bb.iter().len(), 3);
+        assert_eq!(bb.msb(), Square::H8);
+        assert!(bb.more_than_one());
+        assert_eq!(bb.pop_lsb(), Square::A1);
+        assert_eq!(bb.pop_lsb(), Square::E4);
+        assert!(bb.is_single());
+        assert!(!bb.more_than_one());
+        assert_eq!(bb.pop_lsb(), Square::H8);
+        assert!(!bb.is_single());
+        assert_eq!(bb.iter().next(), None);
+    }
+
+    #[test]
+    fn test_shifts_do_not_wrap() {
+        let a = Bitboard::file(1);
+        let h = Bitboard::file(8);
+        assert_eq!(a.west(), 0);
+        assert_eq!(h.east(), 0);
+        assert_eq!(a.east(), Bitboard::file(2));
+        assert_eq!(h.west(), Bitboard::file(7));
+        assert_eq!(a.north_west() | a.south_west() | h.north_east() | h.south_east(), 0);
+        assert_eq!(Bitboard::rank(8).north(), 0);
+        assert_eq!(Bitboard::rank(1).south(), 0);
+
+        let e4 = Bitboard(1 << Square::E4 as u8);
+        let neighbours = e4.north() | e4.south() | e4.east() | e4.west()
+            | e4.north_east() | e4.north_west() | e4.south_east() | e4.south_west();
+        assert_eq!(neighbours.iter().collect::<Vec<_>>(),
+            vec![Square::D3, Square::E3, Square::F3, Square::D4, Square::F4, Square::D5, Square::E5, Square::F5]);
+    }
+}
@@ -15,8 +15,7 @@ impl Board{
         match piece {
             Piece::None => {},
             _ => {
-                self.clear_piece(piece, from);
-                self.set_piece(piece, to);
+                self.move_piece(piece, from, to);
                 if self.state.en_passant != Square::None{
                     self.state.hash ^= ZOBRIST_KEYS.en_passant_keys[(self.state.en_passant as usize)%8];
                 }
@@ -25,8 +24,6 @@ impl Board{
                 if piece == Piece::WhitePawn || piece == Piece::BlackPawn {
                     self.state.halfmove_clock = 0;
                 }
-                self.state.hash ^= ZOBRIST_KEYS.piece_keys[piece as usize][from as usize];
-                self.state.hash ^= ZOBRIST_KEYS.piece_keys[piece as usize][to as usize];
             },
             
         }
@@ -40,18 +37,14 @@ impl Board{
             Piece::None => {},
             _ => {
                 let p_to = self.get_piece_on_square(&Square::new(to));
-                self.clear_piece(piece, from);
                 self.clear_piece(p_to, to);
-                self.set_piece(piece, to);
+                self.move_piece(piece, from, to);
                 if self.state.en_passant != Square::None{
                     self.state.hash ^= ZOBRIST_KEYS.en_passant_keys[(self.state.en_passant as usize)%8];
                 }
                 self.state.en_passant = Square::None;
                 self.check_castle_rights(from, to, piece); //TODO
                 self.state.halfmove_clock = 0;
-                self.state.hash ^= ZOBRIST_KEYS.piece_keys[piece as usize][from as usize];
-                self.state.hash ^= ZOBRIST_KEYS.piece_keys[piece as usize][to as usize];
-                self.state.hash ^= ZOBRIST_KEYS.piece_keys[p_to as usize][to as usize];
             },
         }
     }
@@ -63,8 +56,7 @@ impl Board{
         match piece {
             Piece::None => {},
             _ => {
-                self.clear_piece(piece, from);
-                self.set_piece(piece, to);
+                self.move_piece(piece, from, to);
                 match piece {
                     Piece::WhitePawn => {
                         if self.state.en_passant != Square::None{
@@ -84,8 +76,6 @@ impl Board{
                     },
                     _ => {}
                 };
-                self.state.hash ^= ZOBRIST_KEYS.piece_keys[piece as usize][from as usize];
-                self.state.hash ^= ZOBRIST_KEYS.piece_keys[piece as usize][to as usize];
             },
         }
     }
@@ -97,26 +87,16 @@ impl Board{
         match piece {
             Piece::None => {},
             _ => {
-                self.clear_piece(piece, from);
-                self.set_piece(piece, to);
+                self.move_piece(piece, from, to);
                 if self.state.en_passant != Square::None{
                     self.state.hash ^= ZOBRIST_KEYS.en_passant_keys[(self.state.en_passant as usize)%8];
                 }
                 self.state.en_passant = Square::None;
+                self.check_castle_rights(from, to, piece);
                 if piece == Piece::WhiteKing {
-                    self.clear_piece(Piece::WhiteRook, 7);
-                    self.set_piece(Piece::WhiteRook, 5);
-                    self.state.castling.remove_white_king();
-                    self.state.hash ^= ZOBRIST_KEYS.castling_keys[2];
-                    self.state.castling.remove_white_queen();
-                    self.state.hash ^= ZOBRIST_KEYS.castling_keys[3];
+                    self.move_piece(Piece::WhiteRook, 7, 5);
                 } else {
-                    self.clear_piece(Piece::BlackRook, 63);
-                    self.set_piece(Piece::BlackRook, 61);
-                    self.state.castling.remove_black_king();
-                    self.state.hash ^= ZOBRIST_KEYS.castling_keys[0];
-                    self.state.castling.remove_black_queen();
-                    self.state.hash ^= ZOBRIST_KEYS.castling_keys[1];
+                    self.move_piece(Piece::BlackRook, 63, 61);
                 }
                 
                 
@@ -131,26 +111,16 @@ impl Board{
         match piece {
             Piece::None => {},
             _ => {
-                self.clear_piece(piece, from);
-                self.set_piece(piece, to);
+                self.move_piece(piece, from, to);
                 if self.state.en_passant != Square::None{
                     self.state.hash ^= ZOBRIST_KEYS.en_passant_keys[(self.state.en_passant as usize)%8];
                 }
                 self.state.en_passant = Square::None;
+                self.check_castle_rights(from, to, piece);
                 if piece == Piece::WhiteKing {
-                    self.clear_piece(Piece::WhiteRook, 0);
-                    self.set_piece(Piece::WhiteRook, 3);
-                    self.state.castling.remove_white_king();
-                    self.state.hash ^= ZOBRIST_KEYS.castling_keys[2];
-                    self.state.castling.remove_white_queen();
-                    self.state.hash ^= ZOBRIST_KEYS.castling_keys[3];
+                    self.move_piece(Piece::WhiteRook, 0, 3);
                 } else {
-                    self.clear_piece(Piece::BlackRook, 56);
-                    self.set_piece(Piece::BlackRook, 59);
-                    self.state.castling.remove_black_king();
-                    self.state.hash ^= ZOBRIST_KEYS.castling_keys[0];
-                    self.state.castling.remove_black_queen();
-                    self.state.hash ^= ZOBRIST_KEYS.castling_keys[1];
+                    self.move_piece(Piece::BlackRook, 56, 59);
                 }
                 
             },
@@ -167,23 +137,18 @@ impl Board{
                 match piece {
                     Piece::WhitePawn => {
                         self.clear_piece(self.get_piece_on_square(&Square::new(to-8)), to-8);
-                        self.state.hash ^= ZOBRIST_KEYS.piece_keys[Piece::BlackPawn as usize][(to-8) as usize];
                     },
                     Piece::BlackPawn => {
                         self.clear_piece(self.get_piece_on_square(&Square::new(to+8)), to+8);
-                        self.state.hash ^= ZOBRIST_KEYS.piece_keys[Piece::WhitePawn as usize][(to+8) as usize];
                     },
                     _ => {}
                 };
-                self.clear_piece(piece, from);
-                self.set_piece(piece, to);
+                self.move_piece(piece, from, to);
                 if self.state.en_passant != Square::None{
                     self.state.hash ^= ZOBRIST_KEYS.en_passant_keys[(self.state.en_passant as usize)%8];
                 }
                 self.state.en_passant = Square::None;
                 self.state.halfmove_clock = 0;
-                self.state.hash ^= ZOBRIST_KEYS.piece_keys[piece as usize][from as usize];
-                self.state.hash ^= ZOBRIST_KEYS.piece_keys[piece as usize][to as usize];
             },
         }
     }
@@ -202,8 +167,6 @@ impl Board{
                 }
                 self.state.en_passant = Square::None;
                 self.state.halfmove_clock = 0;
-                self.state.hash ^= ZOBRIST_KEYS.piece_keys[piece as usize][from as usize];
-                self.state.hash ^= ZOBRIST_KEYS.piece_keys[promotion_piece as usize][to as usize];
             },
         }
     }
@@ -216,15 +179,15 @@ impl Board{
             Piece::None => {},
             _ => {
                 let p_to = self.get_piece_on_square(&Square::new(to));
-                self.clear_piece(piece, from);
                 self.clear_piece(p_to, to);
+                self.clear_piece(piece, from);
                 self.set_piece(promotion_piece, to);
+                if self.state.en_passant != Square::None{
+                    self.state.hash ^= ZOBRIST_KEYS.en_passant_keys[(self.state.en_passant as usize)%8];
+                }
                 self.check_castle_rights(from, to, piece); //TODO
                 self.state.en_passant = Square::None;
                 self.state.halfmove_clock = 0;
-                self.state.hash ^= ZOBRIST_KEYS.piece_keys[piece as usize][from as usize];
-                self.state.hash ^= ZOBRIST_KEYS.piece_keys[p_to as usize][to as usize];
-                self.state.hash ^= ZOBRIST_KEYS.piece_keys[promotion_piece as usize][to as usize];
 
             },
         }
@@ -255,7 +218,7 @@ impl Board{
     }
 
     pub fn apply_move(&mut self, mv: &Move){
-        self.state.hash_history[self.state.halfmove_clock as usize % 100] = self.hash();
+        self.hash_history.push(self.hash());
         match mv.kind(){
             MoveType::Capture | MoveType::BPromotionCapture | MoveType::QPromotionCapture | MoveType::KPromotionCapture |MoveType::RPromotionCapture =>{
                 self.state.captured = self.get_piece_on_square(&Square::new(mv.to()))
@@ -273,25 +236,25 @@ impl Board{
 
         }
         self.history.push(self.state);
-        self.move_pieces(mv);
-        self.fill_colors();
+        // Counted before the move so captures and pawn moves reset it to 0
         self.state.halfmove_clock += 1;
+        self.move_pieces(mv);
         if self.state.color == Color::Black {
             self.state.fullmove_number += 1;
         }
         self.state.color = self.state.color.invert();
+        self.state.hash ^= ZOBRIST_KEYS.black_to_move_key;
     }
 
     pub fn undo_move(&mut self, mv: &Move){
         let state = self.history.pop().unwrap();
-        self.state =  state;
+        self.hash_history.pop();
          
         let from = mv.from();
         let to = mv.to();
         let piece = self.get_piece_on_square(&Square::new(to));
 
-        self.set_piece(piece, from);
-        self.clear_piece(piece, to);
+        self.move_piece(piece, to, from);
         let captured = state.captured;
 
         if captured != Piece::None {
@@ -301,29 +264,23 @@ impl Board{
         match mv.kind(){
             MoveType::EnPassant =>{
                 if state.color == Color::White{
-                    self.clear_piece(Piece::BlackPawn, to);
-                    self.set_piece(Piece::BlackPawn, to-8);
+                    self.move_piece(Piece::BlackPawn, to, to-8);
                 } else if state.color == Color::Black{
-                    self.clear_piece(Piece::WhitePawn, to);
-                    self.set_piece(Piece::WhitePawn, to+8);
+                    self.move_piece(Piece::WhitePawn, to, to+8);
                 }
             }
             MoveType::KingCastle =>{
                 if state.color == Color::White{
-                    self.set_piece(Piece::WhiteRook, 7);
-                    self.clear_piece(Piece::WhiteRook, 5);
+                    self.move_piece(Piece::WhiteRook, 5, 7);
                 } else if state.color == Color::Black{
-                    self.clear_piece(Piece::BlackRook, 61);
-                    self.set_piece(Piece::BlackRook, 63);
+                    self.move_piece(Piece::BlackRook, 61, 63);
                 }
             }
             MoveType::QueenCastle =>{
                 if state.color == Color::White{
-                    self.set_piece(Piece::WhiteRook, 0);
-                    self.clear_piece(Piece::WhiteRook, 3);
+                    self.move_piece(Piece::WhiteRook, 3, 0);
                 } else if state.color == Color::Black{
-                    self.set_piece(Piece::BlackRook, 56);
-                    self.clear_piece(Piece::BlackRook, 59);
+                    self.move_piece(Piece::BlackRook, 59, 56);
                 }
             }
             MoveType::QPromotionCapture | MoveType::QPromotion =>{
@@ -364,5 +321,57 @@ impl Board{
             }
             _ =>{}
         }
+        // Restored last, the piece updates above changed the hash
+        self.state = state;
+    }
+}
+
+
+
+/// ######################################################
+/// ################### TESTING ##########################
+/// ######################################################
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::bitboard::Bitboard;
+    use std::str::FromStr;
+
+    /// Checks colors, mailbox and hash against the piece bitboards in every node.
+    fn walk(board: &mut Board, depth: u8) {
+        let white = board.pieces[0..6].iter().fold(Bitboard(0), |acc, bb| acc | *bb);
+        let black = board.pieces[6..12].iter().fold(Bitboard(0), |acc, bb| acc | *bb);
+        assert_eq!(board.colors, [white, black], "{}", board.to_fen());
+        for (index, piece) in board.mailbox.iter().enumerate() {
+            assert!(*piece == Piece::None || board.pieces[*piece as usize].is_set(index as u8), "{}", board.to_fen());
+        }
+        assert_eq!(board.hash(), board.compute_hash(), "{}", board.to_fen());
+        if depth == 0 {
+            return;
+        }
+
+        let moves = board.generate_all_moves();
+        for mv in moves.iter() {
+            let before = (board.pieces, board.colors, board.mailbox, board.hash());
+            board.apply_move(mv);
+            walk(board, depth - 1);
+            board.undo_move(mv);
+            assert!(before == (board.pieces, board.colors, board.mailbox, board.hash()), "{} {}", board.to_fen(), mv.to_lan());
+        }
+    }
+
+    #[test]
+    fn test_incremental_updates_match_full_recompute() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "8/2p5/3p4/KP5r/1P3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ] {
+            let mut board = Board::from_str(fen).unwrap();
+            walk(&mut board, 3);
+        }
     }
-}
\ No newline at end of file
+}
@@ -26,6 +26,8 @@ pub struct Board {
     pub pieces: [Bitboard; Piece::COUNT],
     pub state: InternalState,
     pub history: Box<ArrayVec<InternalState, 512>>,
+    /// Hashes of the positions before every move played, for repetition detection.
+    pub hash_history: Box<ArrayVec<u64, 512>>,
     pub colors: [Bitboard; Color::COUNT],
     pub mailbox: [Piece; Square::COUNT]
 }
@@ -42,6 +44,7 @@ impl Board {
             pieces: [Bitboard::new(0); Piece::COUNT],
             state: InternalState::new(),
             history: Box::new(ArrayVec::new()),
+            hash_history: Box::new(ArrayVec::new()),
             colors: [Bitboard::new(0); Color::COUNT],
             mailbox: [Piece::None; Square::COUNT]
         }
@@ -59,19 +62,6 @@ impl Board {
         self.colors[self.state.color.invert() as usize]
     }
 
-    pub fn fill_colors(&mut self) {
-        let mut bitboard_white= Bitboard::new(0);
-        for &piece_bb in self.pieces[0..6].iter() {
-                bitboard_white |= piece_bb.0; 
-            };
-        let mut bitboard_black= Bitboard::new(0);
-        for &piece_bb in self.pieces[6..12].iter() {
-                bitboard_black |= piece_bb.0; 
-            };
-        self.colors[0] = bitboard_white;
-        self.colors[1] = bitboard_black;
-    }
-        
     pub fn get_piece_on_square(&self, square: &Square) -> Piece {
         self.mailbox[square.to_index() as usize]
     }
@@ -93,16 +83,30 @@ impl Board {
         combined_bitboard
     }
 
+    /// Puts `piece` on `position`, keeping colors, mailbox and hash in sync.
     pub fn set_piece(&mut self, piece: Piece, position: u8) {
         self.pieces[piece as usize].set_bit(position);
+        self.colors[piece as usize / 6].set_bit(position);
         self.mailbox[position as usize] = piece;
-        self.fill_colors();
+        self.state.hash ^= ZOBRIST_KEYS.piece_keys[piece as usize][position as usize];
     }
 
+    /// Removes `piece` from `position`, keeping colors, mailbox and hash in sync.
     pub fn clear_piece(&mut self, piece: Piece, position: u8) {
         self.pieces[piece as usize].clear_bit(position);
+        self.colors[piece as usize / 6].clear_bit(position);
         self.mailbox[position as usize] = Piece::None;
-        self.fill_colors();
+        self.state.hash ^= ZOBRIST_KEYS.piece_keys[piece as usize][position as usize];
+    }
+
+    /// Moves `piece` from `from` to the empty square `to` with a single mask.
+    pub fn move_piece(&mut self, piece: Piece, from: u8, to: u8) {
+        let mask = (1u64 << from) | (1u64 << to);
+        self.pieces[piece as usize] ^= mask;
+        self.colors[piece as usize / 6] ^= mask;
+        self.mailbox[from as usize] = Piece::None;
+        self.mailbox[to as usize] = piece;
+        self.state.hash ^= ZOBRIST_KEYS.piece_keys[piece as usize][from as usize] ^ ZOBRIST_KEYS.piece_keys[piece as usize][to as usize];
     }
 
     pub fn is_set(&self, piece: Piece, position: u8) -> bool {
@@ -199,6 +203,41 @@ impl Board {
         }
     }
 
+    /// How often the current position occurred before. Only positions since the last
+    /// capture or pawn move can repeat, and only those with the same side to move.
+    pub fn repetitions(&self) -> usize {
+        let hash = self.hash();
+        let window = (self.state.halfmove_clock as usize).min(self.hash_history.len());
+        self.hash_history.iter().rev().take(window).skip(1).step_by(2).filter(|h| **h == hash).count()
+    }
+
+    /// Whether the current position occurred `count` times, counting itself.
+    pub fn is_repetition(&self, count: usize) -> bool {
+        self.repetitions() + 1 >= count
+    }
+
+    /// Whether fifty moves by each side passed without a capture or pawn move.
+    /// A mate on the last move still counts, `outcome` checks that first.
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.state.halfmove_clock >= 100
+    }
+
+    /// Whether neither side can ever mate: bare kings, a single minor piece,
+    /// or only bishops that all stand on squares of the same color.
+    pub fn is_insufficient_material(&self) -> bool {
+        let heavy = [Piece::WhitePawn, Piece::WhiteRook, Piece::WhiteQueen, Piece::BlackPawn, Piece::BlackRook, Piece::BlackQueen];
+        if heavy.iter().any(|piece| !self.pieces[*piece as usize].is_empty()) {
+            return false;
+        }
+        let knights = self.pieces[Piece::WhiteKnight as usize] | self.pieces[Piece::BlackKnight as usize];
+        let bishops = self.pieces[Piece::WhiteBishop as usize] | self.pieces[Piece::BlackBishop as usize];
+        if (knights | bishops).count_set() <= 1 {
+            return true;
+        }
+        const LIGHT_SQUARES: u64 = 0x55AA_55AA_55AA_55AA;
+        knights.is_empty() && (bishops.0 & LIGHT_SQUARES == 0 || bishops.0 & !LIGHT_SQUARES == 0)
+    }
+
     pub fn is_legal(&self) -> bool {
         if self.state.color == Color::White {
             let sq_king = Square::new(self.pieces[Piece::BlackKing as usize].0.trailing_zeros() as u8);
@@ -312,8 +351,8 @@ impl FromStr for Board {
             Ok(fullmove_number) => {board.state.fullmove_number = fullmove_number;},
             Err(_) => {return Err(FenError::InvalidFullmoveNumber);}
         }
-        board.fill_colors();
         board.update_mailbox_from_pieces();
+        board.state.hash = board.compute_hash();
 
         Ok(board)
     }
@@ -491,5 +530,70 @@ mod tests {
             }
             }
     }
-}
 
+    #[test]
+    fn test_fen_round_trip_castling_order() {
+        for rights in ["KQkq", "Kq", "Qk", "Qq", "Kk", "k", "-"] {
+            let fen = format!("r3k2r/8/8/8/8/8/8/R3K2R w {} - 0 1", rights);
+            assert_eq!(fen.parse::<Board>().unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn test_repetitions_follow_moves() {
+        use crate::types::moves::Move;
+        let mut board = Board::start();
+        let moves: Vec<Move> = ["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1", "f6g8"]
+            .iter()
+            .map(|lan| {
+                let mv = Move::from_lan(&board, lan);
+                board.apply_move(&mv);
+                mv
+            })
+            .collect();
+        assert_eq!(board.repetitions(), 2);
+        assert_eq!(board.hash_history.len(), 8);
+
+        for mv in moves.iter().rev().take(4) {
+            board.undo_move(mv);
+        }
+        assert_eq!(board.repetitions(), 1);
+        let pawn_move = Move::from_lan(&board, "e2e4");
+        board.apply_move(&pawn_move);
+        assert_eq!(board.repetitions(), 0);
+        board.undo_move(&pawn_move);
+        assert_eq!(board.repetitions(), 1);
+        assert!(board.is_repetition(2));
+        assert!(!board.is_repetition(3));
+    }
+
+    #[test]
+    fn test_halfmove_clock_follows_moves() {
+        use crate::types::moves::Move;
+        let mut board = Board::from_str("4k3/8/8/3p4/8/8/4P3/4K1N1 w - - 5 1").unwrap();
+        for (lan, clock) in [("g1f3", 6), ("e8d7", 7), ("e2e4", 0), ("d7e6", 1), ("e4d5", 0)] {
+            board.apply_move(&Move::from_lan(&board, lan));
+            assert_eq!(board.state.halfmove_clock, clock, "after {}", lan);
+        }
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        let cases = [
+            ("8/8/4k3/8/8/3K4/8/8 w - - 0 1", true),
+            ("8/8/4k3/8/8/3K4/5N2/8 w - - 0 1", true),
+            ("8/8/4k3/2b5/8/3K4/8/8 w - - 0 1", true),
+            // Bishops on c1 and f4 both stand on dark squares
+            ("8/8/4k3/8/5b2/3K4/8/2B5 w - - 0 1", true),
+            ("8/8/4k3/8/5b2/3K4/8/3B4 w - - 0 1", false),
+            ("8/8/4k3/8/5n2/3K4/8/2B5 w - - 0 1", false),
+            ("8/8/4k3/8/8/3K4/5NN1/8 w - - 0 1", false),
+            ("8/8/4k3/8/8/3K4/4P3/8 w - - 0 1", false),
+            ("8/8/4k3/8/8/3K4/8/7R w - - 0 1", false),
+        ];
+        for (fen, expected) in cases {
+            let board = Board::from_str(fen).unwrap();
+            assert_eq!(board.is_insufficient_material(), expected, "FEN {}", fen);
+        }
+    }
+}
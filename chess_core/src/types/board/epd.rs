@@ -0,0 +1,212 @@
+use std::fmt;
+use std::str::FromStr;
+use super::board::{Board, FenError};
+use crate::types::moves::{Move, SanError};
+
+
+#[derive(Debug)]
+pub enum EpdError {
+    /// Fewer than the four position fields.
+    InvalidFormat,
+    InvalidFen(FenError),
+    InvalidOperation(String),
+    InvalidMove { opcode: String, san: String, error: SanError },
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EpdError::InvalidFormat => write!(f, "expected at least four position fields"),
+            EpdError::InvalidFen(e) => write!(f, "invalid position: {:?}", e),
+            EpdError::InvalidOperation(op) => write!(f, "invalid operation '{}'", op),
+            EpdError::InvalidMove { opcode, san, error } => write!(f, "{} {}: {}", opcode, san, error),
+        }
+    }
+}
+
+impl std::error::Error for EpdError {}
+
+/// Operations of an EPD record. The common opcodes are parsed into fields,
+/// every operation is also kept in `operations` in the order it appeared.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EpdOperations {
+    /// `id`
+    pub id: Option<String>,
+    /// `bm`, any of these moves solves the position.
+    pub best_moves: Vec<Move>,
+    /// `am`, none of these moves may be played.
+    pub avoid_moves: Vec<Move>,
+    /// `c0`
+    pub comment: Option<String>,
+    /// `dm`, mate in this many moves.
+    pub direct_mate: Option<u32>,
+    /// `acd`
+    pub analysis_depth: Option<u32>,
+    /// `ce`, relative to the side to move.
+    pub centipawn_eval: Option<i32>,
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl EpdOperations {
+    /// Operands of the first operation with `opcode`.
+    pub fn get(&self, opcode: &str) -> Option<&[String]> {
+        self.operations.iter().find(|(op, _)| op == opcode).map(|(_, operands)| operands.as_slice())
+    }
+
+    /// Whether playing `mv` solves the position, it has to be one of the best
+    /// moves if there are any and must not be one of the moves to avoid.
+    pub fn is_solved_by(&self, mv: &Move) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(mv)) && !self.avoid_moves.contains(mv)
+    }
+}
+
+/// Splits `text` into `(opcode, operands)` pairs. Operations end with `;`,
+/// operands in double quotes may contain spaces and semicolons.
+fn split_operations(text: &str) -> Result<Vec<(String, Vec<String>)>, EpdError> {
+    let mut operations = Vec::new();
+    let mut words: Vec<String> = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => {
+                if words.is_empty() {
+                    return Err(EpdError::InvalidOperation(String::from(";")));
+                }
+                let opcode = words.remove(0);
+                operations.push((opcode, std::mem::take(&mut words)));
+            }
+            '"' => {
+                let mut operand = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => operand.extend(chars.next()),
+                        Some('"') => break,
+                        Some(c) => operand.push(c),
+                        None => return Err(EpdError::InvalidOperation(format!("\"{}", operand))),
+                    }
+                }
+                if words.is_empty() {
+                    return Err(EpdError::InvalidOperation(format!("\"{}\"", operand)));
+                }
+                words.push(operand);
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut word = String::from(c);
+                while let Some(&d) = chars.peek().filter(|d| !d.is_whitespace() && **d != ';' && **d != '"') {
+                    word.push(d);
+                    chars.next();
+                }
+                words.push(word);
+            }
+        }
+    }
+    // The last operation may leave out its semicolon
+    if !words.is_empty() {
+        let opcode = words.remove(0);
+        operations.push((opcode, words));
+    }
+    Ok(operations)
+}
+
+impl Board {
+    /// Parses an EPD record: the first four FEN fields followed by operations
+    /// like `bm Qg6; id "WAC.001";`. `hmvc` and `fmvn` set the move counters.
+    pub fn from_epd(epd: &str) -> Result<(Self, EpdOperations), EpdError> {
+        let mut rest = epd.trim();
+        let mut fields = Vec::with_capacity(4);
+        for _ in 0..4 {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(EpdError::InvalidFormat);
+            }
+            fields.push(&rest[..end]);
+            rest = rest[end..].trim_start();
+        }
+
+        let mut board = Board::from_str(&format!("{} 0 1", fields.join(" "))).map_err(EpdError::InvalidFen)?;
+        let mut ops = EpdOperations {
+            operations: split_operations(rest)?,
+            ..EpdOperations::default()
+        };
+
+        let invalid = |opcode: &str, operands: &[String]| EpdError::InvalidOperation(format!("{} {}", opcode, operands.join(" ")));
+        for (opcode, operands) in ops.operations.iter() {
+            let number = || operands.first().and_then(|n| n.trim_start_matches('+').parse::<i64>().ok()).ok_or_else(|| invalid(opcode, operands));
+            match opcode.as_str() {
+                "bm" | "am" => {
+                    let mut moves = Vec::with_capacity(operands.len());
+                    for san in operands {
+                        let mv = Move::parse_san(&board, san)
+                            .map_err(|error| EpdError::InvalidMove { opcode: opcode.clone(), san: san.clone(), error })?;
+                        moves.push(mv);
+                    }
+                    if opcode == "bm" { ops.best_moves = moves } else { ops.avoid_moves = moves }
+                }
+                "id" => ops.id = operands.first().cloned(),
+                "c0" => ops.comment = Some(operands.join(" ")),
+                "dm" => ops.direct_mate = Some(number()? as u32),
+                "acd" => ops.analysis_depth = Some(number()? as u32),
+                "ce" => ops.centipawn_eval = Some(number()? as i32),
+                "hmvc" => board.state.halfmove_clock = number()?.min(u8::MAX as i64) as u8,
+                "fmvn" => board.state.fullmove_number = number()?.max(1) as usize,
+                _ => {}
+            }
+        }
+        Ok((board, ops))
+    }
+}
+
+
+
+
+/// ######################################################
+/// ################### TESTING ##########################
+/// ######################################################
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wac() {
+        let epd = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\"; c0 \"a; b\";";
+        let (board, ops) = Board::from_epd(epd).unwrap();
+        assert_eq!(board.to_fen(), "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1");
+        assert_eq!(ops.id.as_deref(), Some("WAC.001"));
+        assert_eq!(ops.comment.as_deref(), Some("a; b"));
+        assert_eq!(ops.best_moves, vec![Move::parse_san(&board, "Qg6").unwrap()]);
+        assert!(ops.is_solved_by(&ops.best_moves[0]));
+        assert!(!ops.is_solved_by(&Move::parse_san(&board, "Qh4").unwrap()));
+    }
+
+    #[test]
+    fn test_parse_opcodes() {
+        let epd = "4k3/8/8/8/8/8/8/R3K3 w Q - am Kd2 Ke2; dm 2; acd 12; ce +32000; hmvc 7; fmvn 40; foo bar baz";
+        let (board, ops) = Board::from_epd(epd).unwrap();
+        assert_eq!(board.state.halfmove_clock, 7);
+        assert_eq!(board.state.fullmove_number, 40);
+        assert_eq!(ops.avoid_moves.len(), 2);
+        assert!(ops.best_moves.is_empty());
+        assert_eq!(ops.direct_mate, Some(2));
+        assert_eq!(ops.analysis_depth, Some(12));
+        assert_eq!(ops.centipawn_eval, Some(32000));
+        assert_eq!(ops.get("foo"), Some(&["bar".to_string(), "baz".to_string()][..]));
+        assert!(ops.is_solved_by(&Move::parse_san(&board, "Ra8").unwrap()));
+        assert!(!ops.is_solved_by(&Move::parse_san(&board, "Ke2").unwrap()));
+    }
+
+    #[test]
+    fn test_invalid_records() {
+        assert!(matches!(Board::from_epd("8/8/8/8 w"), Err(EpdError::InvalidFormat)));
+        assert!(matches!(Board::from_epd(""), Err(EpdError::InvalidFormat)));
+        assert!(matches!(
+            Board::from_epd("4k3/8/8/8/8/8/8/4K3 w - - bm Qh5;"),
+            Err(EpdError::InvalidMove { error: SanError::IllegalMove(_), .. })
+        ));
+        assert!(matches!(Board::from_epd("4k3/8/8/8/8/8/8/4K3 w - - dm x;"), Err(EpdError::InvalidOperation(_))));
+        assert!(matches!(Board::from_epd("4k3/8/8/8/8/8/8/4K3 w - - id \"open"), Err(EpdError::InvalidOperation(_))));
+    }
+}
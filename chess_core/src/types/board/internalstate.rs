@@ -38,6 +38,8 @@ impl From<&str> for GameState {
 }
 
 
+/// Everything about a position the pieces do not tell, saved on `Board::history`
+/// before every move so `undo_move` can restore it. Kept small as it is copied per move.
 #[derive(Debug, Clone, Copy)]
 pub struct InternalState {
     pub hash: u64,
@@ -48,11 +50,9 @@ pub struct InternalState {
     pub fullmove_number: usize,
     pub captured: Piece,
     pub checker: Bitboard,
+    /// Pieces of the side to move pinned to their king, set by the move generator.
     pub pinned: Bitboard,
-    pub pin_rays: [Bitboard; 64],
     pub num_checker: u8,
-    pub game_state: GameState,
-    pub hash_history: [u64; 100],
 }
 
 impl InternalState {
@@ -67,10 +67,7 @@ impl InternalState {
             captured: Piece::None,
             checker: Bitboard(0),
             pinned: Bitboard(0),
-            pin_rays: [Bitboard(0); 64],
             num_checker: 0,
-            game_state: GameState::InProgress,
-            hash_history: [0u64; 100],
         }
     }
 }
@@ -3,6 +3,11 @@ use crate::data::attacks::{ROOK_ATTACKS, BISHOP_ATTACKS};
 use crate::types::bitboard::Bitboard;
 use crate::types::square::Square;
 use crate::types::color::Color;
+use crate::magic::magicgen::{generate_pext_attack_tables, PextEntry};
+#[cfg(not(target_arch = "x86_64"))]
+use crate::magic::magicgen::pext_software;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicU8, Ordering};
 
 
 
@@ -14,6 +19,22 @@ fn get_magic_index(magic: &MagicEntry, blockers: &Bitboard) -> usize{
 }
 
 pub fn lookup_bishop(sq: &Square, blockers:&Bitboard) -> Bitboard{
+    if slider_backend() == SliderBackend::Pext {
+        // The backend is only set to PEXT on CPUs with BMI2
+        return unsafe { lookup_bishop_pext(sq, blockers) };
+    }
+    lookup_bishop_magic(sq, blockers)
+}
+
+pub fn lookup_rook(sq: &Square, blockers:&Bitboard) -> Bitboard{
+    if slider_backend() == SliderBackend::Pext {
+        // The backend is only set to PEXT on CPUs with BMI2
+        return unsafe { lookup_rook_pext(sq, blockers) };
+    }
+    lookup_rook_magic(sq, blockers)
+}
+
+pub fn lookup_bishop_magic(sq: &Square, blockers:&Bitboard) -> Bitboard{
     unsafe {
         let magic = BISHOP_MAGICS.get_unchecked(*sq as usize);
         let index = get_magic_index(magic, blockers);
@@ -21,7 +42,7 @@ pub fn lookup_bishop(sq: &Square, blockers:&Bitboard) -> Bitboard{
     }
 }
 
-pub fn lookup_rook(sq: &Square, blockers:&Bitboard) -> Bitboard{
+pub fn lookup_rook_magic(sq: &Square, blockers:&Bitboard) -> Bitboard{
     unsafe {
         let magic = ROOK_MAGICS.get_unchecked(*sq as usize);
         let index = get_magic_index(magic, blockers);
@@ -29,6 +50,114 @@ pub fn lookup_rook(sq: &Square, blockers:&Bitboard) -> Bitboard{
     }
 }
 
+/// How the attacks of sliding pieces are looked up. Both give the same attacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliderBackend {
+    /// Fancy magics from `data::magics`, works on every CPU.
+    Magic,
+    /// Tables indexed with the BMI2 `pext` instruction.
+    Pext,
+}
+
+const BACKEND_UNSET: u8 = 0;
+const BACKEND_MAGIC: u8 = 1;
+const BACKEND_PEXT: u8 = 2;
+
+static SLIDER_BACKEND: AtomicU8 = AtomicU8::new(BACKEND_UNSET);
+
+/// Whether the CPU we run on has BMI2, checked at runtime.
+pub fn pext_supported() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        std::arch::is_x86_feature_detected!("bmi2")
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        false
+    }
+}
+
+/// The backend in use unless `set_slider_backend` picked one: PEXT if the CPU supports it
+/// and either the build enables BMI2 (e.g. `-C target-cpu=native`) or the `pext` cargo
+/// feature is on. Without BMI2 in the build the PEXT lookup cannot be inlined, so whether
+/// it beats the magic multiplication depends on the CPU; the feature is for machines
+/// where it was measured to.
+pub fn slider_backend() -> SliderBackend {
+    match SLIDER_BACKEND.load(Ordering::Relaxed) {
+        BACKEND_MAGIC => SliderBackend::Magic,
+        BACKEND_PEXT => SliderBackend::Pext,
+        _ => {
+            let prefer_pext = cfg!(feature = "pext") || cfg!(target_feature = "bmi2");
+            let fast_pext = pext_supported() && prefer_pext;
+            let backend = if fast_pext { SliderBackend::Pext } else { SliderBackend::Magic };
+            set_slider_backend(backend);
+            backend
+        }
+    }
+}
+
+/// Switches the slider backend for the whole process. Returns false and keeps
+/// the current backend if PEXT is asked for but the CPU has no BMI2.
+pub fn set_slider_backend(backend: SliderBackend) -> bool {
+    let value = match backend {
+        SliderBackend::Magic => BACKEND_MAGIC,
+        SliderBackend::Pext if pext_supported() => BACKEND_PEXT,
+        SliderBackend::Pext => return false,
+    };
+    SLIDER_BACKEND.store(value, Ordering::Relaxed);
+    true
+}
+
+struct PextTables {
+    bishops: Vec<PextEntry>,
+    bishop_attacks: Vec<Bitboard>,
+    rooks: Vec<PextEntry>,
+    rook_attacks: Vec<Bitboard>,
+}
+
+/// Built on first use, about 800kB that magic-only machines never allocate.
+static PEXT_TABLES: LazyLock<PextTables> = LazyLock::new(|| {
+    let (bishops, bishop_attacks) = generate_pext_attack_tables(false);
+    let (rooks, rook_attacks) = generate_pext_attack_tables(true);
+    PextTables { bishops, bishop_attacks, rooks, rook_attacks }
+});
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "bmi2")]
+#[inline]
+unsafe fn pext(value: u64, mask: u64) -> u64 {
+    std::arch::x86_64::_pext_u64(value, mask)
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+unsafe fn pext(value: u64, mask: u64) -> u64 {
+    pext_software(value, mask)
+}
+
+/// # Safety
+/// On x86-64 the CPU has to support BMI2, see `pext_supported`.
+#[cfg_attr(target_arch = "x86_64", target_feature(enable = "bmi2"))]
+pub unsafe fn lookup_bishop_pext(sq: &Square, blockers: &Bitboard) -> Bitboard {
+    let tables = &*PEXT_TABLES;
+    unsafe {
+        let entry = tables.bishops.get_unchecked(*sq as usize);
+        let index = entry.offset + pext(blockers.0, entry.mask.0) as usize;
+        *tables.bishop_attacks.get_unchecked(index)
+    }
+}
+
+/// # Safety
+/// On x86-64 the CPU has to support BMI2, see `pext_supported`.
+#[cfg_attr(target_arch = "x86_64", target_feature(enable = "bmi2"))]
+pub unsafe fn lookup_rook_pext(sq: &Square, blockers: &Bitboard) -> Bitboard {
+    let tables = &*PEXT_TABLES;
+    unsafe {
+        let entry = tables.rooks.get_unchecked(*sq as usize);
+        let index = entry.offset + pext(blockers.0, entry.mask.0) as usize;
+        *tables.rook_attacks.get_unchecked(index)
+    }
+}
+
 pub fn lookup_queen(sq: &Square, blockers:&Bitboard) -> Bitboard{
     lookup_bishop(sq, blockers) | lookup_rook(sq, blockers)
 }
@@ -109,4 +238,154 @@ pub fn lookup_king(sq: &Square) -> Bitboard {
     }
 
     moves_bb
-}
\ No newline at end of file
+}
+
+include!(concat!(env!("OUT_DIR"), "/geometry.rs"));
+
+/// The eight directions a ray can point to, clockwise from north.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+/// Squares strictly between `a` and `b` if they share a rank, file or diagonal, empty otherwise.
+pub fn between(a: Square, b: Square) -> Bitboard {
+    Bitboard(BETWEEN[a as usize][b as usize])
+}
+
+/// The whole line through `a` and `b` from edge to edge, empty if they are not aligned.
+pub fn line(a: Square, b: Square) -> Bitboard {
+    Bitboard(LINE[a as usize][b as usize])
+}
+
+/// Squares from `sq` towards the edge in `direction`, without `sq` itself.
+pub fn ray(sq: Square, direction: Direction) -> Bitboard {
+    Bitboard(RAYS[direction as usize][sq as usize])
+}
+
+/// Number of king moves between two squares.
+pub fn chebyshev_distance(a: Square, b: Square) -> u8 {
+    CHEBYSHEV_DISTANCE[a as usize][b as usize]
+}
+
+/// Number of rook steps of one square between two squares.
+pub fn manhattan_distance(a: Square, b: Square) -> u8 {
+    MANHATTAN_DISTANCE[a as usize][b as usize]
+}
+
+/// The files left and right of `file`, counted from 0 for the a-file.
+pub fn adjacent_files(file: u8) -> Bitboard {
+    Bitboard(ADJACENT_FILES[file as usize])
+}
+
+/// Squares in front of `sq` on its file, seen from `color`.
+pub fn forward_span(color: Color, sq: Square) -> Bitboard {
+    Bitboard(FORWARD_SPAN[color as usize][sq as usize])
+}
+
+/// Squares in front of `sq` on its own and the adjacent files, a pawn of `color`
+/// on `sq` is passed if no enemy pawn stands on them.
+pub fn passed_pawn_mask(color: Color, sq: Square) -> Bitboard {
+    Bitboard(PASSED_PAWN_MASK[color as usize][sq as usize])
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE_A: u64 = 0x0101_0101_0101_0101;
+
+    fn squares(bb: Bitboard) -> Vec<Square> {
+        bb.into_iter().collect()
+    }
+
+    #[test]
+    fn test_slider_backends_agree() {
+        use crate::magic::magicgen::{calculate_bishop_attacks, calculate_rook_attacks};
+        use rand::{Rng, SeedableRng, rngs::StdRng};
+        // The BMI2 lookup itself can only run on BMI2 CPUs, test_pext_tables_match_magics covers the tables
+        if !pext_supported() {
+            eprintln!("skipping hardware PEXT lookups, the CPU has no BMI2");
+            return;
+        }
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..2000 {
+            // Sparse occupancies look like real positions
+            let blockers = Bitboard(rng.random::<u64>() & rng.random::<u64>());
+            for index in 0..64u8 {
+                let sq = Square::new(index);
+                let (rook, bishop) = unsafe { (lookup_rook_pext(&sq, &blockers), lookup_bishop_pext(&sq, &blockers)) };
+                assert_eq!(rook, lookup_rook_magic(&sq, &blockers));
+                assert_eq!(bishop, lookup_bishop_magic(&sq, &blockers));
+                assert_eq!(rook, calculate_rook_attacks(index as usize, blockers));
+                assert_eq!(bishop, calculate_bishop_attacks(index as usize, blockers));
+            }
+        }
+    }
+
+    #[test]
+    fn test_pext_tables_match_magics() {
+        use crate::magic::magicgen::pext_software;
+        use rand::{Rng, SeedableRng, rngs::StdRng};
+        // Indexed in software so the tables are checked on CPUs without BMI2 as well
+        let tables = &*PEXT_TABLES;
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..2000 {
+            let blockers = Bitboard(rng.random::<u64>() & rng.random::<u64>());
+            for index in 0..64u8 {
+                let sq = Square::new(index);
+                let rook = &tables.rooks[index as usize];
+                let bishop = &tables.bishops[index as usize];
+                let rook_index = rook.offset + pext_software(blockers.0, rook.mask.0) as usize;
+                let bishop_index = bishop.offset + pext_software(blockers.0, bishop.mask.0) as usize;
+                assert_eq!(tables.rook_attacks[rook_index], lookup_rook_magic(&sq, &blockers));
+                assert_eq!(tables.bishop_attacks[bishop_index], lookup_bishop_magic(&sq, &blockers));
+            }
+        }
+    }
+
+    #[test]
+    fn test_between_and_line() {
+        assert_eq!(squares(between(Square::A1, Square::D4)), vec![Square::B2, Square::C3]);
+        assert_eq!(between(Square::A1, Square::D4), between(Square::D4, Square::A1));
+        assert_eq!(squares(between(Square::E1, Square::E4)), vec![Square::E2, Square::E3]);
+        assert!(between(Square::E1, Square::E2).is_empty());
+        assert!(between(Square::A1, Square::B3).is_empty());
+
+        assert_eq!(line(Square::C3, Square::E5), line(Square::A1, Square::H8));
+        assert_eq!(line(Square::A1, Square::H8).count_set(), 8);
+        assert_eq!(line(Square::B4, Square::G4), Bitboard::rank(4));
+        assert!(line(Square::A1, Square::B3).is_empty());
+    }
+
+    #[test]
+    fn test_rays_and_distances() {
+        assert_eq!(squares(ray(Square::E4, Direction::NorthEast)), vec![Square::F5, Square::G6, Square::H7]);
+        assert_eq!(squares(ray(Square::B2, Direction::SouthWest)), vec![Square::A1]);
+        assert!(ray(Square::H4, Direction::East).is_empty());
+        assert_eq!(ray(Square::A1, Direction::North) | Bitboard(1), FILE_A);
+
+        assert_eq!(chebyshev_distance(Square::A1, Square::H8), 7);
+        assert_eq!(manhattan_distance(Square::A1, Square::H8), 14);
+        assert_eq!(chebyshev_distance(Square::E4, Square::F6), 2);
+        assert_eq!(manhattan_distance(Square::E4, Square::F6), 3);
+    }
+
+    #[test]
+    fn test_pawn_masks() {
+        assert_eq!(adjacent_files(0), FILE_A << 1);
+        assert_eq!(adjacent_files(4), FILE_A << 3 | FILE_A << 5);
+        assert_eq!(squares(forward_span(Color::White, Square::E6)), vec![Square::E7, Square::E8]);
+        assert_eq!(squares(forward_span(Color::Black, Square::E3)), vec![Square::E1, Square::E2]);
+        assert_eq!(squares(passed_pawn_mask(Color::White, Square::A6)), vec![Square::A7, Square::B7, Square::A8, Square::B8]);
+        assert_eq!(squares(passed_pawn_mask(Color::Black, Square::D2)), vec![Square::C1, Square::D1, Square::E1]);
+    }
+}
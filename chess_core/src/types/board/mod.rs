@@ -1,7 +1,10 @@
 pub mod board;
 pub mod movegen;
 pub mod internalstate;
+pub mod outcome;
 pub mod transposition;
 pub mod lookup;
 pub mod applymove;
-pub mod parse_pgn;
\ No newline at end of file
+pub mod parse_pgn;
+pub mod pgn_writer;
+pub mod epd;
\ No newline at end of file
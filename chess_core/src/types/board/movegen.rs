@@ -1,13 +1,55 @@
 use crate::types::{bitboard::Bitboard, lists::MoveList, moves::{Move, MoveType}, piece::{Piece, PieceType}, square::Square, color::Color};
 use crate::types::board::lookup::{lookup_pawn_captures};
 use super::board::Board;
-use super::lookup::{lookup_bishop, lookup_knight, lookup_rook, lookup_queen, lookup_king};
-use crate::types::board::internalstate::GameState;
+use super::lookup::{lookup_bishop, lookup_knight, lookup_rook, lookup_queen, lookup_king, between, line};
+use crate::types::board::outcome::{Outcome, Termination};
 
 const ALL: u8 = 2;
 const NOISY: u8 = 1;
 const QUIET: u8 = 0;
 
+const LEGAL: bool = true;
+const PSEUDO_LEGAL: bool = false;
+
+
+/// Where the pieces of the side to move may go, computed once per generation.
+/// Moves within these masks are legal, so no move has to be made to test it.
+#[derive(Debug, Clone, Copy)]
+pub struct MoveMasks {
+    /// Squares capturing or blocking a single checker, all squares without check.
+    pub evasion: Bitboard,
+    /// Squares attacked by the opponent with our king taken off the board.
+    pub king_danger: Bitboard,
+    pub pinned: Bitboard,
+    pub king: Square,
+}
+
+impl MoveMasks {
+    /// No restrictions, for pseudo legal generation.
+    pub fn pseudo_legal() -> Self {
+        MoveMasks {
+            evasion: Bitboard(u64::MAX),
+            king_danger: Bitboard(0),
+            pinned: Bitboard(0),
+            king: Square::None,
+        }
+    }
+
+    /// Squares the non-king piece on `from` may move to.
+    pub fn targets(&self, from: Square) -> Bitboard {
+        if self.pinned.is_set(from.to_index()) {
+            self.evasion & line(self.king, from)
+        } else {
+            self.evasion
+        }
+    }
+
+    pub fn allows(&self, from: Square, to: Square) -> bool {
+        self.targets(from).is_set(to.to_index())
+    }
+}
+
+
 
 
 impl Board{
@@ -23,6 +65,14 @@ impl Board{
         list
     }
 
+    /// Moves that may leave the own king in check, filter them with `pseudo_to_legal`.
+    pub fn generate_pseudo_legal_moves(&mut self) -> MoveList {
+        let mut list = MoveList::new();
+        self.generate_moves::<NOISY, PSEUDO_LEGAL>(&mut list);
+        self.generate_moves::<QUIET, PSEUDO_LEGAL>(&mut list);
+        list
+    }
+
     pub fn generate_attacking_mask(&mut self, color:Color) -> Bitboard {
         let mut mask = Bitboard(0);
         for (sq, p) in self.mailbox.iter().enumerate(){
@@ -53,31 +103,35 @@ impl Board{
     }
         
 
-    /// Generates pseudo legal moves for the current position.
-    fn generate_moves<const TYPE: u8>(&mut self, list: &mut MoveList) {
+    /// Generates the moves of `TYPE` for the current position, only legal ones if `LEGAL`.
+    fn generate_moves<const TYPE: u8, const LEGAL: bool>(&mut self, list: &mut MoveList) {
         let (checker, num_checker) = self.get_checker();
         self.state.checker = checker;
         self.state.num_checker = num_checker;
-        let (pinned, pin_rays) = self.get_pinner();
-        self.state.pinned = pinned;
-        self.state.pin_rays = pin_rays;
+        self.state.pinned = self.get_pinner();
+        let masks = if LEGAL { self.masks_from(checker, num_checker, self.state.pinned) } else { MoveMasks::pseudo_legal() };
+        self.collect_all::<TYPE, LEGAL>(list, &masks);
+    }
+
+    /// Collects the moves of `TYPE` within `masks`, leaves the board untouched.
+    fn collect_all<const TYPE: u8, const LEGAL: bool>(&self, list: &mut MoveList, masks: &MoveMasks) {
         let occupancies = self.occupied();
 
-        self.collect_pawn_moves::<TYPE>(list);
+        self.collect_pawn_moves::<TYPE, LEGAL>(list, masks);
         if self.state.color == Color::White {
 
-            self.collect_moves::<TYPE, _>(list, Piece::WhiteKnight, |square| lookup_knight(&square));
-            self.collect_moves::<TYPE, _>(list, Piece::WhiteBishop, |square| lookup_bishop(&square, &occupancies));
-            self.collect_moves::<TYPE, _>(list, Piece::WhiteRook, |square| lookup_rook(&square, &occupancies));
-            self.collect_moves::<TYPE, _>(list, Piece::WhiteQueen, |square| lookup_queen(&square, &occupancies));
-            self.collect_moves::<TYPE, _>(list, Piece::WhiteKing, |square| lookup_king(&square));
+            self.collect_moves::<TYPE, _>(list, masks, Piece::WhiteKnight, |square| lookup_knight(&square));
+            self.collect_moves::<TYPE, _>(list, masks, Piece::WhiteBishop, |square| lookup_bishop(&square, &occupancies));
+            self.collect_moves::<TYPE, _>(list, masks, Piece::WhiteRook, |square| lookup_rook(&square, &occupancies));
+            self.collect_moves::<TYPE, _>(list, masks, Piece::WhiteQueen, |square| lookup_queen(&square, &occupancies));
+            self.collect_moves::<TYPE, _>(list, masks, Piece::WhiteKing, |square| lookup_king(&square));
 
         }else if self.state.color == Color::Black {
-            self.collect_moves::<TYPE, _>(list, Piece::BlackKnight, |square| lookup_knight(&square));
-            self.collect_moves::<TYPE, _>(list, Piece::BlackBishop, |square| lookup_bishop(&square, &occupancies));
-            self.collect_moves::<TYPE, _>(list, Piece::BlackRook, |square| lookup_rook(&square, &occupancies));
-            self.collect_moves::<TYPE, _>(list, Piece::BlackQueen, |square| lookup_queen(&square, &occupancies));
-            self.collect_moves::<TYPE, _>(list, Piece::BlackKing, |square| lookup_king(&square));
+            self.collect_moves::<TYPE, _>(list, masks, Piece::BlackKnight, |square| lookup_knight(&square));
+            self.collect_moves::<TYPE, _>(list, masks, Piece::BlackBishop, |square| lookup_bishop(&square, &occupancies));
+            self.collect_moves::<TYPE, _>(list, masks, Piece::BlackRook, |square| lookup_rook(&square, &occupancies));
+            self.collect_moves::<TYPE, _>(list, masks, Piece::BlackQueen, |square| lookup_queen(&square, &occupancies));
+            self.collect_moves::<TYPE, _>(list, masks, Piece::BlackKing, |square| lookup_king(&square));
 
         }
         if TYPE == QUIET || TYPE == ALL{
@@ -87,101 +141,149 @@ impl Board{
     }
 
     pub fn append_all_moves(&mut self, list: &mut MoveList) {
-        //self.generate_moves::<ALL>(list);
-        self.generate_moves::<NOISY>(list);
-        self.generate_moves::<QUIET>(list);
-        self.update_game_state(list);
+        //self.generate_moves::<ALL, LEGAL>(list);
+        self.generate_moves::<NOISY, LEGAL>(list);
+        self.generate_moves::<QUIET, LEGAL>(list);
     }
 
     pub fn append_quiet_moves(&mut self, list: &mut MoveList) {
-        self.generate_moves::<QUIET>(list);
+        self.generate_moves::<QUIET, LEGAL>(list);
     }
 
-    /// Generates only pseudo legal capture moves for the current position.
+    /// Generates only legal capture moves for the current position.
     pub fn append_noisy_moves(&mut self, list: &mut MoveList) {
-        self.generate_moves::<NOISY>(list);
-    }
-
-    pub fn update_game_state(&mut self, list: &MoveList) {
-        if list.is_empty(){
-            if self.state.checker != 0{
-                self.state.game_state = match self.state.color {
-                    Color::White => GameState::BlackWin,
-                    Color::Black => GameState::WhiteWin,
-                    _ => {self.state.game_state},
-                };
-            }else{
-                self.state.game_state = GameState::Draw;
-            }
+        self.generate_moves::<NOISY, LEGAL>(list);
+    }
+
+    /// Masks for the side to move, computed from scratch.
+    pub fn move_masks(&self) -> MoveMasks {
+        let (checker, num_checker) = self.get_checker();
+        self.masks_from(checker, num_checker, self.get_pinner())
+    }
+
+    /// Masks for the side to move from its check rays and pinned pieces.
+    fn masks_from(&self, checker: Bitboard, num_checker: u8, pinned: Bitboard) -> MoveMasks {
+        let (king_piece, color) = match self.state.color {
+            Color::White => (Piece::WhiteKing, Color::White),
+            _ => (Piece::BlackKing, Color::Black),
+        };
+        let king = self.pieces[king_piece as usize].lsb();
+        let evasion = match num_checker {
+            0 => Bitboard(u64::MAX),
+            1 => checker,
+            _ => Bitboard(0),
+        };
+        // Without the king, sliders also attack the squares behind it
+        let blockers = self.occupied() & !self.pieces[king_piece as usize];
+        MoveMasks {
+            evasion,
+            king_danger: self.attack_map(color.invert(), blockers),
+            pinned,
+            king,
         }
-        else{
-            if self.state.halfmove_clock == 100 {
-                self.state.game_state = GameState::Draw;
-            }
-        else{
-            self.state.game_state = GameState::InProgress;
+    }
+
+    /// Every square attacked by the pieces of `color` with `blockers` stopping sliders.
+    pub fn attack_map(&self, color: Color, blockers: Bitboard) -> Bitboard {
+        let offset = if color == Color::White { 0 } else { 6 };
+        let mut attacks = Bitboard(0);
+        for sq in self.pieces[offset + PieceType::Pawn as usize] {
+            attacks |= lookup_pawn_captures(&sq, &color).0;
+        }
+        for sq in self.pieces[offset + PieceType::Knight as usize] {
+            attacks |= lookup_knight(&sq).0;
+        }
+        for sq in self.pieces[offset + PieceType::Bishop as usize] | self.pieces[offset + PieceType::Queen as usize] {
+            attacks |= lookup_bishop(&sq, &blockers).0;
+        }
+        for sq in self.pieces[offset + PieceType::Rook as usize] | self.pieces[offset + PieceType::Queen as usize] {
+            attacks |= lookup_rook(&sq, &blockers).0;
+        }
+        for sq in self.pieces[offset + PieceType::King as usize] {
+            attacks |= lookup_king(&sq).0;
+        }
+        attacks
+    }
+
+    /// How the game ended in the current position, `None` while it goes on.
+    /// Only finds what the rules decide, the board is left as it is.
+    pub fn outcome(&self) -> Option<Outcome> {
+        let (checker, num_checker) = self.get_checker();
+        let masks = self.masks_from(checker, num_checker, self.get_pinner());
+        let mut list = MoveList::new();
+        self.collect_all::<NOISY, LEGAL>(&mut list, &masks);
+        self.collect_all::<QUIET, LEGAL>(&mut list, &masks);
+        self.outcome_with_moves(&list, num_checker > 0)
+    }
+
+    /// Like `outcome`, with the legal moves of the position already generated.
+    pub fn outcome_with_moves(&self, list: &MoveList, in_check: bool) -> Option<Outcome> {
+        // Mate and stalemate take precedence over the fifty-move rule
+        if list.is_empty() {
+            if in_check {
+                return Some(Outcome::win(self.state.color.invert(), Termination::Checkmate));
             }
+            return Some(Outcome::draw(Termination::Stalemate));
         }
+        if self.is_fifty_move_draw() {
+            return Some(Outcome::draw(Termination::FiftyMove));
+        }
+        if self.is_repetition(3) {
+            return Some(Outcome::draw(Termination::Repetition));
+        }
+        if self.is_insufficient_material() {
+            return Some(Outcome::draw(Termination::Insufficient));
+        }
+        None
     }
 
 
 
-    pub fn collect_moves<const TYPE: u8, T> (&self, list: &mut MoveList, piece: Piece, generator: T) 
+    pub fn collect_moves<const TYPE: u8, T> (&self, list: &mut MoveList, masks: &MoveMasks, piece: Piece, generator: T) 
     where T: Fn(Square) -> Bitboard, 
     {
         let is_king = piece == Piece::WhiteKing || piece == Piece::BlackKing;
         for from in self.pieces[piece as usize]{
-        let targets = generator(from) & !self.us();
+        let allowed = if is_king { !masks.king_danger } else { masks.targets(from) };
+        let targets = generator(from) & !self.us() & allowed;
             match TYPE {
                 ALL => {
                     for to in targets & !self.them() {
-                        let mv = Move::new_from_squares(from, to, MoveType::Normal);
-                        if self.is_move_legal(&mv, is_king){
-                            list.push(mv);
-                        }
+                        list.push(Move::new_from_squares(from, to, MoveType::Normal));
                     }
                     for to in targets & self.them() {
-                        let mv = Move::new_from_squares(from, to, MoveType::Capture);
-                        if self.is_move_legal(&mv, is_king){
-                            list.push(mv);
-                        }
+                        list.push(Move::new_from_squares(from, to, MoveType::Capture));
                     }
                 },
                 NOISY => {
                     for to in targets & self.them() {
-                        let mv = Move::new_from_squares(from, to, MoveType::Capture);
-                        if self.is_move_legal(&mv, is_king){
-                            list.push(mv);
-                        }
+                        list.push(Move::new_from_squares(from, to, MoveType::Capture));
                     }
                 },
                 QUIET => {
                     for to in targets & !self.them() {
-                        let mv = Move::new_from_squares(from, to, MoveType::Normal);
-                        if self.is_move_legal(&mv, is_king){
-                            list.push(mv);
-                        }
+                        list.push(Move::new_from_squares(from, to, MoveType::Normal));
                     }
                 },
                 _ => {}
             }
     }}
 
-    pub fn collect_pawn_moves<const TYPE: u8>(&self, list: &mut MoveList){
+    pub fn collect_pawn_moves<const TYPE: u8, const LEGAL: bool>(&self, list: &mut MoveList, masks: &MoveMasks){
         let (pawns, before_promotion) = match self.state.color {
             Color::White => (self.pieces[Piece::WhitePawn as usize], Bitboard::rank(7)),
             Color::Black => (self.pieces[Piece::BlackPawn as usize], Bitboard::rank(2)),
             _ => (Bitboard::new(0), Bitboard::new(0))
         };
 
-        self.collect_pawn_pushes::<TYPE>(list, &pawns, &before_promotion);
+        self.collect_pawn_pushes::<TYPE>(list, masks, &pawns, &before_promotion);
         if TYPE == NOISY || TYPE == ALL {
-            self.collect_pawn_captures::<NOISY>(list, pawns, before_promotion);
-            self.collect_en_passant_moves(list, pawns);
+            self.collect_pawn_captures::<NOISY>(list, masks, pawns, before_promotion);
+            self.collect_en_passant_moves::<LEGAL>(list, masks, pawns);
         }
     }
 
-    pub fn collect_pawn_pushes<const TYPE: u8>(&self, list: &mut MoveList, pawns: &Bitboard, before_promotion: &Bitboard){
+    pub fn collect_pawn_pushes<const TYPE: u8>(&self, list: &mut MoveList, masks: &MoveMasks, pawns: &Bitboard, before_promotion: &Bitboard){
         let (diff, double_push_rank) = match self.state.color {
             Color::White => (8, Bitboard::rank(3)),
             Color::Black => (-8i8, Bitboard::rank(6)),
@@ -195,15 +297,15 @@ impl Board{
             let double = (pushed & double_push_rank).shift(diff) & free;
 
             for to in pushed{
-                let mv = Move::new_from_squares(to.shift(-diff), to, MoveType::Normal);
-                if self.is_move_legal(&mv, false){
-                    list.push(mv);
+                let from = to.shift(-diff);
+                if masks.allows(from, to){
+                    list.push(Move::new_from_squares(from, to, MoveType::Normal));
                 }  
             }
             for to in double{
-                let mv = Move::new_from_squares(to.shift(-2*diff), to, MoveType::DoublePush);
-                if self.is_move_legal(&mv, false){
-                    list.push(mv);
+                let from = to.shift(-2*diff);
+                if masks.allows(from, to){
+                    list.push(Move::new_from_squares(from, to, MoveType::DoublePush));
                 } 
             }
 
@@ -212,67 +314,71 @@ impl Board{
         let promotions = (*pawns & *before_promotion).shift(diff) & free;
         for to in promotions {
             let from = to.shift(-diff);
+            if !masks.allows(from, to) {
+                continue;
+            }
 
             if TYPE == NOISY || TYPE == ALL {
-                let mv = Move::new_from_squares(from, to, MoveType::QPromotion);
-                if self.is_move_legal(&mv, false){
-                    list.push(mv);
-                }
+                list.push(Move::new_from_squares(from, to, MoveType::QPromotion));
             }
 
             if TYPE == QUIET || TYPE == ALL{
-                let mv = Move::new_from_squares(from, to, MoveType::BPromotion);
-                if self.is_move_legal(&mv, false){
-                    list.push(mv);
-                    list.push(Move::new_from_squares(from, to, MoveType::RPromotion));
-                    list.push(Move::new_from_squares(from, to, MoveType::KPromotion));
-                }
-                
+                list.push(Move::new_from_squares(from, to, MoveType::BPromotion));
+                list.push(Move::new_from_squares(from, to, MoveType::RPromotion));
+                list.push(Move::new_from_squares(from, to, MoveType::KPromotion));
             }
         }
 
     }
 
 
-    fn collect_pawn_captures<const TYPE: u8>(&self, list: &mut MoveList, pawns: Bitboard, before_promotion: Bitboard) {
+    fn collect_pawn_captures<const TYPE: u8>(&self, list: &mut MoveList, masks: &MoveMasks, pawns: Bitboard, before_promotion: Bitboard) {
         let promotions = pawns & before_promotion;
         for from in promotions {
-            let captures = self.them() & lookup_pawn_captures(&from, &self.state.color);
+            let captures = self.them() & lookup_pawn_captures(&from, &self.state.color) & masks.targets(from);
             for to in captures {
-                let mv = Move::new_from_squares(from, to, MoveType::BPromotionCapture);
-                if self.is_move_legal(&mv, false){
-                    list.push(mv);
-                    list.push(Move::new_from_squares(from, to, MoveType::KPromotionCapture));
-                    list.push(Move::new_from_squares(from, to, MoveType::QPromotionCapture));
-                    list.push(Move::new_from_squares(from, to, MoveType::RPromotionCapture));
-                }
-                
+                list.push(Move::new_from_squares(from, to, MoveType::BPromotionCapture));
+                list.push(Move::new_from_squares(from, to, MoveType::KPromotionCapture));
+                list.push(Move::new_from_squares(from, to, MoveType::QPromotionCapture));
+                list.push(Move::new_from_squares(from, to, MoveType::RPromotionCapture));
             }
         }
 
         let non_promotions = pawns & !before_promotion;
         for from in non_promotions {
-            let targets = self.them() & lookup_pawn_captures(&from, &self.state.color);
+            let targets = self.them() & lookup_pawn_captures(&from, &self.state.color) & masks.targets(from);
             for to in targets {
-                let mv = Move::new_from_squares(from, to, MoveType::Capture);
-                if self.is_move_legal(&mv, false){
-                    list.push(mv);
-                }
+                list.push(Move::new_from_squares(from, to, MoveType::Capture));
             }
         }
     }
 
-    fn collect_en_passant_moves(&self, list: &mut MoveList, pawns: Bitboard) {
-        if self.state.en_passant != Square::None {
-            let pawns = pawns & lookup_pawn_captures(&self.state.en_passant, &self.state.color.invert());
-            for pawn in pawns {
-                let mv = Move::new_from_squares(pawn, self.state.en_passant, MoveType::EnPassant);
-                let mut _board = self.clone();
-                _board.apply_move(&mv);
-                if _board.is_legal(){
-                    list.push(mv);
+    fn collect_en_passant_moves<const LEGAL: bool>(&self, list: &mut MoveList, masks: &MoveMasks, pawns: Bitboard) {
+        let to = self.state.en_passant;
+        if to == Square::None {
+            return;
+        }
+        let (captured, color_index) = match self.state.color {
+            Color::White => (to.shift(-8), 6),
+            _ => (to.shift(8), 0),
+        };
+        let pawns = pawns & lookup_pawn_captures(&to, &self.state.color.invert());
+        for pawn in pawns {
+            if LEGAL {
+                // Either square can answer a check, the pawn that just moved may be the checker
+                if masks.evasion & ((1u64 << to.to_index()) | (1u64 << captured.to_index())) == 0 {
+                    continue;
+                }
+                // Two pawns leave the rank at once, which can uncover a slider the
+                // pin detection does not see, so look at the board after the capture
+                let blockers = Bitboard(self.occupied().0 ^ (1u64 << pawn.to_index()) ^ (1u64 << captured.to_index()) | (1u64 << to.to_index()));
+                let diagonal = self.pieces[color_index + PieceType::Bishop as usize] | self.pieces[color_index + PieceType::Queen as usize];
+                let straight = self.pieces[color_index + PieceType::Rook as usize] | self.pieces[color_index + PieceType::Queen as usize];
+                if lookup_bishop(&masks.king, &blockers) & diagonal != 0 || lookup_rook(&masks.king, &blockers) & straight != 0 {
+                    continue;
                 }
             }
+            list.push(Move::new_from_squares(pawn, to, MoveType::EnPassant));
         }
     }
 
@@ -369,8 +475,7 @@ impl Board{
         if checking_bb != 0 {
             for asq in checking_bb{
                 num_checkers += 1;
-                let attacking_moves = lookup_bishop(&asq, &blockers);
-                checkers |= moves.0 & attacking_moves.0;
+                checkers |= between(sq, asq).0;
                 checkers.set_bit(asq.to_index());
             }
         }
@@ -380,8 +485,7 @@ impl Board{
         if checking_bb != 0 {
             for asq in checking_bb{
                 num_checkers += 1;
-                let attacking_moves = lookup_rook(&asq, &blockers);
-                checkers |= moves.0 & attacking_moves.0;
+                checkers |= between(sq, asq).0;
                 checkers.set_bit(asq.to_index());
             }
         }
@@ -427,122 +531,30 @@ impl Board{
         return (checkers, num_checkers)
     }
 
-    pub fn get_pinner(&self) -> (Bitboard, [Bitboard; 64]) {
+    pub fn get_pinner(&self) -> Bitboard {
         let mut pinned = Bitboard(0);
-        let mut pin_rays = [Bitboard(0); 64];
 
         let (color_index, sq, our_blockers) = match self.state.color {
             Color::White => (6, Square::new(self.pieces[Piece::WhiteKing as usize].0.trailing_zeros() as u8), self.colors[Color::White as usize]),
             Color::Black => (0, Square::new(self.pieces[Piece::BlackKing as usize].0.trailing_zeros() as u8), self.colors[Color::Black as usize]),
-            _ => return (pinned, pin_rays)
+            _ => return pinned
         };
         let blockers = self.occupied();
-
-        let mut reduced_blockers = Bitboard(0);
-        reduced_blockers.set_bit(sq.to_index());
-
-
-        let potential_diag = self.pieces[color_index + PieceType::Bishop as usize] | self.pieces[color_index + PieceType::Queen as usize];
-        for _sq in potential_diag{
-            let _sq_bit = _sq.to_index();
-            reduced_blockers.set_bit(_sq_bit);
-            
-            let moves_sq = lookup_bishop(&_sq, &reduced_blockers);
-            if moves_sq.is_set(sq.to_index()) {
-                let moves_king = lookup_bishop(&sq, &reduced_blockers);
-                let ray_between = moves_sq & moves_king;
-                if ray_between != 0 {
-                    if (ray_between & blockers).count() == 1{
-                        let _pinned = ray_between & our_blockers;
-                        if _pinned.count() == 1 {
-                            let idx = _pinned.lsb() as usize;
-                            pinned |= _pinned.0;
-                            pin_rays[idx] |= ray_between.0;
-                            pin_rays[idx].set_bit(_sq_bit);
-                        }
-                    } 
-                }
+        let empty = Bitboard(0);
+
+        // Sliders seeing the king through exactly one of our pieces pin it
+        let diagonal = (self.pieces[color_index + PieceType::Bishop as usize] | self.pieces[color_index + PieceType::Queen as usize]) & lookup_bishop(&sq, &empty);
+        let straight = (self.pieces[color_index + PieceType::Rook as usize] | self.pieces[color_index + PieceType::Queen as usize]) & lookup_rook(&sq, &empty);
+        for pinner in diagonal | straight {
+            let ray_between = between(sq, pinner) & blockers;
+            if ray_between.is_single() && ray_between & our_blockers != 0 {
+                pinned |= ray_between.0;
             }
-            reduced_blockers.clear_bit(_sq_bit);
-        }
-
-        let potential_hv = self.pieces[color_index + PieceType::Rook as usize] | self.pieces[color_index + PieceType::Queen as usize];
-        for _sq in potential_hv{
-            let _sq_bit = _sq.to_index();
-            reduced_blockers.set_bit(_sq_bit);
-
-            let moves_sq = lookup_rook(&_sq, &reduced_blockers);
-            if moves_sq.is_set(sq.to_index()) {
-                let moves_king = lookup_rook(&sq, &reduced_blockers);
-                let ray_between = moves_sq & moves_king;
-                if ray_between != 0 {
-                    if (ray_between & blockers).count() == 1{
-                        let _pinned = ray_between & our_blockers;
-                        if _pinned.count() == 1 {
-                            let idx = _pinned.lsb() as usize;
-                            pinned |= _pinned.0;
-                            pin_rays[idx] |= ray_between.0;
-                            pin_rays[idx].set_bit(_sq_bit);
-                        }
-                    } 
-                }
-            }
-            reduced_blockers.clear_bit(_sq_bit);
         }
 
-        return (pinned, pin_rays)
-    }
-
-    pub fn is_move_legal(&self, mv: &Move, is_king: bool) -> bool{
-        if !self.is_move_pinned(mv) && self.does_move_evade_checkers(mv, is_king){
-            return true;
-        }
-        false
+        return pinned
     }
 
-    pub fn is_move_pinned(&self, mv: &Move) -> bool{
-        let pinned = self.state.pinned;
-        if pinned & (1 << mv.from()) != 0{
-            let pin_rays = self.state.pin_rays;
-            let ray =  pin_rays[mv.from() as usize];
-            if ray & (1 << mv.to()) != 0{
-                return false;
-            }
-            return true;
-        };
-        return false;
-    }
-
-    pub fn does_move_evade_checkers(&self, mv: &Move, is_king: bool) -> bool{
-        let checker = self.state.checker;
-        let num_checker = self.state.num_checker;
-        if !is_king{
-            if num_checker == 0{
-                return true;
-            }
-            if num_checker > 1{
-                return false;
-            }else{
-                if checker & (1 << mv.to()) != 0{
-                    return true;
-                }
-                return false;
-            }
-        }else{
-            //println!("{}", self.is_attacked(&Square::new(mv.to()), self.state.color));
-            let p = match self.state.color {
-                Color::White => Piece::WhiteKing,
-                Color::Black => Piece::BlackKing,
-                _ => return false
-            };
-            if self.is_attacked(&Square::new(mv.to()), self.state.color, Some(p)){
-                return false;
-            }
-            //println!("{}", mv.to_string());
-            return true;
-        }
-    }
-    
 
 }
 
@@ -611,4 +623,71 @@ mod legal_move_count_tests {
         let expected_moves = 46;
         assert_eq!(count_legal_moves_depth_1(fen), expected_moves, "Position 6: FEN {}", fen);
     }
-}
\ No newline at end of file
+
+    /// Compares the legal generator to filtered pseudo legal moves in every node.
+    fn compare_generators(board: &mut Board, depth: u8) {
+        let mut legal: Vec<String> = board.generate_all_moves().iter().map(|mv| mv.to_lan()).collect();
+        let pseudo = board.generate_pseudo_legal_moves();
+        let mut filtered: Vec<String> = board.pseudo_to_legal(&pseudo).iter().map(|mv| mv.to_lan()).collect();
+        legal.sort();
+        filtered.sort();
+        assert_eq!(legal, filtered, "FEN {}", board.to_fen());
+        if depth == 0 {
+            return;
+        }
+        for mv in board.generate_all_moves().iter() {
+            board.apply_move(mv);
+            compare_generators(board, depth - 1);
+            board.undo_move(mv);
+        }
+    }
+
+    #[test]
+    fn test_legal_generator_matches_filtered_pseudo_legal() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            // En passant would uncover the rook on the fourth rank
+            "8/8/8/8/k2Pp2Q/8/8/3K4 b - d3 0 1",
+            // The double pushed pawn gives check and can be taken en passant
+            "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+        ] {
+            let mut board = Board::from_str(fen).unwrap();
+            compare_generators(&mut board, 3);
+        }
+        let mut board = Board::from_str("8/8/8/8/k2Pp2Q/8/8/3K4 b - d3 0 1").unwrap();
+        assert!(board.generate_all_moves().iter().all(|mv| mv.to_lan() != "e4d3"));
+        let mut board = Board::from_str("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1").unwrap();
+        assert!(board.generate_all_moves().iter().any(|mv| mv.to_lan() == "e4d3"));
+    }
+
+    #[test]
+    fn test_outcome_reasons() {
+        use crate::types::board::outcome::{Outcome, Termination};
+        use crate::types::{color::Color, moves::Move};
+        let cases = [
+            ("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3", Some(Outcome::win(Color::Black, Termination::Checkmate))),
+            ("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", Some(Outcome::draw(Termination::Stalemate))),
+            // Mate delivered with the hundredth halfmove still wins
+            ("7k/6Q1/6K1/8/8/8/8/8 b - - 100 80", Some(Outcome::win(Color::White, Termination::Checkmate))),
+            ("7k/8/6K1/8/8/8/8/6Q1 b - - 100 80", Some(Outcome::draw(Termination::FiftyMove))),
+            ("7k/8/6K1/8/8/8/8/6Q1 b - - 99 80", None),
+            ("7k/8/6K1/8/8/8/8/6B1 b - - 0 80", Some(Outcome::draw(Termination::Insufficient))),
+        ];
+        for (fen, expected) in cases {
+            let board = Board::from_str(fen).unwrap();
+            assert_eq!(board.outcome(), expected, "FEN {}", fen);
+        }
+
+        let mut board = Board::start();
+        for lan in ["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1"] {
+            let mv = Move::from_lan(&board, lan);
+            board.apply_move(&mv);
+            assert_eq!(board.outcome(), None);
+        }
+        let mv = Move::from_lan(&board, "f6g8");
+        board.apply_move(&mv);
+        assert_eq!(board.outcome(), Some(Outcome::draw(Termination::Repetition)));
+    }
+}
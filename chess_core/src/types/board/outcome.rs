@@ -0,0 +1,83 @@
+use crate::types::board::internalstate::GameState;
+use crate::types::color::Color;
+use std::fmt;
+
+
+/// Why a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    /// The same position occurred three times.
+    Repetition,
+    /// Fifty moves by each side without a capture or pawn move.
+    FiftyMove,
+    /// Neither side has the material left to mate.
+    Insufficient,
+    Resignation,
+    /// A side ran out of time.
+    Timeout,
+    /// Decided by the tooling, e.g. on the evaluation or a ply limit.
+    Adjudication,
+}
+
+/// The end of a game, `winner` is `None` for a draw. The rules of chess decide the
+/// first five reasons, `Board::outcome` finds those; the rest are set by whoever runs the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    pub winner: Option<Color>,
+    pub reason: Termination,
+}
+
+impl Outcome {
+    pub fn win(winner: Color, reason: Termination) -> Self {
+        Outcome { winner: Some(winner), reason }
+    }
+
+    pub fn draw(reason: Termination) -> Self {
+        Outcome { winner: None, reason }
+    }
+
+    pub fn is_draw(&self) -> bool {
+        self.winner.is_none()
+    }
+
+    /// The result as written in PGN.
+    pub fn result(&self) -> GameState {
+        match self.winner {
+            Some(Color::White) => GameState::WhiteWin,
+            Some(_) => GameState::BlackWin,
+            None => GameState::Draw,
+        }
+    }
+
+    /// Value of the PGN `Termination` tag.
+    pub fn pgn_termination(&self) -> &'static str {
+        match self.reason {
+            Termination::Timeout => "time forfeit",
+            Termination::Adjudication => "adjudication",
+            _ => "normal",
+        }
+    }
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Termination::Checkmate => write!(f, "checkmate"),
+            Termination::Stalemate => write!(f, "stalemate"),
+            Termination::Repetition => write!(f, "threefold repetition"),
+            Termination::FiftyMove => write!(f, "fifty-move rule"),
+            Termination::Insufficient => write!(f, "insufficient material"),
+            Termination::Resignation => write!(f, "resignation"),
+            Termination::Timeout => write!(f, "timeout"),
+            Termination::Adjudication => write!(f, "adjudication"),
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.result(), self.reason)
+    }
+}
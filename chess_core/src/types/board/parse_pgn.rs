@@ -1,15 +1,301 @@
-use std::collections::HashMap;
-use regex::Regex; // Required for move parsing
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
 use super::board::Board;
 use crate::types::board::internalstate::GameState;
+use crate::types::moves::{Move, SanError};
 
 
-// Struct to hold the parsed PGN game data.
 #[derive(Debug)]
+pub enum PgnError {
+    Io(io::Error),
+    UnexpectedCharacter { line: usize, character: char },
+    MalformedTag { line: usize },
+    UnbalancedVariation { line: usize },
+    InvalidFen(String),
+    /// The move at `ply` (counted from 0) of the mainline could not be played.
+    InvalidMove { ply: usize, error: SanError },
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::Io(e) => write!(f, "{}", e),
+            PgnError::UnexpectedCharacter { line, character } => write!(f, "line {}: unexpected character '{}'", line, character),
+            PgnError::MalformedTag { line } => write!(f, "line {}: malformed tag pair", line),
+            PgnError::UnbalancedVariation { line } => write!(f, "line {}: unbalanced variation", line),
+            PgnError::InvalidFen(fen) => write!(f, "invalid FEN '{}'", fen),
+            PgnError::InvalidMove { ply, error } => write!(f, "ply {}: {}", ply + 1, error),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl From<io::Error> for PgnError {
+    fn from(e: io::Error) -> Self {
+        PgnError::Io(e)
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnToken {
+    Tag(String, String),
+    /// Move number indication, `true` if it is followed by three dots.
+    MoveNumber(u32, bool),
+    San(String),
+    Nag(u8),
+    Comment(String),
+    VariationStart,
+    VariationEnd,
+    Result(GameState),
+}
+
+impl fmt::Display for PgnToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnToken::Tag(name, value) => write!(f, "[{} \"{}\"]", name, value.replace('\\', "\\\\").replace('"', "\\\"")),
+            PgnToken::MoveNumber(n, false) => write!(f, "{}.", n),
+            PgnToken::MoveNumber(n, true) => write!(f, "{}...", n),
+            PgnToken::San(san) => write!(f, "{}", san),
+            PgnToken::Nag(nag) => write!(f, "${}", nag),
+            PgnToken::Comment(text) => write!(f, "{{{}}}", text),
+            PgnToken::VariationStart => write!(f, "("),
+            PgnToken::VariationEnd => write!(f, ")"),
+            PgnToken::Result(result) => write!(f, "{}", result),
+        }
+    }
+}
+
+/// Numeric annotation glyph of a move suffix like `!?`.
+fn suffix_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '#' | '=' | ':' | '-' | '/')
+}
+
+/// Splits PGN text into tokens, reading one line at a time.
+pub struct PgnTokenizer<R: BufRead> {
+    input: R,
+    line: String,
+    line_number: usize,
+    queue: VecDeque<PgnToken>,
+    // Text of a brace comment that continues on the next line
+    comment: Option<String>,
+}
+
+impl<R: BufRead> PgnTokenizer<R> {
+    pub fn new(input: R) -> Self {
+        PgnTokenizer {
+            input,
+            line: String::new(),
+            line_number: 0,
+            queue: VecDeque::new(),
+            comment: None,
+        }
+    }
+
+    /// Line of the last token read, starting at 1.
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    fn tokenize_line(&mut self) -> Result<(), PgnError> {
+        let line = std::mem::take(&mut self.line);
+        let text = line.trim_end_matches(['\r', '\n']);
+        let mut chars = text.char_indices().peekable();
+
+        // Escape mechanism, the whole line is ignored
+        if self.comment.is_none() && text.starts_with('%') {
+            return Ok(());
+        }
+
+        while let Some(&(i, c)) = chars.peek() {
+            if let Some(comment) = self.comment.as_mut() {
+                match text[i..].find('}') {
+                    Some(end) => {
+                        comment.push_str(&text[i..i + end]);
+                        let comment = self.comment.take().unwrap();
+                        self.queue.push_back(PgnToken::Comment(comment.trim().to_string()));
+                        while chars.peek().is_some_and(|&(j, _)| j <= i + end) {
+                            chars.next();
+                        }
+                        continue;
+                    }
+                    None => {
+                        comment.push_str(&text[i..]);
+                        comment.push('\n');
+                        break;
+                    }
+                }
+            }
+
+            match c {
+                c if c.is_whitespace() || c == '.' => {
+                    chars.next();
+                }
+                '{' => {
+                    chars.next();
+                    self.comment = Some(String::new());
+                }
+                ';' => {
+                    self.queue.push_back(PgnToken::Comment(text[i + 1..].trim().to_string()));
+                    break;
+                }
+                '(' => {
+                    chars.next();
+                    self.queue.push_back(PgnToken::VariationStart);
+                }
+                ')' => {
+                    chars.next();
+                    self.queue.push_back(PgnToken::VariationEnd);
+                }
+                '*' => {
+                    chars.next();
+                    self.queue.push_back(PgnToken::Result(GameState::InProgress));
+                }
+                '$' => {
+                    chars.next();
+                    let mut value = 0u32;
+                    while let Some(&(_, d)) = chars.peek().filter(|(_, d)| d.is_ascii_digit()) {
+                        value = value * 10 + d.to_digit(10).unwrap();
+                        chars.next();
+                    }
+                    self.queue.push_back(PgnToken::Nag(value.min(255) as u8));
+                }
+                '!' | '?' => {
+                    let mut end = i;
+                    while let Some(&(j, _)) = chars.peek().filter(|(_, d)| *d == '!' || *d == '?') {
+                        end = j + 1;
+                        chars.next();
+                    }
+                    if let Some(nag) = suffix_nag(&text[i..end]) {
+                        self.queue.push_back(PgnToken::Nag(nag));
+                    }
+                }
+                '[' => {
+                    let tag = self.parse_tag(&text[i..])?;
+                    let consumed = tag.0;
+                    self.queue.push_back(PgnToken::Tag(tag.1, tag.2));
+                    while chars.peek().is_some_and(|&(j, _)| j < i + consumed) {
+                        chars.next();
+                    }
+                }
+                c if is_symbol_char(c) => {
+                    let mut end = i;
+                    while let Some(&(j, d)) = chars.peek().filter(|(_, d)| is_symbol_char(*d)) {
+                        end = j + d.len_utf8();
+                        chars.next();
+                    }
+                    let symbol = &text[i..end];
+                    match symbol {
+                        "1-0" | "0-1" | "1/2-1/2" => self.queue.push_back(PgnToken::Result(symbol.into())),
+                        _ if symbol.chars().all(|d| d.is_ascii_digit()) => {
+                            let mut dots = 0;
+                            while chars.peek().is_some_and(|&(_, d)| d == '.') {
+                                dots += 1;
+                                chars.next();
+                            }
+                            self.queue.push_back(PgnToken::MoveNumber(symbol.parse().unwrap_or(0), dots >= 3));
+                        }
+                        _ => self.queue.push_back(PgnToken::San(symbol.to_string())),
+                    }
+                }
+                c => return Err(PgnError::UnexpectedCharacter { line: self.line_number, character: c }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses `[Name "value"]` at the start of `text`, returns the consumed length, name and value.
+    fn parse_tag(&self, text: &str) -> Result<(usize, String, String), PgnError> {
+        let error = PgnError::MalformedTag { line: self.line_number };
+        let mut chars = text.char_indices().skip(1).peekable();
+        while chars.peek().is_some_and(|(_, c)| c.is_whitespace()) {
+            chars.next();
+        }
+        let mut name = String::new();
+        while let Some(&(_, c)) = chars.peek().filter(|(_, c)| is_symbol_char(*c)) {
+            name.push(c);
+            chars.next();
+        }
+        while chars.peek().is_some_and(|(_, c)| c.is_whitespace()) {
+            chars.next();
+        }
+        if name.is_empty() || chars.next().map(|(_, c)| c) != Some('"') {
+            return Err(error);
+        }
+        let mut value = String::new();
+        loop {
+            match chars.next() {
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, c)) => value.push(c),
+                    None => return Err(error),
+                },
+                Some((_, '"')) => break,
+                Some((_, c)) => value.push(c),
+                None => return Err(error),
+            }
+        }
+        while chars.peek().is_some_and(|(_, c)| c.is_whitespace()) {
+            chars.next();
+        }
+        match chars.next() {
+            Some((i, ']')) => Ok((i + 1, name, value)),
+            _ => Err(error),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnTokenizer<R> {
+    type Item = Result<PgnToken, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.queue.is_empty() {
+            self.line.clear();
+            match self.input.read_line(&mut self.line) {
+                Ok(0) => {
+                    // An unterminated comment at the end of the input still counts
+                    return self.comment.take().map(|c| Ok(PgnToken::Comment(c.trim().to_string())));
+                }
+                Ok(_) => {
+                    self.line_number += 1;
+                    if let Err(e) = self.tokenize_line() {
+                        return Some(Err(e));
+                    }
+                }
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+        self.queue.pop_front().map(Ok)
+    }
+}
+
+
+// Struct to hold the parsed PGN game data.
+#[derive(Debug, Clone)]
 pub struct PgnGame {
     pub tags: HashMap<String, String>,
     pub movetext: String,
-    pub parsed_moves: Vec<String>, // New field for parsed moves
+    pub parsed_moves: Vec<String>, // Mainline moves in SAN
+    /// Mainline comments, keyed by the number of moves played before them.
+    pub comments: Vec<(usize, String)>,
+    /// Mainline NAGs, keyed by the number of moves played before them.
+    pub nags: Vec<(usize, u8)>,
     pub result: GameState,
 }
 
@@ -20,131 +306,151 @@ impl PgnGame {
             tags: HashMap::new(),
             movetext: String::new(),
             parsed_moves: Vec::new(),
+            comments: Vec::new(),
+            nags: Vec::new(),
             result: GameState::InProgress,
         }
     }
 
-    /// Parses a PGN string and returns a PgnGame struct.
+    /// Parses the first game of a PGN string.
     pub fn parse(pgn_string: &str) -> Result<PgnGame, String> {
-        let mut game = PgnGame::new();
-        let mut lines = pgn_string.lines().peekable();
-        let mut raw_result_str: Option<String> = None; // To store the raw result string for move parsing
-
-        // Parse tags
-        while let Some(line) = lines.next() {
-            // Check if the line is a tag pair
-            if line.starts_with('[') && line.ends_with(']') {
-                // Remove brackets and split by the first space to separate tag name and value
-                let content = &line[1..line.len() - 1];
-                let parts: Vec<&str> = content.splitn(2, ' ').collect();
-
-                if parts.len() == 2 {
-                    let tag_name = parts[0].to_string();
-                    // Remove quotes from the tag value
-                    let tag_value = parts[1].trim_matches('"').to_string();
-                    if tag_name == "Result" {
-                        raw_result_str = Some(tag_value.clone());
-                    }
-                    game.tags.insert(tag_name, tag_value);
-                } else {
-                    return Err(format!("Malformed tag pair: {}", line));
-                }
-            } else if !line.trim().is_empty() {
-                // If it's not a tag line and not empty, it's the start of movetext
-                let mut full_movetext_lines = String::new();
-                full_movetext_lines.push_str(line.trim());
-                full_movetext_lines.push('\n');
-
-                while let Some(movetext_line) = lines.next() {
-                    full_movetext_lines.push_str(movetext_line.trim());
-                    full_movetext_lines.push('\n');
-                }
-
-                game.movetext = full_movetext_lines.trim().to_string();
-                break; // Tags are done, move to movetext parsing
-            }
+        match PgnReader::new(pgn_string.as_bytes()).next() {
+            Some(game) => game.map_err(|e| e.to_string()),
+            None => Ok(PgnGame::new()),
         }
+    }
 
-        // Extract result from tags if available
-        if let Some(result_str) = game.tags.get("Result") {
-            game.result = result_str.as_str().into();
-            raw_result_str = Some(result_str.clone());
-        } else {
-            // Attempt to extract result from the end of movetext if not in tags
-            if let Some(last_word) = game.movetext.split_whitespace().last() {
-                game.result = last_word.into();
-                raw_result_str = Some(last_word.to_string());
-            }
+    /// The FEN of the starting position given by the `SetUp`/`FEN` tags, if any.
+    pub fn starting_fen(&self) -> Option<&str> {
+        if self.tags.get("SetUp").is_some_and(|s| s == "0") {
+            return None;
         }
+        self.tags.get("FEN").map(|fen| fen.as_str())
+    }
+}
 
-        // Parse moves from movetext
-        if let Some(result_str) = raw_result_str {
-            game.parsed_moves = PgnGame::parse_movetext_into_moves(&game.movetext, &result_str);
-        } else {
-            // If no result found in tags or movetext, try to parse moves assuming no result string to filter
-            game.parsed_moves = PgnGame::parse_movetext_into_moves(&game.movetext, "");
-        }
+impl Default for PgnGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
+/// Reads games one after another from a PGN stream. Only the game being read
+/// is kept in memory, so arbitrarily large databases can be processed.
+pub struct PgnReader<R: BufRead> {
+    tokens: PgnTokenizer<R>,
+    pending: Option<PgnToken>,
+}
 
-        Ok(game)
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(input: R) -> Self {
+        PgnReader {
+            tokens: PgnTokenizer::new(input),
+            pending: None,
+        }
     }
 
-    /// Helper function to parse movetext into individual moves.
-    fn parse_movetext_into_moves(movetext: &str, game_result_str: &str) -> Vec<String> {
-        let mut moves = Vec::new();
-
-        // Regex to remove comments {}
-        let re_comments = Regex::new(r"\{[^}]*\}").expect("Invalid regex for comments");
-        let cleaned_movetext = re_comments.replace_all(movetext, "").to_string();
+    /// Skips the rest of a game after an error, up to the next tag pair.
+    fn recover(&mut self) {
+        for token in self.tokens.by_ref() {
+            if let Ok(tag @ PgnToken::Tag(..)) = token {
+                self.pending = Some(tag);
+                return;
+            }
+        }
+    }
+}
 
-        // Regex to remove variations () - basic, might not handle nested variations perfectly
-        let re_variations = Regex::new(r"\([^)]*\)").expect("Invalid regex for variations");
-        let cleaned_movetext = re_variations.replace_all(&cleaned_movetext, "").to_string();
+impl PgnReader<BufReader<File>> {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(path)?)))
+    }
+}
 
-        let parts: Vec<&str> = cleaned_movetext.split_whitespace().collect();
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, PgnError>;
 
-        for part in parts {
-            // Skip move numbers (e.g., "1.", "2.", "1...")
-            // A move number is typically one or more digits followed by a dot.
-            if part.ends_with('.') && part.chars().next().map_or(false, |c| c.is_ascii_digit()) {
-                continue;
-            }
-            // Also skip '...' which sometimes appears for black's move number if it's explicitly shown.
-            if part == "..." {
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut game = PgnGame::new();
+        let mut movetext: Vec<String> = Vec::new();
+        let mut movetext_result = None;
+        let mut depth = 0usize;
+        let mut empty = true;
+
+        loop {
+            let token = match self.pending.take().map(Ok).or_else(|| self.tokens.next()) {
+                Some(Ok(token)) => token,
+                Some(Err(e)) => {
+                    self.recover();
+                    return Some(Err(e));
+                }
+                None => break,
+            };
+            empty = false;
+
+            if let PgnToken::Tag(name, value) = token {
+                if !movetext.is_empty() {
+                    // A new game started without a termination marker
+                    self.pending = Some(PgnToken::Tag(name, value));
+                    break;
+                }
+                game.tags.insert(name, value);
                 continue;
             }
 
-            // Skip the final game result if it's still present in the movetext
-            if part == game_result_str {
-                continue;
+            let separator = !matches!(token, PgnToken::VariationEnd) && movetext.last().is_none_or(|t| t != "(");
+            match &token {
+                PgnToken::San(san) if depth == 0 => game.parsed_moves.push(san.clone()),
+                PgnToken::Nag(nag) if depth == 0 => game.nags.push((game.parsed_moves.len(), *nag)),
+                PgnToken::Comment(text) if depth == 0 => game.comments.push((game.parsed_moves.len(), text.clone())),
+                PgnToken::VariationStart => depth += 1,
+                PgnToken::VariationEnd => {
+                    if depth == 0 {
+                        let line = self.tokens.line_number();
+                        self.recover();
+                        return Some(Err(PgnError::UnbalancedVariation { line }));
+                    }
+                    depth -= 1;
+                }
+                _ => {}
             }
+            match movetext.last_mut() {
+                Some(last) if !separator => last.push_str(&token.to_string()),
+                _ => movetext.push(token.to_string()),
+            }
+            if let PgnToken::Result(result) = token && depth == 0 {
+                movetext_result = Some(result);
+                break;
+            }
+        }
 
-            // Otherwise, it's considered a move (or a NAG, or other PGN annotation, which we keep for now)
-            moves.push(part.to_string());
+        if empty {
+            return None;
         }
-        moves
+        game.movetext = movetext.join(" ");
+        game.result = match game.tags.get("Result") {
+            Some(result) => result.as_str().into(),
+            None => movetext_result.unwrap_or(GameState::InProgress),
+        };
+        Some(Ok(game))
     }
 }
 
 impl Board{
-    pub fn from_pgn(pgn_game: PgnGame) -> Result<Self, String> {
-        let game = pgn_game;
-        let mut board = Board::start();
-        for move_str in game.parsed_moves {
-            let _mv_str = move_str.clone().replace("+", "").replace("#", "");
-            let moves = board.generate_all_moves();
-            let algebraic = moves.generate_algebraic_notation(&board);
-            if algebraic.contains(&_mv_str) {
-                let index = algebraic.iter().position(|s| s == &_mv_str).unwrap();
-                let mv = moves.moves[index];
-                board.apply_move(&mv);
-            }else{
-                println!("{}", move_str);
-                println!("{:?}", algebraic);
-                panic!()
-            }
+    /// Replays the mainline of `game` from its starting position, the `FEN` tag or
+    /// the standard start position. Returns the final position and every move played.
+    pub fn from_pgn(game: &PgnGame) -> Result<(Self, Vec<Move>), PgnError> {
+        let mut board = match game.starting_fen() {
+            Some(fen) => Board::from_str(fen).map_err(|_| PgnError::InvalidFen(fen.to_string()))?,
+            None => Board::start(),
+        };
+        let mut moves = Vec::with_capacity(game.parsed_moves.len());
+        for (ply, san) in game.parsed_moves.iter().enumerate() {
+            let mv = Move::parse_san(&board, san).map_err(|error| PgnError::InvalidMove { ply, error })?;
+            board.apply_move(&mv);
+            moves.push(mv);
         }
-        Ok(board)
+        Ok((board, moves))
     }
 }
 
@@ -162,7 +468,7 @@ mod tests {
 [Black "PlayerB"]
 [Result "1-0"]
 
-1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. b4 Bb6 5. a4 a6 6. Nc3 Nf6 7. Nd5 Nxd5 8. exd5 Na5 9. Bd3 O-O 10. O-O d6 11. b5 axb5 12. axb5 c6 13. dxc6 bxc6 14. bxc6 Nxc6 15. Rxa8 Bc7 16. c3 d5 17. Ba3 e4 18. Bxf8 Kxf8 19. Bb5 Qd6 20. Rxc8+ Ke7 21. Bxc6 exf3 22. Qe1+ Kf6 23. g3 g5 24. Qe8 Kf5 25. Qxf7+ Ke4 26. Re1+ Kd3 27. Re3+ Kc2 28. Qf5+ Kd1 29. Ba4+ Kxd2 30. Rd3+ Ke1 31. Re3+ Kd2 32. Rxf3 d4 33. cxd4 Bb6 34. Qxg5 1-0"#;
+1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. b4 Bb6 5. a4 a6 6. Nc3 Nf6 7. Nd5 Nxd5 8. exd5 Na5 9. Bd3 O-O 10. O-O d6 11. b5 axb5 12. axb5 c6 13. dxc6 bxc6 14. bxc6 Nxc6 15. Rxa8 Bc7 16. c3 d5 17. Ba3 e4 18. Bxf8 Kxf8 19. Bb5 Qd6 20. Rxc8+ Ke7 21. Bxc6 exf3 22. Qe1+ Kf6 23. g3 g5 24. Qe8 Kf5 25. Qxf7+ Ke4 26. Re1+ Kd3 27. Re3+ Kc2 28. Qf5+ Kd1 29. Ba4+ Kxd2 30. Rd3+ Ke1 31. Re3+ Kd2 32. Rxf3 d4 33. cxd4 Bb6 34. Qxg5+ 1-0"#;
 
         let game = PgnGame::parse(pgn_example).unwrap();
 
@@ -172,7 +478,7 @@ mod tests {
         assert_eq!(game.tags["Black"], "PlayerB");
         assert_eq!(game.result, GameState::WhiteWin);
 
-        let expected_movetext_start = "1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. b4 Bb6 5. a4 a6 6. Nc3 Nf6 7. Nd5 Nxd5 8. exd5 Na5 9. Bd3 O-O 10. O-O d6 11. b5 axb5 12. axb5 c6 13. dxc6 bxc6 14. bxc6 Nxc6 15. Rxa8 Bc7 16. c3 d5 17. Ba3 e4 18. Bxf8 Kxf8 19. Bb5 Qd6 20. Rxc8+ Ke7 21. Bxc6 exf3 22. Qe1+ Kf6 23. g3 g5 24. Qe8 Kf5 25. Qxf7+ Ke4 26. Re1+ Kd3 27. Re3+ Kc2 28. Qf5+ Kd1 29. Ba4+ Kxd2 30. Rd3+ Ke1 31. Re3+ Kd2 32. Rxf3 d4 33. cxd4 Bb6 34. Qxg5 1-0";
+        let expected_movetext_start = "1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. b4 Bb6 5. a4 a6 6. Nc3 Nf6 7. Nd5 Nxd5 8. exd5 Na5 9. Bd3 O-O 10. O-O d6 11. b5 axb5 12. axb5 c6 13. dxc6 bxc6 14. bxc6 Nxc6 15. Rxa8 Bc7 16. c3 d5 17. Ba3 e4 18. Bxf8 Kxf8 19. Bb5 Qd6 20. Rxc8+ Ke7 21. Bxc6 exf3 22. Qe1+ Kf6 23. g3 g5 24. Qe8 Kf5 25. Qxf7+ Ke4 26. Re1+ Kd3 27. Re3+ Kc2 28. Qf5+ Kd1 29. Ba4+ Kxd2 30. Rd3+ Ke1 31. Re3+ Kd2 32. Rxf3 d4 33. cxd4 Bb6 34. Qxg5+ 1-0";
         assert_eq!(game.movetext, expected_movetext_start);
 
         let expected_moves = vec![
@@ -182,10 +488,35 @@ mod tests {
             "c3", "d5", "Ba3", "e4", "Bxf8", "Kxf8", "Bb5", "Qd6", "Rxc8+", "Ke7",
             "Bxc6", "exf3", "Qe1+", "Kf6", "g3", "g5", "Qe8", "Kf5", "Qxf7+", "Ke4",
             "Re1+", "Kd3", "Re3+", "Kc2", "Qf5+", "Kd1", "Ba4+", "Kxd2", "Rd3+", "Ke1",
-            "Re3+", "Kd2", "Rxf3", "d4", "cxd4", "Bb6", "Qxg5"
+            "Re3+", "Kd2", "Rxf3", "d4", "cxd4", "Bb6", "Qxg5+"
         ];
         assert_eq!(game.parsed_moves, expected_moves);
-        let board = Board::from_pgn(game).unwrap();
+        let (board, moves) = Board::from_pgn(&game).unwrap();
+        assert_eq!(moves.len(), expected_moves.len());
+        assert_eq!(moves[0].to_lan(), "e2e4");
+        assert_eq!(board.state.color, crate::types::color::Color::Black);
+
+        // Writing the moves back gives the original SAN, check marks included
+        let mut replay = Board::start();
+        for (mv, san) in moves.iter().zip(game.parsed_moves.iter()) {
+            assert_eq!(&mv.to_san(&replay), san);
+            replay.apply_move(mv);
+        }
+    }
+
+    #[test]
+    fn test_from_pgn_with_fen_tag() {
+        let pgn = r#"[SetUp "1"]
+[FEN "4k3/P7/8/8/8/8/8/4K3 w - - 0 1"]
+
+1. a8=Q+ Kd7 2. Qb7+ *"#;
+        let game = PgnGame::parse(pgn).unwrap();
+        let (board, moves) = Board::from_pgn(&game).unwrap();
+        assert_eq!(moves.iter().map(|mv| mv.to_lan()).collect::<Vec<_>>(), vec!["a7a8q", "e8d7", "a8b7"]);
+        assert!(board.to_fen().starts_with("8/1Q1k4/8/8/8/8/8/4K3 b - -"));
+
+        let illegal = PgnGame::parse("1. e4 e4 *").unwrap();
+        assert!(matches!(Board::from_pgn(&illegal), Err(PgnError::InvalidMove { ply: 1, .. })));
     }
 
     #[test]
@@ -237,6 +568,61 @@ mod tests {
         assert_eq!(game.parsed_moves, expected_moves);
     }
 
+    #[test]
+    fn test_nested_variations_comments_and_nags() {
+        let pgn = r#"[Event "Annotated"]
+[White "A \"quoted\" name"]
+[Result "1-0"]
+
+% This escape line is ignored
+1. e4 $1 e5!? (1... c5 2. Nf3 (2. c3 {Alapin
+spanning lines} d5) 2... d6) 2. Nf3 {Main line} Nc6?! ; rest of the line
+3. Bb5 1-0"#;
+        let game = PgnGame::parse(pgn).unwrap();
+        assert_eq!(game.tags["White"], "A \"quoted\" name");
+        assert_eq!(game.parsed_moves, vec!["e4", "e5", "Nf3", "Nc6", "Bb5"]);
+        assert_eq!(game.nags, vec![(1, 1), (2, 5), (4, 6)]);
+        assert_eq!(game.comments, vec![(3, "Main line".to_string()), (4, "rest of the line".to_string())]);
+        assert_eq!(game.result, GameState::WhiteWin);
+        assert!(game.movetext.contains("(1... c5 2. Nf3 (2. c3 {Alapin\nspanning lines} d5) 2... d6)"));
+    }
+
+    #[test]
+    fn test_multi_game_stream() {
+        let pgn = r#"[Event "First"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"]
+
+1. e4 Kd7 *
+
+[Event "Second"]
+
+1. d4 d5 2. c4 1/2-1/2
+1. Nf3 0-1
+[Event "Fourth"]
+1. e4"#;
+        let games: Vec<PgnGame> = PgnReader::new(pgn.as_bytes()).collect::<Result<_, _>>().unwrap();
+        assert_eq!(games.len(), 4);
+        assert_eq!(games[0].starting_fen(), Some("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+        assert_eq!(games[0].result, GameState::InProgress);
+        assert_eq!(games[1].tags["Event"], "Second");
+        assert_eq!(games[1].starting_fen(), None);
+        assert_eq!(games[1].result, GameState::Draw);
+        assert!(games[2].tags.is_empty());
+        assert_eq!(games[2].result, GameState::BlackWin);
+        assert_eq!(games[3].parsed_moves, vec!["e4"]);
+    }
+
+    #[test]
+    fn test_reader_recovers_after_error() {
+        let pgn = "[Event \"Broken\"]\n1. e4 ) e5 1-0\n\n[Event \"Fine\"]\n1. d4 1-0\n";
+        let mut reader = PgnReader::new(pgn.as_bytes());
+        assert!(matches!(reader.next(), Some(Err(PgnError::UnbalancedVariation { line: 2 }))));
+        let game = reader.next().unwrap().unwrap();
+        assert_eq!(game.tags["Event"], "Fine");
+        assert!(reader.next().is_none());
+    }
+
     #[test]
     fn test_empty_pgn() {
         let pgn_empty = "";
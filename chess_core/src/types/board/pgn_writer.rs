@@ -0,0 +1,351 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::time::Duration;
+use super::board::Board;
+use super::parse_pgn::PgnToken;
+use crate::types::board::internalstate::GameState;
+use crate::types::board::outcome::Outcome;
+use crate::types::color::Color;
+use crate::types::moves::Move;
+
+const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const LINE_WIDTH: usize = 80;
+/// Tags every exported game carries, in this order.
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// Engine score of a move, from white's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PgnEval {
+    Centipawns(i32),
+    /// Mate in n moves, negative if black mates.
+    Mate(i32),
+}
+
+impl fmt::Display for PgnEval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnEval::Centipawns(cp) => {
+                let sign = if *cp < 0 { "-" } else { "" };
+                write!(f, "{}{}.{:02}", sign, cp.abs() / 100, cp.abs() % 100)
+            }
+            PgnEval::Mate(n) => write!(f, "#{}", n),
+        }
+    }
+}
+
+/// Optional information written as a comment after a move,
+/// e.g. `{[%eval 0.34] [%depth 12] [%clk 0:01:00]}`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MoveAnnotation {
+    pub eval: Option<PgnEval>,
+    pub depth: Option<u32>,
+    /// Clock time left after the move.
+    pub clock: Option<Duration>,
+    /// Time spent on the move.
+    pub elapsed: Option<Duration>,
+    pub comment: Option<String>,
+}
+
+impl MoveAnnotation {
+    pub fn is_empty(&self) -> bool {
+        self.to_string().is_empty()
+    }
+}
+
+/// `H:MM:SS`, with tenths if the duration is not a whole second.
+fn format_clock(time: Duration) -> String {
+    let secs = time.as_secs();
+    let tenths = time.subsec_millis() / 100;
+    let clock = format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60);
+    if tenths == 0 { clock } else { format!("{}.{}", clock, tenths) }
+}
+
+impl fmt::Display for MoveAnnotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(eval) = self.eval {
+            parts.push(format!("[%eval {}]", eval));
+        }
+        if let Some(depth) = self.depth {
+            parts.push(format!("[%depth {}]", depth));
+        }
+        if let Some(clock) = self.clock {
+            parts.push(format!("[%clk {}]", format_clock(clock)));
+        }
+        if let Some(elapsed) = self.elapsed {
+            parts.push(format!("[%emt {}]", format_clock(elapsed)));
+        }
+        if let Some(comment) = self.comment.as_ref().filter(|c| !c.is_empty()) {
+            // Braces cannot be escaped inside a comment
+            parts.push(comment.replace(['{', '}'], ""));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Collects the tags and moves of a game and exports them as PGN in export
+/// format: seven tag roster first, move numbers and lines of at most 80 characters.
+#[derive(Debug, Clone)]
+pub struct PgnWriter {
+    start: Board,
+    tags: Vec<(String, String)>,
+    moves: Vec<(Move, MoveAnnotation)>,
+    result: GameState,
+}
+
+impl PgnWriter {
+    /// Starts a game from `board`. A `SetUp`/`FEN` tag pair is written if it is
+    /// not the standard starting position.
+    pub fn new(board: &Board) -> Self {
+        PgnWriter {
+            start: board.clone(),
+            tags: Vec::new(),
+            moves: Vec::new(),
+            result: GameState::InProgress,
+        }
+    }
+
+    /// Starts a game from `board` with `moves` already played.
+    pub fn from_moves(board: &Board, moves: &[Move]) -> Self {
+        let mut writer = PgnWriter::new(board);
+        for mv in moves {
+            writer.push(*mv);
+        }
+        writer
+    }
+
+    /// Sets a tag, replacing an earlier value. `Result` is taken from `set_result`.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn set_result(&mut self, result: GameState) {
+        self.result = result;
+    }
+
+    /// Sets the result and the `Termination` tag from how the game ended.
+    pub fn set_outcome(&mut self, outcome: Outcome) {
+        self.result = outcome.result();
+        self.set_tag("Termination", outcome.pgn_termination());
+    }
+
+    pub fn push(&mut self, mv: Move) {
+        self.moves.push((mv, MoveAnnotation::default()));
+    }
+
+    pub fn push_annotated(&mut self, mv: Move, annotation: MoveAnnotation) {
+        self.moves.push((mv, annotation));
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// All tags in export order.
+    fn export_tags(&self) -> Vec<(String, String)> {
+        let mut tags: Vec<(String, String)> = SEVEN_TAG_ROSTER
+            .iter()
+            .map(|(name, default)| {
+                let value = match *name {
+                    "Result" => self.result.to_string(),
+                    _ => self.tags.iter().find(|(n, _)| n == name).map_or(default.to_string(), |(_, v)| v.clone()),
+                };
+                (name.to_string(), value)
+            })
+            .collect();
+
+        let fen = self.start.to_fen();
+        if fen != STARTING_FEN {
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), fen));
+        }
+        for (name, value) in self.tags.iter() {
+            if !tags.iter().any(|(n, _)| n == name) {
+                tags.push((name.clone(), value.clone()));
+            }
+        }
+        tags
+    }
+
+    /// The movetext as tokens, with the moves in SAN.
+    fn movetext_tokens(&self) -> Vec<PgnToken> {
+        let mut tokens = Vec::new();
+        let mut board = self.start.clone();
+        let mut number = board.state.fullmove_number.max(1) as u32;
+        // Black moves need a number after a comment or at the start
+        let mut needs_number = true;
+
+        for (mv, annotation) in self.moves.iter() {
+            let white = board.state.color == Color::White;
+            if white {
+                tokens.push(PgnToken::MoveNumber(number, false));
+            } else if needs_number {
+                tokens.push(PgnToken::MoveNumber(number, true));
+            }
+            tokens.push(PgnToken::San(mv.to_san(&board)));
+            board.apply_move(mv);
+
+            needs_number = !annotation.is_empty();
+            if needs_number {
+                tokens.push(PgnToken::Comment(annotation.to_string()));
+            }
+            if !white {
+                number += 1;
+            }
+        }
+        tokens.push(PgnToken::Result(self.result));
+        tokens
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for (name, value) in self.export_tags() {
+            writeln!(out, "{}", PgnToken::Tag(name, value))?;
+        }
+        writeln!(out)?;
+
+        // Comments may be broken between words, everything else is kept whole
+        let mut line = String::new();
+        for token in self.movetext_tokens() {
+            let text = token.to_string();
+            for word in text.split(' ') {
+                if !line.is_empty() && line.len() + 1 + word.len() > LINE_WIDTH {
+                    writeln!(out, "{}", line)?;
+                    line.clear();
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(word);
+            }
+        }
+        writeln!(out, "{}", line)?;
+        writeln!(out)
+    }
+
+    pub fn to_pgn(&self) -> String {
+        let mut out = Vec::new();
+        self.write(&mut out).expect("Writing to a Vec cannot fail");
+        String::from_utf8(out).expect("PGN is valid UTF-8")
+    }
+}
+
+impl fmt::Display for PgnWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_pgn())
+    }
+}
+
+
+
+
+/// ######################################################
+/// ################### TESTING ##########################
+/// ######################################################
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use crate::types::board::parse_pgn::PgnGame;
+    use crate::types::board::outcome::Termination;
+
+    fn play(board: &Board, sans: &[&str]) -> Vec<Move> {
+        let mut board = board.clone();
+        sans.iter()
+            .map(|san| {
+                let mv = Move::parse_san(&board, san).unwrap();
+                board.apply_move(&mv);
+                mv
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_write_game() {
+        let start = Board::start();
+        let moves = play(&start, &["e4", "e5", "Nf3", "Nc6", "Bc4", "Nd4", "Nxe5", "Qg5", "Nxf7", "Qxg2", "Rf1", "Qxe4+", "Be2", "Nf3#"]);
+        let mut writer = PgnWriter::from_moves(&start, &moves);
+        writer.set_tag("White", "Clockwork");
+        writer.set_tag("Black", "Opponent \"X\"");
+        writer.set_tag("TimeControl", "60+1");
+        writer.set_result(GameState::BlackWin);
+        let pgn = writer.to_pgn();
+
+        let expected = "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n[White \"Clockwork\"]\n\
+            [Black \"Opponent \\\"X\\\"\"]\n[Result \"0-1\"]\n[TimeControl \"60+1\"]\n\n\
+            1. e4 e5 2. Nf3 Nc6 3. Bc4 Nd4 4. Nxe5 Qg5 5. Nxf7 Qxg2 6. Rf1 Qxe4+ 7. Be2 Nf3#\n0-1\n\n";
+        assert_eq!(pgn, expected);
+
+        let game = PgnGame::parse(&pgn).unwrap();
+        assert_eq!(game.tags["Black"], "Opponent \"X\"");
+        assert_eq!(game.result, GameState::BlackWin);
+        assert_eq!(Board::from_pgn(&game).unwrap().1, moves);
+    }
+
+    #[test]
+    fn test_outcome_tags() {
+        let start = Board::start();
+        let moves = play(&start, &["f3", "e5", "g4", "Qh4#"]);
+        let mut board = start.clone();
+        for mv in moves.iter() {
+            board.apply_move(mv);
+        }
+        let mut writer = PgnWriter::from_moves(&start, &moves);
+        writer.set_outcome(board.outcome().unwrap());
+        let pgn = writer.to_pgn();
+        assert!(pgn.contains("[Result \"0-1\"]\n[Termination \"normal\"]"), "{}", pgn);
+        assert!(pgn.contains("\n1. f3 e5 2. g4 Qh4# 0-1\n"), "{}", pgn);
+
+        writer.set_outcome(Outcome::win(Color::White, Termination::Timeout));
+        let game = PgnGame::parse(&writer.to_pgn()).unwrap();
+        assert_eq!(game.result, GameState::WhiteWin);
+        assert_eq!(game.tags["Termination"], "time forfeit");
+    }
+
+    #[test]
+    fn test_annotations_and_wrapping() {
+        let start = Board::from_str("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 2 2").unwrap();
+        let moves = play(&start, &["Nf6", "Nc3", "Bb4", "Bc4", "O-O", "O-O", "d6", "d3", "Bxc3", "bxc3", "h6", "h3"]);
+        let mut writer = PgnWriter::new(&start);
+        for (i, mv) in moves.iter().enumerate() {
+            let annotation = MoveAnnotation {
+                eval: Some(if i == 3 { PgnEval::Mate(-4) } else { PgnEval::Centipawns(34 - 20 * i as i32) }),
+                depth: Some(12),
+                clock: Some(Duration::from_millis(61_500)),
+                ..MoveAnnotation::default()
+            };
+            writer.push_annotated(*mv, annotation);
+        }
+        let pgn = writer.to_pgn();
+        // Comments may be wrapped anywhere between words
+        let flat = pgn.replace('\n', " ");
+
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 2 2\"]"));
+        assert!(flat.contains("2... Nf6 {[%eval 0.34] [%depth 12] [%clk 0:01:01.5]} 3. Nc3"));
+        assert!(flat.contains("3. Nc3 {[%eval 0.14]"));
+        assert!(flat.contains("[%eval #-4]"));
+        assert!(flat.contains("[%eval -0.46]"));
+        assert!(pgn.lines().all(|line| line.len() <= LINE_WIDTH), "{}", pgn);
+
+        let game = PgnGame::parse(&pgn).unwrap();
+        assert_eq!(Board::from_pgn(&game).unwrap().1, moves);
+        assert_eq!(game.comments.len(), moves.len());
+        assert_eq!(game.result, GameState::InProgress);
+    }
+}
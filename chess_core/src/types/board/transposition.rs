@@ -7,37 +7,38 @@ use crate::types::moves::Move;
 include!(concat!(env!("OUT_DIR"), "/zobrist_keys.rs"));
 
 impl Board{
-    pub fn hash(&mut self) -> u64{
-        if self.state.hash == 0{
-            let mut hash = 0;
-            for i in 0..Piece::COUNT{
-                for j in self.pieces[i].to_squares(){
-                    hash ^= ZOBRIST_KEYS.piece_keys[i][j as usize];
-                }
-            }
-            if self.state.color == Color::Black{
-                hash ^= ZOBRIST_KEYS.black_to_move_key;
-            }
-            if self.state.castling.black_king(){
-                hash ^= ZOBRIST_KEYS.castling_keys[0];
-            }
-            if self.state.castling.black_queen(){
-                hash ^= ZOBRIST_KEYS.castling_keys[1];
-            }
-            if self.state.castling.white_king(){
-                hash ^= ZOBRIST_KEYS.castling_keys[2];
-            }
-            if self.state.castling.white_queen(){
-                hash ^= ZOBRIST_KEYS.castling_keys[3];
-            }
-            if self.state.en_passant != Square::None{
-                hash ^= ZOBRIST_KEYS.en_passant_keys[(self.state.en_passant as usize)%8];
+    /// Zobrist hash of the position, updated with every change to the board.
+    pub fn hash(&self) -> u64{
+        self.state.hash
+    }
+
+    /// Zobrist hash computed from scratch, `hash` always has to agree with it.
+    pub fn compute_hash(&self) -> u64{
+        let mut hash = 0;
+        for i in 0..Piece::COUNT{
+            for j in self.pieces[i]{
+                hash ^= ZOBRIST_KEYS.piece_keys[i][j as usize];
             }
-            self.state.hash = hash;
-            hash
-        }else{
-            self.state.hash
         }
+        if self.state.color == Color::Black{
+            hash ^= ZOBRIST_KEYS.black_to_move_key;
+        }
+        if self.state.castling.black_king(){
+            hash ^= ZOBRIST_KEYS.castling_keys[0];
+        }
+        if self.state.castling.black_queen(){
+            hash ^= ZOBRIST_KEYS.castling_keys[1];
+        }
+        if self.state.castling.white_king(){
+            hash ^= ZOBRIST_KEYS.castling_keys[2];
+        }
+        if self.state.castling.white_queen(){
+            hash ^= ZOBRIST_KEYS.castling_keys[3];
+        }
+        if self.state.en_passant != Square::None{
+            hash ^= ZOBRIST_KEYS.en_passant_keys[(self.state.en_passant as usize)%8];
+        }
+        hash
     }
 }
 
@@ -170,10 +171,7 @@ impl TranspositionTable{
     }
 
     pub fn clear(&mut self) {
-        if !self.table.is_empty() {
-            self.table = vec![TableEntry::default(); self.mask + 1];
-        }
-         println!("Transposition Table Cleared.");
+        self.table.fill(TableEntry::default());
     }
 
     pub fn num_entries(&self) -> usize {
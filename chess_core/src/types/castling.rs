@@ -60,23 +60,28 @@ impl Castling{
         false
     }
 
+    /// Neither side may castle anymore.
+    pub fn is_empty(&self) -> bool{
+        self.0 == 0
+    }
+
     pub fn to_fen_string(&self) -> String{
         let mut fen = String::new();
         if self.0 == 0{
             fen.push('-');
         }else{
-            if self.white_queen() {
-                fen.push('Q');
-            }
             if self.white_king() {
                 fen.push('K');
             }
-            if self.black_queen() {
-                fen.push('q');
+            if self.white_queen() {
+                fen.push('Q');
             }
             if self.black_king() {
                 fen.push('k');
             }
+            if self.black_queen() {
+                fen.push('q');
+            }
         }
         fen
     }
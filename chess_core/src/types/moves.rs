@@ -1,7 +1,9 @@
+use std::fmt;
 use std::mem;
 use crate::types::square::Square;
 use crate::types::board::board::Board;
 use crate::types::lists::MoveList;
+use crate::types::piece::PieceType;
 
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -28,6 +30,27 @@ pub enum MoveType{
     QPromotionCapture=15,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    Empty,
+    InvalidSyntax(String),
+    IllegalMove(String),
+    AmbiguousMove(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanError::Empty => write!(f, "empty move"),
+            SanError::InvalidSyntax(san) => write!(f, "invalid SAN '{}'", san),
+            SanError::IllegalMove(san) => write!(f, "illegal move '{}'", san),
+            SanError::AmbiguousMove(san) => write!(f, "ambiguous move '{}'", san),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
 impl Move{
     pub const NULL: Self = Self(0);
 
@@ -75,53 +98,159 @@ impl Move{
         false
     }
 
+    /// SAN of the move without check or mate marker, `movelist` has to hold all
+    /// legal moves of `board`.
     pub fn generate_algebraic_notation(&self, board: &Board, movelist: &MoveList) -> String {
-        // Notation without gamestate and checks
-        let mut notation = String::new();
-        let mut disambiguation = Vec::<Move>::new();
-        let mv_piece = board.mailbox[self.from() as usize];
-        let mv_to = self.to();
-        for _mv in movelist.moves.iter() {
-            if board.mailbox[_mv.from() as usize] == mv_piece && _mv.to() == mv_to && _mv != self {
-                disambiguation.push(*_mv);
-            };
+        match self.kind() {
+            MoveType::KingCastle => return "O-O".to_string(),
+            MoveType::QueenCastle => return "O-O-O".to_string(),
+            _ => {}
         }
-        if self.kind() == MoveType::KingCastle {
-            return "O-O".to_string();
-        }else if self.kind() == MoveType::QueenCastle {
-            return "O-O-O".to_string();
-        }
-        let mut disambiguate_rank = false;
-        let mut disambiguate_file = false;
-        for mv in disambiguation.iter() {
-            if Square::new(mv.from()).rank() != Square::new(self.from()).rank() {
-                disambiguate_rank = true;
-            }else if Square::new(mv.from()).file() != Square::new(self.from()).file() {
-                disambiguate_file = true;
+        let from = Square::new(self.from());
+        let to = Square::new(self.to());
+        let piece = board.mailbox[self.from() as usize];
+        let mut notation = String::with_capacity(8);
+
+        if piece.piece_type() == PieceType::Pawn {
+            if self.is_capture() {
+                notation.push((b'a' + from.file()) as char);
+            }
+        } else {
+            notation.push(piece.to_char().to_ascii_uppercase());
+            let others: Vec<Square> = movelist
+                .iter()
+                .filter(|mv| mv.to() == self.to() && mv.from() != self.from() && board.mailbox[mv.from() as usize] == piece)
+                .map(|mv| Square::new(mv.from()))
+                .collect();
+            if !others.is_empty() {
+                if others.iter().all(|sq| sq.file() != from.file()) {
+                    notation.push((b'a' + from.file()) as char);
+                } else if others.iter().all(|sq| sq.rank() != from.rank()) {
+                    notation.push((b'1' + from.rank()) as char);
+                } else {
+                    notation.push_str(&from.to_algebraic().unwrap_or_default());
+                }
             }
         }
-        if disambiguate_rank && disambiguate_file {
-            notation.push_str(Square::new(self.from()).to_algebraic().unwrap().to_ascii_lowercase().as_str());
-        }else if disambiguate_rank {
-            notation.push((b'1' + Square::new(self.from()).rank()) as char);
-        }else if disambiguate_file {
-            notation.push((b'a' + Square::new(self.from()).file()) as char);
-        }
-        notation.push(mv_piece.to_char().to_ascii_uppercase());
-        notation = notation.replace("P", "");
         if self.is_capture() {
-            if notation.is_empty() {
-                notation.push((b'a' + Square::new(self.from()).file()) as char);
-            }
             notation.push('x');
         }
-        notation.push_str(Square::new(self.to()).to_algebraic().unwrap().as_str());
+        notation.push_str(&to.to_algebraic().unwrap_or_default());
+        match self.promotion_type() {
+            PieceType::Knight => notation.push_str("=N"),
+            PieceType::Bishop => notation.push_str("=B"),
+            PieceType::Rook => notation.push_str("=R"),
+            PieceType::Queen => notation.push_str("=Q"),
+            _ => {}
+        }
         notation
     }
+
+    /// Standard algebraic notation of the move in `board`, with `+` or `#` if it gives check or mate.
+    pub fn to_san(&self, board: &Board) -> String {
+        let mut board = board.clone();
+        let moves = board.generate_all_moves();
+        let mut san = self.generate_algebraic_notation(&board, &moves);
+        board.apply_move(self);
+        let replies = board.generate_all_moves();
+        if !board.state.checker.is_empty() {
+            san.push(if replies.is_empty() { '#' } else { '+' });
+        }
+        san
+    }
 }
 
 
 impl Move{
+    /// Promotion piece of the move, `PieceType::None` for other moves.
+    pub fn promotion_type(&self) -> PieceType {
+        match self.kind() {
+            MoveType::KPromotion | MoveType::KPromotionCapture => PieceType::Knight,
+            MoveType::BPromotion | MoveType::BPromotionCapture => PieceType::Bishop,
+            MoveType::RPromotion | MoveType::RPromotionCapture => PieceType::Rook,
+            MoveType::QPromotion | MoveType::QPromotionCapture => PieceType::Queen,
+            _ => PieceType::None,
+        }
+    }
+
+    /// Parses a move in standard algebraic notation, e.g. `Nbd7`, `exd8=Q+`, `O-O-O` or `e4!?`.
+    ///
+    /// Check and mate markers and annotation glyphs are ignored. Castling may be
+    /// written with zeros, the `=` of a promotion and capture markers may be omitted.
+    pub fn parse_san(board: &Board, san: &str) -> Result<Self, SanError> {
+        let invalid = || SanError::InvalidSyntax(san.to_string());
+        let body = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        if body.is_empty() {
+            return Err(SanError::Empty);
+        }
+
+        let mut board = board.clone();
+        let moves = board.generate_all_moves();
+        let candidates: Vec<Move> = match body {
+            "O-O" | "0-0" => moves.iter().filter(|mv| mv.kind() == MoveType::KingCastle).copied().collect(),
+            "O-O-O" | "0-0-0" => moves.iter().filter(|mv| mv.kind() == MoveType::QueenCastle).copied().collect(),
+            _ => {
+                let (piece, rest) = match body.chars().next() {
+                    Some('N') => (PieceType::Knight, &body[1..]),
+                    Some('B') => (PieceType::Bishop, &body[1..]),
+                    Some('R') => (PieceType::Rook, &body[1..]),
+                    Some('Q') => (PieceType::Queen, &body[1..]),
+                    Some('K') => (PieceType::King, &body[1..]),
+                    _ => (PieceType::Pawn, body),
+                };
+
+                let (rest, promotion) = match rest.char_indices().last() {
+                    Some((i, c)) if piece == PieceType::Pawn && "NBRQ".contains(c) => {
+                        let promotion = match c {
+                            'N' => PieceType::Knight,
+                            'B' => PieceType::Bishop,
+                            'R' => PieceType::Rook,
+                            _ => PieceType::Queen,
+                        };
+                        (rest[..i].trim_end_matches('='), promotion)
+                    }
+                    _ => (rest, PieceType::None),
+                };
+
+                let squares: String = rest.chars().filter(|c| *c != 'x' && *c != ':' && *c != '-').collect();
+                if squares.len() < 2 || !squares.is_ascii() {
+                    return Err(invalid());
+                }
+                let (hint, target) = squares.split_at(squares.len() - 2);
+                let to = Square::try_from(target).map_err(|_| invalid())?;
+                let mut from_file = None;
+                let mut from_rank = None;
+                for c in hint.chars() {
+                    match c {
+                        'a'..='h' => from_file = Some(c as u8 - b'a'),
+                        '1'..='8' => from_rank = Some(c as u8 - b'1'),
+                        _ => return Err(invalid()),
+                    }
+                }
+
+                moves
+                    .iter()
+                    .filter(|mv| {
+                        let from = Square::new(mv.from());
+                        mv.to() == to.to_index()
+                            && board.mailbox[mv.from() as usize].piece_type() == piece
+                            && mv.promotion_type() == promotion
+                            && !matches!(mv.kind(), MoveType::KingCastle | MoveType::QueenCastle)
+                            && from_file.is_none_or(|f| f == from.file())
+                            && from_rank.is_none_or(|r| r == from.rank())
+                    })
+                    .copied()
+                    .collect()
+            }
+        };
+
+        match candidates.len() {
+            1 => Ok(candidates[0]),
+            0 => Err(SanError::IllegalMove(san.to_string())),
+            _ => Err(SanError::AmbiguousMove(san.to_string())),
+        }
+    }
+
     pub fn from_lan(board: &Board, uci: &str) -> Self{
         let mut _board = board.clone();
         let mut promotion_str = "";
@@ -205,4 +334,106 @@ mod test{
         assert_eq!(m.to(), 2);
         assert_eq!(m.kind(), MoveType::Normal);
     }
+
+    #[test]
+    fn test_parse_san() {
+        use std::str::FromStr;
+        let board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let lan = |san: &str| Move::parse_san(&board, san).map(|mv| mv.to_lan());
+        assert_eq!(lan("O-O").unwrap(), "e1g1");
+        assert_eq!(lan("0-0-0+").unwrap(), "e1c1");
+        assert_eq!(lan("Nxf7!?").unwrap(), "e5f7");
+        assert_eq!(lan("dxe6").unwrap(), "d5e6");
+        assert_eq!(lan("Qxh3").unwrap(), "f3h3");
+        assert_eq!(lan("Rb1").unwrap(), "a1b1");
+        assert_eq!(lan("Ncb5").unwrap(), "c3b5");
+        assert_eq!(lan("N3b5").unwrap(), "c3b5");
+        assert_eq!(lan("Nc3-b5").unwrap(), "c3b5");
+        assert_eq!(lan("gxh3").unwrap(), "g2h3");
+        assert_eq!(lan("Ng4"), Ok("e5g4".to_string()));
+        assert_eq!(lan("Nd7"), Ok("e5d7".to_string()));
+        assert_eq!(lan("Qe5"), Err(SanError::IllegalMove("Qe5".to_string())));
+        assert_eq!(lan(""), Err(SanError::Empty));
+        assert!(matches!(lan("Zz9"), Err(SanError::InvalidSyntax(_))));
+
+        let ambiguous = Board::from_str("k7/8/8/8/8/8/8/R5RK w - - 0 1").unwrap();
+        assert_eq!(Move::parse_san(&ambiguous, "Rd1"), Err(SanError::AmbiguousMove("Rd1".to_string())));
+        assert_eq!(Move::parse_san(&ambiguous, "Rad1").unwrap().to_lan(), "a1d1");
+
+        let promotion = Board::from_str("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(Move::parse_san(&promotion, "a8=Q").unwrap().to_lan(), "a7a8q");
+        assert_eq!(Move::parse_san(&promotion, "axb8N+").unwrap().to_lan(), "a7b8n");
+        assert!(Move::parse_san(&promotion, "a8").is_err());
+    }
+
+    #[test]
+    fn test_to_san() {
+        use std::str::FromStr;
+        let san = |fen: &str, lan: &str| {
+            let board = Board::from_str(fen).unwrap();
+            Move::from_lan(&board, lan).to_san(&board)
+        };
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(san(kiwipete, "e1g1"), "O-O");
+        assert_eq!(san(kiwipete, "e1c1"), "O-O-O");
+        assert_eq!(san(kiwipete, "e5f7"), "Nxf7");
+        assert_eq!(san(kiwipete, "g2h3"), "gxh3");
+        assert_eq!(san(kiwipete, "c3b5"), "Nb5");
+        assert_eq!(san("k7/8/8/8/8/8/8/R5RK w - - 0 1", "a1d1"), "Rad1");
+        assert_eq!(san("7k/8/8/8/R7/8/8/R6K w - - 0 1", "a1a2"), "R1a2");
+        assert_eq!(san("7k/8/8/8/Q1Q5/8/Q7/7K w - - 0 1", "a4b3"), "Qa4b3");
+        assert_eq!(san("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q"), "axb8=Q+");
+        assert_eq!(san("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8"), "Ra8#");
+        assert_eq!(san("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", "e5f6"), "exf6");
+    }
+
+    fn assert_round_trip(board: &mut Board, depth: u8) {
+        if depth == 0 {
+            return;
+        }
+        let moves = board.generate_all_moves();
+        for mv in moves.iter() {
+            let san = mv.to_san(board);
+            assert_eq!(Move::parse_san(board, &san), Ok(*mv), "{} in {}", san, board.to_fen());
+            board.apply_move(mv);
+            assert_round_trip(board, depth - 1);
+            board.undo_move(mv);
+        }
+    }
+
+    #[test]
+    fn test_san_round_trip() {
+        use std::str::FromStr;
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ] {
+            assert_round_trip(&mut Board::from_str(fen).unwrap(), 2);
+        }
+    }
+    #[test]
+    fn test_san_corpus() {
+        use std::str::FromStr;
+        use crate::types::board::parse_pgn::PgnReader;
+        // Generated by testdata/gen_san_corpus.py with an independent move generator
+        let corpus = include_str!("../../testdata/san_corpus.pgn");
+        let mut games = 0;
+        for game in PgnReader::new(corpus.as_bytes()) {
+            let game = game.unwrap();
+            let mut board = match game.starting_fen() {
+                Some(fen) => Board::from_str(fen).unwrap(),
+                None => Board::start(),
+            };
+            for san in game.parsed_moves.iter() {
+                let mv = Move::parse_san(&board, san).unwrap_or_else(|e| panic!("{} in {}: {:?}", san, board.to_fen(), e));
+                assert_eq!(&mv.to_san(&board), san, "{}", board.to_fen());
+                board.apply_move(&mv);
+            }
+            games += 1;
+        }
+        assert_eq!(games, 300);
+    }
+
 }
\ No newline at end of file
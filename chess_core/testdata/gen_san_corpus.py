@@ -0,0 +1,332 @@
+"""Generates san_corpus.pgn, the SAN fixture used by the move tests.
+
+The games are random but biased towards checks, mates, castling, en passant,
+promotions and ambiguous moves. Moves are generated and written with this
+standalone implementation so the Rust SAN code is checked against an
+independent one.
+
+    python3 gen_san_corpus.py > san_corpus.pgn
+"""
+
+import random
+
+FILES = "abcdefgh"
+KNIGHT = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)]
+KING = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)]
+BISHOP = [(1, 1), (1, -1), (-1, 1), (-1, -1)]
+ROOK = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+
+
+def name(sq):
+    return FILES[sq % 8] + str(sq // 8 + 1)
+
+
+def parse_square(s):
+    return FILES.index(s[0]) + 8 * (int(s[1]) - 1)
+
+
+class Position:
+    def __init__(self, fen):
+        placement, color, castling, ep, half, full = fen.split()
+        self.board = [None] * 64
+        for r, row in enumerate(placement.split("/")):
+            f = 0
+            for c in row:
+                if c.isdigit():
+                    f += int(c)
+                else:
+                    self.board[(7 - r) * 8 + f] = c
+                    f += 1
+        self.white = color == "w"
+        self.castling = "" if castling == "-" else castling
+        self.ep = None if ep == "-" else parse_square(ep)
+        self.half = int(half)
+        self.full = int(full)
+
+    def copy(self):
+        p = Position.__new__(Position)
+        p.board = self.board[:]
+        p.white = self.white
+        p.castling = self.castling
+        p.ep = self.ep
+        p.half = self.half
+        p.full = self.full
+        return p
+
+    def own(self, piece, white):
+        return piece is not None and piece.isupper() == white
+
+    def attacked(self, sq, by_white):
+        f, r = sq % 8, sq // 8
+        pawn_rank = -1 if by_white else 1
+        for df in (-1, 1):
+            ff, rr = f + df, r + pawn_rank
+            if 0 <= ff < 8 and 0 <= rr < 8 and self.board[rr * 8 + ff] == ("P" if by_white else "p"):
+                return True
+        for steps, pieces, slide in ((KNIGHT, "N", False), (KING, "K", False), (BISHOP, "BQ", True), (ROOK, "RQ", True)):
+            for df, dr in steps:
+                ff, rr = f + df, r + dr
+                while 0 <= ff < 8 and 0 <= rr < 8:
+                    piece = self.board[rr * 8 + ff]
+                    if piece is not None:
+                        if self.own(piece, by_white) and piece.upper() in pieces:
+                            return True
+                        break
+                    if not slide:
+                        break
+                    ff, rr = ff + df, rr + dr
+        return False
+
+    def king(self, white):
+        return self.board.index("K" if white else "k")
+
+    def in_check(self):
+        return self.attacked(self.king(self.white), not self.white)
+
+    def pseudo_moves(self):
+        moves = []
+        white = self.white
+        for sq, piece in enumerate(self.board):
+            if not self.own(piece, white):
+                continue
+            f, r = sq % 8, sq // 8
+            kind = piece.upper()
+            if kind == "P":
+                up = 1 if white else -1
+                last = 7 if white else 0
+                start = 1 if white else 6
+                targets = []
+                one = sq + 8 * up
+                if self.board[one] is None:
+                    targets.append(one)
+                    two = one + 8 * up
+                    if r == start and self.board[two] is None:
+                        targets.append(two)
+                for df in (-1, 1):
+                    if 0 <= f + df < 8:
+                        to = one + df
+                        if self.board[to] is not None and not self.own(self.board[to], white):
+                            targets.append(to)
+                        elif to == self.ep:
+                            targets.append(to)
+                for to in targets:
+                    if to // 8 == last:
+                        for promo in "QRBN":
+                            moves.append((sq, to, promo))
+                    else:
+                        moves.append((sq, to, None))
+                continue
+            steps, slide = {
+                "N": (KNIGHT, False),
+                "K": (KING, False),
+                "B": (BISHOP, True),
+                "R": (ROOK, True),
+                "Q": (BISHOP + ROOK, True),
+            }[kind]
+            for df, dr in steps:
+                ff, rr = f + df, r + dr
+                while 0 <= ff < 8 and 0 <= rr < 8:
+                    to = rr * 8 + ff
+                    if self.own(self.board[to], white):
+                        break
+                    moves.append((sq, to, None))
+                    if self.board[to] is not None or not slide:
+                        break
+                    ff, rr = ff + df, rr + dr
+        # Castling
+        home = 0 if white else 56
+        if not self.in_check():
+            for right, rook, empty, path in (("K", 7, (5, 6), (5, 6)), ("Q", 0, (1, 2, 3), (3, 2))):
+                right = right if white else right.lower()
+                if right not in self.castling or self.board[home + 4] != ("K" if white else "k"):
+                    continue
+                if self.board[home + rook] != ("R" if white else "r"):
+                    continue
+                if any(self.board[home + e] is not None for e in empty):
+                    continue
+                if any(self.attacked(home + p, not white) for p in path):
+                    continue
+                moves.append((home + 4, home + path[-1], None))
+        return moves
+
+    def play(self, move):
+        frm, to, promo = move
+        p = self.copy()
+        piece = p.board[frm]
+        kind = piece.upper()
+        capture = p.board[to] is not None
+        if kind == "P" and to == self.ep:
+            p.board[to - 8 if self.white else to + 8] = None
+            capture = True
+        p.board[to] = piece
+        p.board[frm] = None
+        if promo:
+            p.board[to] = promo if self.white else promo.lower()
+        if kind == "K" and abs(to - frm) == 2:
+            if to > frm:
+                p.board[frm + 1], p.board[frm + 3] = p.board[frm + 3], None
+            else:
+                p.board[frm - 1], p.board[frm - 4] = p.board[frm - 4], None
+        for sq, right in ((0, "Q"), (7, "K"), (56, "q"), (63, "k"), (4, "KQ"), (60, "kq")):
+            if frm == sq or to == sq:
+                for c in right:
+                    p.castling = p.castling.replace(c, "")
+        p.ep = (frm + to) // 2 if kind == "P" and abs(to - frm) == 16 else None
+        p.half = 0 if kind == "P" or capture else self.half + 1
+        if not self.white:
+            p.full += 1
+        p.white = not self.white
+        return p
+
+    def legal_moves(self):
+        result = []
+        for move in self.pseudo_moves():
+            after = self.play(move)
+            if not after.attacked(after.king(self.white), after.white):
+                result.append(move)
+        return result
+
+    def is_capture(self, move):
+        frm, to, _ = move
+        return self.board[to] is not None or (self.board[frm].upper() == "P" and to == self.ep)
+
+    def san(self, move, legal):
+        frm, to, promo = move
+        kind = self.board[frm].upper()
+        if kind == "K" and abs(to - frm) == 2:
+            text = "O-O" if to > frm else "O-O-O"
+        elif kind == "P":
+            text = (FILES[frm % 8] + "x" if self.is_capture(move) else "") + name(to)
+            if promo:
+                text += "=" + promo
+        else:
+            rivals = [m[0] for m in legal if m[1] == to and m[0] != frm and self.board[m[0]].upper() == kind]
+            text = kind
+            if rivals:
+                if all(r % 8 != frm % 8 for r in rivals):
+                    text += FILES[frm % 8]
+                elif all(r // 8 != frm // 8 for r in rivals):
+                    text += str(frm // 8 + 1)
+                else:
+                    text += name(frm)
+            if self.is_capture(move):
+                text += "x"
+            text += name(to)
+        after = self.play(move)
+        if after.in_check():
+            text += "#" if not after.legal_moves() else "+"
+        return text
+
+    def fen(self):
+        rows = []
+        for r in range(7, -1, -1):
+            row, empty = "", 0
+            for f in range(8):
+                piece = self.board[r * 8 + f]
+                if piece is None:
+                    empty += 1
+                    continue
+                if empty:
+                    row += str(empty)
+                    empty = 0
+                row += piece
+            rows.append(row + (str(empty) if empty else ""))
+        ep = name(self.ep) if self.ep is not None else "-"
+        return "%s %s %s %s %d %d" % ("/".join(rows), "w" if self.white else "b", self.castling or "-", ep, self.half, self.full)
+
+
+START = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+SETUPS = [
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "4k3/PPPP4/8/8/8/8/4pppp/4K3 w - - 0 1",
+    "n1n1k3/1P6/8/8/8/8/6p1/4K1N1 w - - 0 1",
+    "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+    "4k3/8/8/2N1N3/8/2N1N3/8/4K3 w - - 0 1",
+    "7k/8/8/8/Q1Q5/8/Q7/7K w - - 0 1",
+    "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+]
+
+
+def score(pos, move, legal):
+    frm, to, promo = move
+    after = pos.play(move)
+    weight = 1.0
+    if after.in_check():
+        if not after.legal_moves():
+            return 1000.0
+        weight += 4.0
+    if promo:
+        weight += 6.0 if promo == "Q" else 3.0
+    if pos.board[frm].upper() == "K" and abs(to - frm) == 2:
+        weight += 20.0
+    if pos.board[frm].upper() == "P" and to == pos.ep:
+        weight += 20.0
+    if pos.is_capture(move):
+        weight += 2.0
+    kind = pos.board[frm].upper()
+    rivals = [m[0] for m in legal if kind != "P" and m[1] == to and m[0] != frm and pos.board[m[0]].upper() == kind]
+    if rivals:
+        weight += 3.0
+        if any(r % 8 == frm % 8 for r in rivals) and any(r // 8 == frm // 8 for r in rivals):
+            weight += 50.0
+    return weight
+
+
+def play_game(rng, fen, max_plies):
+    pos = Position(fen)
+    sans = []
+    result = "*"
+    for _ in range(max_plies):
+        legal = pos.legal_moves()
+        if not legal:
+            if pos.in_check():
+                result = "0-1" if pos.white else "1-0"
+            else:
+                result = "1/2-1/2"
+            break
+        weights = [score(pos, m, legal) for m in legal]
+        move = rng.choices(legal, weights)[0]
+        sans.append((pos.white, pos.full, pos.san(move, legal)))
+        pos = pos.play(move)
+    return sans, result
+
+
+def movetext(sans, result, first_white):
+    tokens = []
+    for i, (white, number, san) in enumerate(sans):
+        if white:
+            tokens.append("%d." % number)
+        elif i == 0:
+            tokens.append("%d..." % number)
+        tokens.append(san)
+    tokens.append(result)
+    lines, line = [], ""
+    for token in tokens:
+        if line and len(line) + 1 + len(token) > 79:
+            lines.append(line)
+            line = token
+        else:
+            line = token if not line else line + " " + token
+    lines.append(line)
+    return "\n".join(lines)
+
+
+def main():
+    rng = random.Random(20261019)
+    out = []
+    for game in range(300):
+        fen = START if game % 3 else SETUPS[(game // 3) % len(SETUPS)]
+        sans, result = play_game(rng, fen, 120)
+        tags = [("Event", "SAN corpus"), ("Round", str(game + 1)), ("Result", result)]
+        if fen != START:
+            tags += [("SetUp", "1"), ("FEN", fen)]
+        header = "\n".join('[%s "%s"]' % tag for tag in tags)
+        out.append(header + "\n\n" + movetext(sans, result, Position(fen).white) + "\n")
+    print("\n".join(out), end="")
+
+
+if __name__ == "__main__":
+    main()
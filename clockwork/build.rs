@@ -0,0 +1,27 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Copies the default network into OUT_DIR so it can be embedded with
+// `include_bytes!`. The network is taken from `CLOCKWORK_EVALFILE` or
+// `nets/default.nnue`. An explicit `CLOCKWORK_EVALFILE` must be readable; without
+// it a missing default net embeds an empty file and the engine falls back to the
+// handcrafted evaluation.
+fn main() {
+    println!("cargo:rerun-if-env-changed=CLOCKWORK_EVALFILE");
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let explicit = env::var_os("CLOCKWORK_EVALFILE").map(PathBuf::from);
+    let source = explicit
+        .clone()
+        .unwrap_or_else(|| manifest_dir.join("nets").join("default.nnue"));
+    println!("cargo:rerun-if-changed={}", source.display());
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let dest = Path::new(&out_dir).join("default.nnue");
+    let bytes = match (fs::read(&source), explicit) {
+        (Ok(bytes), _) => bytes,
+        (Err(e), Some(path)) => panic!("CLOCKWORK_EVALFILE {}: {}", path.display(), e),
+        (Err(_), None) => Vec::new(),
+    };
+    fs::write(&dest, bytes).unwrap();
+}
@@ -1,33 +1,87 @@
 use chess_core::types::board::board::Board;
 use chess_core::types::moves::Move;
+use chess_core::types::color::Color;
 use std::error::Error;
 use std::io;
 use std::process;
 use std::str::FromStr;
-use std::sync::{Arc, atomic::AtomicBool};
-use std::thread;
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::thread::{self, JoinHandle};
 use std::time::Duration;
-use chess_core::engine::perft::perft;
+use chess_core::engine::perft::{format_divide, perft_divide, PerftOptions};
+use chess_core::engine::eval::Evaluate;
+use chess_core::engine::search::iterative_deepening_search;
+use chess_core::engine::book::{BookSelection, PolyglotBook};
+use chess_core::engine::syzygy::{SyzygyTablebase, Tablebase};
+use chess_core::types::board::lookup::{set_slider_backend, slider_backend, SliderBackend};
+use crate::engine::evaluation::evaluator::Evaluator;
+use crate::engine::evaluation::nnue::network::Network;
+use std::path::Path;
 
+/// Value of the `EvalFile` option that selects the network built into the binary.
+const EMBEDDED_EVAL_FILE: &str = "<embedded>";
+/// Value of the `EvalFile` option that disables the network.
+const NO_EVAL_FILE: &str = "<none>";
+/// Value of the `BookFile` option without a book.
+const NO_BOOK_FILE: &str = "<empty>";
+/// Value of the `SyzygyPath` option without tablebases.
+const NO_SYZYGY_PATH: &str = "<empty>";
+/// Search time for `go infinite` and a bare `go`, until `stop` arrives.
+const NO_TIME_LIMIT: Duration = Duration::from_secs(60 * 60 * 24 * 365);
+/// Moves the remaining clock time is shared between without `movestogo`.
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+/// Kept back from every search for the GUI and engine overhead.
+const MOVE_OVERHEAD_MS: u64 = 50;
 
 
+/// State of the `Hash` and `Threads` options.
+#[derive(Debug, Clone, Copy)]
+struct EngineOptions {
+    /// Transposition table of the search, and the table of `go perft`.
+    hash_mb: usize,
+    /// Only `go perft` runs on several threads, the search uses one.
+    threads: usize,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        EngineOptions { hash_mb: 16, threads: 1 }
+    }
+}
 
-pub struct UciInfo {
-    pub depth: u8,
-    pub seldepth: u8,
-    pub score_cp: Option<i32>,
-    pub score_mate: Option<i32>,
-    pub nodes: u64,
-    pub nps: u64,
-    pub time: Duration,
-    pub pv: Vec<Move>,
+const MAX_HASH_MB: usize = 65536;
+const MAX_THREADS: usize = 512;
+
+/// State of the `OwnBook`, `BookFile` and `BookBestMove` options.
+#[derive(Debug, Default)]
+struct BookOptions {
+    own_book: bool,
+    best_move: bool,
+    book: Option<PolyglotBook>,
+}
+
+impl BookOptions {
+    /// The book move for `board` if the book is enabled and knows the position.
+    fn book_move(&self, board: &Board) -> Option<Move> {
+        let book = self.book.as_ref().filter(|_| self.own_book)?;
+        let selection = if self.best_move { BookSelection::Best } else { BookSelection::Weighted };
+        book.pick(board, selection, &mut rand::rng())
+    }
 }
 
 
+
+
 pub fn uci_listener() {
     eprintln!("UCI starting up...");
     
     let mut board = Board::start();
+    let mut evaluator = default_evaluator();
+    let mut book = BookOptions::default();
+    let mut options = EngineOptions::default();
+    let mut tablebase: Option<Arc<dyn Tablebase>> = None;
+    let mut stop_signal = Arc::new(AtomicBool::new(false));
+    let mut search: Option<JoinHandle<()>> = None;
     
     loop {
         let mut input = String::new();
@@ -42,6 +96,11 @@ pub fn uci_listener() {
         if let Some(&command) = commands.get(0) {
             match command {
                 "uci" => uci_handshake(),
+                "setoption" => {
+                    if let Err(e) = handle_setoption_command(&mut evaluator, &mut book, &mut options, &mut tablebase, &commands) {
+                        eprintln!("Error setting option: {}", e);
+                    }
+                },
                 "isready" => is_ready(),
                 "ucinewgame" => board = Board::start(), 
                 "position" => {
@@ -50,13 +109,22 @@ pub fn uci_listener() {
                     }
                 },
                 "go" => {
-                    handle_go_command(&board, &commands);
+                    // A search still running answers its own go first, so every go gets one bestmove
+                    stop_search(&stop_signal, &mut search);
+                    stop_signal = Arc::new(AtomicBool::new(false));
+                    search = handle_go_command(&board, &evaluator, &book, &options, &tablebase, stop_signal.clone(), &commands);
+                },
+                "eval" => {
+                    if let Err(e) = handle_eval_command(&board, &evaluator, &commands) {
+                        eprintln!("Error evaluating position: {}", e);
+                    }
                 },
                 "stop" => {
-                    // This command is handled implicitly by the search stopping
-                    // when it sees "stop" or a new "go" command. We'll use atomics for this.
+                    // The search checks the signal at every node and prints its bestmove
+                    stop_signal.store(true, Ordering::Relaxed);
                 },
                 "quit" => {
+                    stop_search(&stop_signal, &mut search);
                     eprintln!("Quitting.");
                     process::exit(0);
                 }
@@ -68,9 +136,27 @@ pub fn uci_listener() {
     }
 }
 
+/// Stops the running search and waits until it printed its bestmove.
+fn stop_search(stop_signal: &AtomicBool, search: &mut Option<JoinHandle<()>>) {
+    stop_signal.store(true, Ordering::Relaxed);
+    if let Some(handle) = search.take() {
+        let _ = handle.join();
+    }
+}
+
 fn uci_handshake() {
     println!("id name Blaze");
     println!("id author Dominik Schiwietz");
+    let defaults = EngineOptions::default();
+    println!("option name Hash type spin default {} min 1 max {}", defaults.hash_mb, MAX_HASH_MB);
+    println!("option name Threads type spin default {} min 1 max {}", defaults.threads, MAX_THREADS);
+    let default_eval_file = if Network::embedded().is_some() { EMBEDDED_EVAL_FILE } else { NO_EVAL_FILE };
+    println!("option name EvalFile type string default {}", default_eval_file);
+    println!("option name OwnBook type check default false");
+    println!("option name BookFile type string default {}", NO_BOOK_FILE);
+    println!("option name BookBestMove type check default false");
+    println!("option name SyzygyPath type string default {}", NO_SYZYGY_PATH);
+    println!("option name UsePext type check default {}", slider_backend() == SliderBackend::Pext);
     
     // Signal that the initial handshake is done
     println!("uciok");
@@ -81,6 +167,84 @@ fn is_ready() {
 }
 
 
+/// Evaluator with the embedded network, or the handcrafted evaluation if there is none.
+fn default_evaluator() -> Evaluator {
+    match Network::embedded() {
+        Some(Ok(network)) => Evaluator::with_network(Arc::new(network)),
+        Some(Err(e)) => {
+            eprintln!("Embedded network is unusable, using handcrafted evaluation: {}", e);
+            Evaluator::new()
+        }
+        None => Evaluator::new(),
+    }
+}
+
+
+/// `setoption name <name> [value <value>]`, the value may contain spaces.
+fn handle_setoption_command(evaluator: &mut Evaluator, book: &mut BookOptions, options: &mut EngineOptions, tablebase: &mut Option<Arc<dyn Tablebase>>, commands: &[&str]) -> Result<(), Box<dyn Error>> {
+    let value_index = commands.iter().position(|&c| c == "value");
+    let name = commands[2.min(commands.len())..value_index.unwrap_or(commands.len())].join(" ");
+    let value = value_index.map(|i| commands[i + 1..].join(" ")).unwrap_or_default();
+
+    match name.to_lowercase().as_str() {
+        "hash" => {
+            options.hash_mb = value.parse::<usize>()?.clamp(1, MAX_HASH_MB);
+            Ok(())
+        }
+        "threads" => {
+            options.threads = value.parse::<usize>()?.clamp(1, MAX_THREADS);
+            Ok(())
+        }
+        "evalfile" => {
+            match value.as_str() {
+                EMBEDDED_EVAL_FILE => *evaluator = default_evaluator(),
+                NO_EVAL_FILE | "" => evaluator.set_network(None),
+                path => evaluator.set_network(Some(Arc::new(Network::load(Path::new(path))?))),
+            }
+            Ok(())
+        }
+        "ownbook" => {
+            book.own_book = value.parse()?;
+            Ok(())
+        }
+        "bookbestmove" => {
+            book.best_move = value.parse()?;
+            Ok(())
+        }
+        "bookfile" => {
+            book.book = match value.as_str() {
+                NO_BOOK_FILE | "" => None,
+                path => Some(PolyglotBook::load(Path::new(path))?),
+            };
+            Ok(())
+        }
+        "syzygypath" => {
+            match value.as_str() {
+                NO_SYZYGY_PATH | "" => *tablebase = None,
+                paths => {
+                    let (opened, errors) = SyzygyTablebase::open(paths);
+                    for e in errors.iter() {
+                        eprintln!("Skipping tablebase file: {}", e);
+                    }
+                    let (wdl, dtz) = opened.num_tables();
+                    println!("info string Found {} WDL and {} DTZ tablebase files, up to {} pieces", wdl, dtz, opened.max_pieces());
+                    *tablebase = Some(Arc::new(opened));
+                }
+            }
+            Ok(())
+        }
+        "usepext" => {
+            let backend = if value.parse()? { SliderBackend::Pext } else { SliderBackend::Magic };
+            if !set_slider_backend(backend) {
+                return Err("PEXT needs a CPU with BMI2".into());
+            }
+            Ok(())
+        }
+        _ => Err(format!("unknown option '{}'", name).into()),
+    }
+}
+
+
 fn handle_position_command(board: &mut Board, commands: &[&str]) -> Result<(), Box<dyn Error>> {
     let mut moves_start_index = None;
 
@@ -109,24 +273,52 @@ fn handle_position_command(board: &mut Board, commands: &[&str]) -> Result<(), B
 }
 
 
-// This function will spawn the search thread
-fn handle_go_command(board: &Board, commands: &[&str]) {
+/// `eval` prints the static evaluation of the current position, `eval trace [<fen>]`
+/// additionally prints every term for both sides, optionally for a given FEN.
+fn handle_eval_command(board: &Board, evaluator: &Evaluator, commands: &[&str]) -> Result<(), Box<dyn Error>> {
+    let trace = commands.get(1) == Some(&"trace");
+    let fen = commands.iter().skip(2).cloned().collect::<Vec<&str>>().join(" ");
+
+    let board = if trace && !fen.is_empty() {
+        Board::from_str(&fen).map_err(|e| format!("invalid FEN '{}': {:?}", fen, e))?
+    } else {
+        board.clone()
+    };
+
+    if trace {
+        println!("{}", evaluator.trace(&board));
+    } else {
+        println!("Evaluation: {} (side to move)", evaluator.evaluate(&board));
+    }
+    Ok(())
+}
+
+
+// This function will spawn the search thread, returned unless the answer is immediate
+fn handle_go_command(board: &Board, evaluator: &Evaluator, book: &BookOptions, options: &EngineOptions, tablebase: &Option<Arc<dyn Tablebase>>, stop_signal: Arc<AtomicBool>, commands: &[&str]) -> Option<JoinHandle<()>> {
     let mut search_board = board.clone();
 
-    let stop_signal = Arc::new(AtomicBool::new(false));
-    let stop_clone = stop_signal.clone();
-
-    if commands.len() == 3 && commands[1] == "perft"{
-        // Clone board and parse depth before spawning the thread to avoid borrowing issues
-        let mut search_board = board.clone();
-        let depth = commands[2].parse::<u8>().unwrap();
-        thread::spawn(move || {
-            let total_nodes = perft(&mut search_board, depth);
-            println!("{}", total_nodes);
-        });
+    if commands.get(1) == Some(&"perft") {
+        let Some(depth) = commands.get(2).and_then(|depth| depth.parse::<u8>().ok()) else {
+            println!("info string go perft needs a depth from 0 to 255");
+            return None;
+        };
+        // One table of `Hash` MB shared by `Threads` threads
+        let perft_options = PerftOptions { threads: options.threads, hash_mb: options.hash_mb };
+        Some(thread::spawn(move || {
+            let divide = perft_divide(&search_board, depth, &perft_options);
+            println!("{}", format_divide(&divide));
+        }))
+    }else if let Some(mv) = book.book_move(board) {
+        println!("bestmove {}", mv.to_lan());
+        None
     }else{
-        thread::spawn(move || {
-            let best_move = search_in_thread(&mut search_board, stop_clone);
+        let mut evaluator = evaluator.clone();
+        let search_time = search_time(board, commands);
+        let tablebase = tablebase.clone();
+        let hash_mb = options.hash_mb;
+        Some(thread::spawn(move || {
+            let best_move = iterative_deepening_search(&mut search_board, &mut evaluator, tablebase.as_deref(), hash_mb, search_time, stop_signal);
             match best_move {
                 Some(mv) => {
                     println!("bestmove {}", mv.to_lan());
@@ -135,45 +327,60 @@ fn handle_go_command(board: &Board, commands: &[&str]) {
                     println!("bestmove n/a");
                 }
             }
-        });
+        }))
     }
 
 }
 
 
-fn search_in_thread(board: &mut Board, stop_signal: Arc<AtomicBool>) -> Option<Move>{
-   let ret = None;
-   ret
+
+/// Value following `name` in the `go` command.
+fn go_parameter(commands: &[&str], name: &str) -> Option<u64> {
+    let index = commands.iter().position(|&c| c == name)?;
+    commands.get(index + 1)?.parse().ok()
 }
 
-pub fn post_uci_info(info: UciInfo) {
-    // Start with the base "info" command
-    let mut uci_string = String::from("info");
+/// Time for one search: `movetime`, or a share of the side to move's clock
+/// plus half its increment. Without either the search runs until `stop`.
+fn search_time(board: &Board, commands: &[&str]) -> Duration {
+    if let Some(movetime) = go_parameter(commands, "movetime") {
+        return Duration::from_millis(movetime.saturating_sub(MOVE_OVERHEAD_MS).max(1));
+    }
+    if commands.contains(&"infinite") {
+        return NO_TIME_LIMIT;
+    }
+
+    let (time, increment) = if board.state.color == Color::White { ("wtime", "winc") } else { ("btime", "binc") };
+    let Some(remaining) = go_parameter(commands, time) else {
+        return NO_TIME_LIMIT;
+    };
+    let increment = go_parameter(commands, increment).unwrap_or(0);
+    let moves_to_go = go_parameter(commands, "movestogo").unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
 
-    // Add depth and selective depth
-    uci_string.push_str(&format!(" depth {}", info.depth));
-    uci_string.push_str(&format!(" seldepth {}", info.seldepth));
+    // Never plan to use more than the clock holds
+    let budget = remaining / moves_to_go + increment / 2;
+    let available = remaining.saturating_sub(MOVE_OVERHEAD_MS);
+    Duration::from_millis(budget.min(available).max(1))
+}
 
-    // Add score. It can be either centipawns (cp) or mate in X.
-    if let Some(mate_in) = info.score_mate {
-        uci_string.push_str(&format!(" score mate {}", mate_in));
-    } else if let Some(centipawns) = info.score_cp {
-        uci_string.push_str(&format!(" score cp {}", centipawns));
-    }
 
-    // Add node count, nodes per second, and time
-    uci_string.push_str(&format!(" nodes {}", info.nodes));
-    uci_string.push_str(&format!(" nps {}", info.nps));
-    uci_string.push_str(&format!(" time {}", info.time.as_millis()));
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Add the Principal Variation (PV)
-    if !info.pv.is_empty() {
-        uci_string.push_str(" pv");
-        for mv in info.pv {
-            uci_string.push_str(&format!(" {}", mv.to_lan()));
-        }
-    }
+    #[test]
+    fn test_search_time() {
+        let white = Board::start();
+        let mut black = Board::start();
+        black.apply_move(&Move::from_lan(&black, "e2e4"));
+        let time = |board: &Board, go: &str| search_time(board, &go.split_whitespace().collect::<Vec<_>>());
 
-    // Print the final string to standard output, which the GUI will read.
-    println!("{}", uci_string);
-}
\ No newline at end of file
+        assert_eq!(time(&white, "go movetime 1000"), Duration::from_millis(950));
+        assert_eq!(time(&white, "go infinite"), NO_TIME_LIMIT);
+        assert_eq!(time(&white, "go"), NO_TIME_LIMIT);
+        assert_eq!(time(&white, "go wtime 30000 btime 60000 winc 1000"), Duration::from_millis(1500));
+        assert_eq!(time(&black, "go wtime 30000 btime 60000 movestogo 10"), Duration::from_millis(6000));
+        // A nearly empty clock still leaves the overhead
+        assert_eq!(time(&white, "go wtime 100 btime 100 winc 2000 movestogo 1"), Duration::from_millis(50));
+    }
+}
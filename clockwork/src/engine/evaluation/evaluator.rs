@@ -1,25 +1,70 @@
-use super::tables::{PieceSquareTables, PSTS};
+use std::sync::Arc;
+use chess_core::engine::eval::{Evaluate, EvalTrace, HandcraftedEval};
 use chess_core::types::board::board::Board;
-use super::functions::{material_eval, pices_square_eval};
+use chess_core::types::moves::Move;
+use super::nnue::network::Network;
+use super::nnue::nnue::Nnue;
 
+/// Evaluator used by the engine. Falls back to the handcrafted evaluation
+/// when no network is loaded.
+#[derive(Debug, Clone, Default)]
 pub struct Evaluator{
-    psts: PieceSquareTables,
+    handcrafted: HandcraftedEval,
+    nnue: Option<Nnue>,
 }
 
 impl Evaluator{
     pub fn new() ->  Self{
         Self {
-            psts: PSTS
+            handcrafted: HandcraftedEval,
+            nnue: None,
         }
     }
 
-    pub fn evaluate(&self, board: &Board) -> i32{
-        let mut score = 0;
-        let us = board.us();
-        
-        score += material_eval(board); // Evaluate raw material difference
-        score += pices_square_eval(board, &self.psts); // Evaluate board with piece square tables
-        
-        score
+    pub fn with_network(network: Arc<Network>) -> Self{
+        Self {
+            handcrafted: HandcraftedEval,
+            nnue: Some(Nnue::new(network)),
+        }
+    }
+
+    pub fn set_network(&mut self, network: Option<Arc<Network>>){
+        self.nnue = network.map(Nnue::new);
+    }
+
+    pub fn uses_nnue(&self) -> bool{
+        self.nnue.is_some()
+    }
+
+    /// Per-term breakdown of the evaluation, as printed by `eval trace`.
+    pub fn trace(&self, board: &Board) -> EvalTrace{
+        self.handcrafted.trace(board)
+    }
+}
+
+impl Evaluate for Evaluator{
+    fn evaluate(&self, board: &Board) -> i32{
+        match &self.nnue {
+            Some(nnue) => nnue.evaluate(board),
+            None => self.handcrafted.evaluate(board),
+        }
+    }
+
+    fn refresh(&mut self, board: &Board){
+        if let Some(nnue) = &mut self.nnue {
+            nnue.refresh(board);
+        }
     }
-}
\ No newline at end of file
+
+    fn make_move(&mut self, board: &Board, mv: &Move){
+        if let Some(nnue) = &mut self.nnue {
+            nnue.make_move(board, mv);
+        }
+    }
+
+    fn undo_move(&mut self){
+        if let Some(nnue) = &mut self.nnue {
+            nnue.undo_move();
+        }
+    }
+}
@@ -1,3 +1,2 @@
-pub mod functions;
 pub mod evaluator;
-pub mod tables;
\ No newline at end of file
+pub mod nnue;
\ No newline at end of file
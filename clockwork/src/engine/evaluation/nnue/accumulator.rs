@@ -0,0 +1,51 @@
+use chess_core::types::board::board::Board;
+use chess_core::types::color::Color;
+use chess_core::types::piece::Piece;
+use super::network::{feature_index, Network, HIDDEN_SIZE};
+use super::simd;
+
+/// Hidden layer pre-activations for both perspectives, indexed by `Color as usize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C, align(64))]
+pub struct Accumulator {
+    pub values: [[i16; HIDDEN_SIZE]; Color::COUNT],
+}
+
+impl Accumulator {
+    /// Computes the accumulator from scratch for every piece on `board`.
+    pub fn refresh(network: &Network, board: &Board) -> Self {
+        let mut accumulator = Accumulator { values: [[0; HIDDEN_SIZE]; Color::COUNT] };
+        for perspective in [Color::White, Color::Black] {
+            let mut features = Vec::with_capacity(32);
+            for sq in board.occupied() {
+                let piece = board.mailbox[sq as usize];
+                features.push(network.feature(feature_index(perspective, piece, sq.to_index())));
+            }
+            simd::update(&mut accumulator.values[perspective as usize], network.feature_bias(), &features, &[]);
+        }
+        accumulator
+    }
+
+    /// Derives the accumulator of a child position from its parent by adding
+    /// and removing the `(piece, square)` pairs that changed.
+    pub fn update(network: &Network, parent: &Accumulator, added: &[(Piece, u8)], removed: &[(Piece, u8)]) -> Self {
+        let mut accumulator = Accumulator { values: [[0; HIDDEN_SIZE]; Color::COUNT] };
+        for perspective in [Color::White, Color::Black] {
+            let mut adds: [&[i16]; 2] = [&[]; 2];
+            let mut subs: [&[i16]; 3] = [&[]; 3];
+            for (slot, &(piece, sq)) in adds.iter_mut().zip(added) {
+                *slot = network.feature(feature_index(perspective, piece, sq));
+            }
+            for (slot, &(piece, sq)) in subs.iter_mut().zip(removed) {
+                *slot = network.feature(feature_index(perspective, piece, sq));
+            }
+            simd::update(
+                &mut accumulator.values[perspective as usize],
+                &parent.values[perspective as usize],
+                &adds[..added.len()],
+                &subs[..removed.len()],
+            );
+        }
+        accumulator
+    }
+}
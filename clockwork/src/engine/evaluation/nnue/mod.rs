@@ -0,0 +1,4 @@
+pub mod nnue;
+pub mod network;
+pub mod accumulator;
+pub mod simd;
\ No newline at end of file
@@ -0,0 +1,255 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use chess_core::types::color::Color;
+use chess_core::types::piece::Piece;
+use super::accumulator::Accumulator;
+use super::simd;
+
+/// One input per (piece color, piece type, square) seen from each side.
+pub const INPUT_SIZE: usize = 768;
+/// Neurons per perspective in the hidden layer.
+pub const HIDDEN_SIZE: usize = 256;
+
+/// Quantization of the feature transformer.
+pub const QA: i32 = 255;
+/// Quantization of the output layer.
+pub const QB: i32 = 64;
+/// Converts the network output to centipawns.
+pub const SCALE: i32 = 400;
+
+/// First bytes of every weights file.
+pub const MAGIC: [u8; 4] = *b"CWNN";
+/// Current version of the weights file format.
+pub const VERSION: u32 = 1;
+/// Size of the file header in bytes.
+pub const HEADER_BYTES: usize = 20;
+
+/// Network embedded at build time, empty if none was available. See `build.rs`.
+pub static EMBEDDED_NETWORK: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/default.nnue"));
+
+const ACTIVATION: &str = "screlu";
+
+/// Fingerprint of the layer sizes and activation, so a file trained for a
+/// different topology is rejected instead of silently misread.
+pub const ARCHITECTURE_HASH: u32 = architecture_hash(INPUT_SIZE, HIDDEN_SIZE, ACTIVATION);
+
+/// FNV-1a over the layer sizes followed by the activation name.
+pub const fn architecture_hash(input_size: usize, hidden_size: usize, activation: &str) -> u32 {
+    let mut hash: u32 = 0x811c9dc5;
+    let sizes = [input_size as u32, hidden_size as u32];
+    let mut i = 0;
+    while i < sizes.len() {
+        let bytes = sizes[i].to_le_bytes();
+        let mut j = 0;
+        while j < bytes.len() {
+            hash = (hash ^ bytes[j] as u32).wrapping_mul(0x01000193);
+            j += 1;
+        }
+        i += 1;
+    }
+    let bytes = activation.as_bytes();
+    let mut j = 0;
+    while j < bytes.len() {
+        hash = (hash ^ bytes[j] as u32).wrapping_mul(0x01000193);
+        j += 1;
+    }
+    hash
+}
+
+#[derive(Debug)]
+pub enum NetworkError {
+    Io(io::Error),
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    ArchitectureMismatch { expected: u32, found: u32 },
+    QuantizationMismatch { qa: u16, qb: u16, scale: u16 },
+    InvalidLength { expected: usize, found: usize },
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::Io(e) => write!(f, "could not read network: {}", e),
+            NetworkError::InvalidMagic => write!(f, "not a clockwork network file"),
+            NetworkError::UnsupportedVersion(v) => write!(f, "unsupported network version {} (expected {})", v, VERSION),
+            NetworkError::ArchitectureMismatch { expected, found } => {
+                write!(f, "network architecture {:08x} does not match {:08x}", found, expected)
+            }
+            NetworkError::QuantizationMismatch { qa, qb, scale } => write!(
+                f,
+                "network quantization QA={} QB={} SCALE={} does not match QA={} QB={} SCALE={}",
+                qa, qb, scale, QA, QB, SCALE
+            ),
+            NetworkError::InvalidLength { expected, found } => {
+                write!(f, "expected {} bytes of network data, got {}", expected, found)
+            }
+        }
+    }
+}
+
+impl Error for NetworkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            NetworkError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for NetworkError {
+    fn from(e: io::Error) -> Self {
+        NetworkError::Io(e)
+    }
+}
+
+/// A 768 -> 256x2 -> 1 perspective network with SCReLU activation.
+///
+/// A weights file starts with a `HEADER_BYTES` long header, all fields little-endian:
+///
+/// | Offset | Type      | Field                                 |
+/// |--------|-----------|---------------------------------------|
+/// | 0      | `[u8; 4]` | `MAGIC`                               |
+/// | 4      | `u32`     | format `VERSION`                      |
+/// | 8      | `u32`     | `ARCHITECTURE_HASH`                   |
+/// | 12     | `u16`     | `QA`                                  |
+/// | 14     | `u16`     | `QB`                                  |
+/// | 16     | `u16`     | `SCALE`                               |
+/// | 18     | `u16`     | reserved, zero                        |
+///
+/// The header is followed by the weights as `i16` values in this order:
+/// feature weights (input major), feature biases, output weights (side to
+/// move half first) and the output bias.
+#[derive(Debug, Clone)]
+pub struct Network {
+    feature_weights: Vec<i16>,
+    feature_bias: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i16,
+    // Output weights fit the fast 16 bit multiplication of the SIMD kernel
+    fast_output: bool,
+}
+
+impl Network {
+    /// Size of a weights file in bytes, including the header.
+    pub const BYTES: usize = HEADER_BYTES + (INPUT_SIZE * HIDDEN_SIZE + HIDDEN_SIZE + 2 * HIDDEN_SIZE + 1) * 2;
+
+    pub fn load(path: &Path) -> Result<Self, NetworkError> {
+        let bytes = fs::read(path)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// The network embedded into the binary, if there is one.
+    pub fn embedded() -> Option<Result<Self, NetworkError>> {
+        if EMBEDDED_NETWORK.is_empty() {
+            None
+        } else {
+            Some(Self::from_bytes(EMBEDDED_NETWORK))
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, NetworkError> {
+        if bytes.len() < HEADER_BYTES {
+            return Err(NetworkError::InvalidLength { expected: Self::BYTES, found: bytes.len() });
+        }
+        let (header, weights) = bytes.split_at(HEADER_BYTES);
+        let u32_at = |i: usize| u32::from_le_bytes([header[i], header[i + 1], header[i + 2], header[i + 3]]);
+        let u16_at = |i: usize| u16::from_le_bytes([header[i], header[i + 1]]);
+
+        if header[0..4] != MAGIC {
+            return Err(NetworkError::InvalidMagic);
+        }
+        let version = u32_at(4);
+        if version != VERSION {
+            return Err(NetworkError::UnsupportedVersion(version));
+        }
+        let architecture = u32_at(8);
+        if architecture != ARCHITECTURE_HASH {
+            return Err(NetworkError::ArchitectureMismatch { expected: ARCHITECTURE_HASH, found: architecture });
+        }
+        let (qa, qb, scale) = (u16_at(12), u16_at(14), u16_at(16));
+        if (qa as i32, qb as i32, scale as i32) != (QA, QB, SCALE) {
+            return Err(NetworkError::QuantizationMismatch { qa, qb, scale });
+        }
+        if bytes.len() != Self::BYTES {
+            return Err(NetworkError::InvalidLength { expected: Self::BYTES, found: bytes.len() });
+        }
+
+        let mut values = weights.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]]));
+
+        let feature_weights: Vec<i16> = values.by_ref().take(INPUT_SIZE * HIDDEN_SIZE).collect();
+        let feature_bias: Vec<i16> = values.by_ref().take(HIDDEN_SIZE).collect();
+        let output_weights: Vec<i16> = values.by_ref().take(2 * HIDDEN_SIZE).collect();
+        let output_bias = values.next().unwrap_or_default();
+
+        Ok(Self::from_parts(feature_weights, feature_bias, output_weights, output_bias))
+    }
+
+    /// Builds a network from already quantized weights, laid out as in the weights file.
+    pub fn from_parts(feature_weights: Vec<i16>, feature_bias: Vec<i16>, output_weights: Vec<i16>, output_bias: i16) -> Self {
+        assert_eq!(feature_weights.len(), INPUT_SIZE * HIDDEN_SIZE);
+        assert_eq!(feature_bias.len(), HIDDEN_SIZE);
+        assert_eq!(output_weights.len(), 2 * HIDDEN_SIZE);
+        let fast_output = output_weights.iter().all(|w| (-128..=127).contains(w));
+        Network {
+            feature_weights,
+            feature_bias,
+            output_weights,
+            output_bias,
+            fast_output,
+        }
+    }
+
+    /// Serializes the header and weights in the layout read by `from_bytes`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTES);
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&ARCHITECTURE_HASH.to_le_bytes());
+        for value in [QA as u16, QB as u16, SCALE as u16, 0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in self.feature_weights.iter().chain(&self.feature_bias).chain(&self.output_weights) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.output_bias.to_le_bytes());
+        bytes
+    }
+
+    pub fn feature_bias(&self) -> &[i16] {
+        &self.feature_bias
+    }
+
+    /// Weights the hidden layer receives from a single input feature.
+    pub fn feature(&self, index: usize) -> &[i16] {
+        &self.feature_weights[index * HIDDEN_SIZE..(index + 1) * HIDDEN_SIZE]
+    }
+
+    /// Output in centipawns relative to `side_to_move`.
+    pub fn evaluate(&self, accumulator: &Accumulator, side_to_move: Color) -> i32 {
+        let (us, them) = match side_to_move {
+            Color::Black => (&accumulator.values[1], &accumulator.values[0]),
+            _ => (&accumulator.values[0], &accumulator.values[1]),
+        };
+        let mut output = simd::screlu_dot(us, &self.output_weights[..HIDDEN_SIZE], self.fast_output)
+            + simd::screlu_dot(them, &self.output_weights[HIDDEN_SIZE..], self.fast_output);
+        output /= QA;
+        output += self.output_bias as i32;
+        output * SCALE / (QA * QB)
+    }
+}
+
+/// Input index of `piece` on `square` as seen by `perspective`.
+///
+/// Each side sees its own pieces in the first half of the inputs and the board
+/// from its own side, so black's view is flipped vertically.
+pub fn feature_index(perspective: Color, piece: Piece, square: u8) -> usize {
+    let piece_color = piece as usize / 6;
+    let piece_type = piece as usize % 6;
+    match perspective {
+        Color::Black => (1 - piece_color) * 384 + piece_type * 64 + (square ^ 56) as usize,
+        _ => piece_color * 384 + piece_type * 64 + square as usize,
+    }
+}
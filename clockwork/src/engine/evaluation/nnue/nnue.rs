@@ -0,0 +1,225 @@
+use std::sync::Arc;
+use chess_core::engine::eval::Evaluate;
+use chess_core::types::board::board::Board;
+use chess_core::types::color::Color;
+use chess_core::types::moves::{Move, MoveType};
+use chess_core::types::piece::Piece;
+use super::accumulator::Accumulator;
+use super::network::Network;
+
+const MAX_PLY: usize = 256;
+
+/// Evaluates positions with a `Network`, keeping one accumulator per ply that
+/// is updated incrementally as the search makes and unmakes moves.
+#[derive(Debug, Clone)]
+pub struct Nnue {
+    network: Arc<Network>,
+    stack: Vec<Accumulator>,
+}
+
+impl Nnue {
+    pub fn new(network: Arc<Network>) -> Self {
+        Nnue {
+            network,
+            stack: Vec::with_capacity(MAX_PLY),
+        }
+    }
+
+    pub fn network(&self) -> &Arc<Network> {
+        &self.network
+    }
+
+    /// Number of accumulators currently on the stack.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+}
+
+impl Evaluate for Nnue {
+    fn evaluate(&self, board: &Board) -> i32 {
+        match self.stack.last() {
+            Some(accumulator) => self.network.evaluate(accumulator, board.state.color),
+            None => self.network.evaluate(&Accumulator::refresh(&self.network, board), board.state.color),
+        }
+    }
+
+    fn refresh(&mut self, board: &Board) {
+        self.stack.clear();
+        self.stack.push(Accumulator::refresh(&self.network, board));
+    }
+
+    fn make_move(&mut self, board: &Board, mv: &Move) {
+        let parent = match self.stack.last() {
+            Some(parent) => parent,
+            None => {
+                self.stack.push(Accumulator::refresh(&self.network, board));
+                return;
+            }
+        };
+
+        let from = mv.from();
+        let to = mv.to();
+        let mover = board.state.color.invert();
+        let placed = board.mailbox[to as usize];
+        let moved = if mv.is_promotion() {
+            if mover == Color::White { Piece::WhitePawn } else { Piece::BlackPawn }
+        } else {
+            placed
+        };
+
+        let mut added = [(Piece::None, 0u8); 2];
+        let mut removed = [(Piece::None, 0u8); 2];
+        added[0] = (placed, to);
+        removed[0] = (moved, from);
+        let mut num_added = 1;
+        let mut num_removed = 1;
+
+        let captured = board.state.captured;
+        if captured != Piece::None {
+            let captured_sq = match mv.kind() {
+                MoveType::EnPassant => if mover == Color::White { to - 8 } else { to + 8 },
+                _ => to,
+            };
+            removed[num_removed] = (captured, captured_sq);
+            num_removed += 1;
+        }
+
+        let rook_move = match (mv.kind(), mover) {
+            (MoveType::KingCastle, Color::White) => Some((Piece::WhiteRook, 7, 5)),
+            (MoveType::KingCastle, Color::Black) => Some((Piece::BlackRook, 63, 61)),
+            (MoveType::QueenCastle, Color::White) => Some((Piece::WhiteRook, 0, 3)),
+            (MoveType::QueenCastle, Color::Black) => Some((Piece::BlackRook, 56, 59)),
+            _ => None,
+        };
+        if let Some((rook, rook_from, rook_to)) = rook_move {
+            added[num_added] = (rook, rook_to);
+            removed[num_removed] = (rook, rook_from);
+            num_added += 1;
+            num_removed += 1;
+        }
+
+        let child = Accumulator::update(&self.network, parent, &added[..num_added], &removed[..num_removed]);
+        self.stack.push(child);
+    }
+
+    fn undo_move(&mut self) {
+        self.stack.pop();
+    }
+}
+
+
+
+
+/// ######################################################
+/// ################### TESTING ##########################
+/// ######################################################
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::network::{architecture_hash, NetworkError, ARCHITECTURE_HASH, HIDDEN_SIZE, INPUT_SIZE};
+    use super::super::simd;
+    use std::str::FromStr;
+
+    /// Deterministic pseudo random weights in `-range..range`.
+    fn random_network(seed: u64, range: i16) -> Network {
+        let mut state = seed;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % (2 * range as u64)) as i16 - range
+        };
+        let feature_weights = (0..INPUT_SIZE * HIDDEN_SIZE).map(|_| next()).collect();
+        let feature_bias = (0..HIDDEN_SIZE).map(|_| next()).collect();
+        let output_weights = (0..2 * HIDDEN_SIZE).map(|_| next()).collect();
+        Network::from_parts(feature_weights, feature_bias, output_weights, next())
+    }
+
+    fn walk(nnue: &mut Nnue, board: &mut Board, depth: u8) {
+        let fresh = Accumulator::refresh(nnue.network(), board);
+        assert_eq!(nnue.stack.last(), Some(&fresh), "Accumulator mismatch in {}", board.to_fen());
+        if depth == 0 {
+            return;
+        }
+        let moves = board.generate_all_moves();
+        for mv in moves.iter() {
+            board.apply_move(mv);
+            nnue.make_move(board, mv);
+            walk(nnue, board, depth - 1);
+            nnue.undo_move();
+            board.undo_move(mv);
+        }
+    }
+
+    #[test]
+    fn test_incremental_updates_match_refresh() {
+        let network = Arc::new(random_network(0x9E3779B97F4A7C15, 64));
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ] {
+            let mut board = Board::from_str(fen).unwrap();
+            let mut nnue = Nnue::new(network.clone());
+            nnue.refresh(&board);
+            walk(&mut nnue, &mut board, 2);
+            assert_eq!(nnue.depth(), 1);
+        }
+    }
+
+    #[test]
+    fn test_evaluation_is_side_relative() {
+        let nnue = Nnue::new(Arc::new(random_network(7, 32)));
+        let white = Board::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let black = Board::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        // The start position is symmetric, so both sides see the same thing
+        assert_eq!(nnue.evaluate(&white), nnue.evaluate(&black));
+    }
+
+    #[test]
+    fn test_simd_matches_scalar() {
+        let network = random_network(42, 127);
+        let board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let accumulator = Accumulator::refresh(&network, &board);
+        let weights = network.feature(5);
+        for values in accumulator.values.iter() {
+            let mut scalar_out = [0i16; HIDDEN_SIZE];
+            let mut dispatched_out = [0i16; HIDDEN_SIZE];
+            simd::scalar::update(&mut scalar_out, values, &[weights], &[network.feature(9)]);
+            simd::update(&mut dispatched_out, values, &[weights], &[network.feature(9)]);
+            assert_eq!(scalar_out, dispatched_out);
+            assert_eq!(simd::scalar::screlu_dot(values, weights), simd::screlu_dot(values, weights, true));
+        }
+    }
+
+    #[test]
+    fn test_network_bytes_round_trip() {
+        let network = random_network(3, 100);
+        let bytes = network.to_bytes();
+        assert_eq!(bytes.len(), Network::BYTES);
+        let loaded = Network::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.to_bytes(), bytes);
+        assert!(matches!(
+            Network::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(NetworkError::InvalidLength { .. })
+        ));
+    }
+
+    #[test]
+    fn test_network_header_is_validated() {
+        let bytes = random_network(5, 100).to_bytes();
+        let corrupt = |offset: usize, value: u8| {
+            let mut corrupted = bytes.clone();
+            corrupted[offset] = value;
+            Network::from_bytes(&corrupted)
+        };
+        assert!(matches!(corrupt(0, b'X'), Err(NetworkError::InvalidMagic)));
+        assert!(matches!(corrupt(4, 2), Err(NetworkError::UnsupportedVersion(2))));
+        assert!(matches!(corrupt(8, !bytes[8]), Err(NetworkError::ArchitectureMismatch { .. })));
+        assert!(matches!(corrupt(12, 0), Err(NetworkError::QuantizationMismatch { .. })));
+        assert!(matches!(Network::from_bytes(&bytes[..10]), Err(NetworkError::InvalidLength { .. })));
+        assert_ne!(ARCHITECTURE_HASH, architecture_hash(INPUT_SIZE, 512, "screlu"));
+    }
+}
@@ -0,0 +1,117 @@
+// Vector kernels used by the network. Every kernel has a plain scalar version
+// written so the compiler can auto-vectorize it, and an AVX2 version that is
+// picked at runtime when the CPU supports it.
+
+use super::network::QA;
+
+/// `out = prev + sum(adds) - sum(subs)`, element wise.
+pub fn update(out: &mut [i16], prev: &[i16], adds: &[&[i16]], subs: &[&[i16]]) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU supports AVX2.
+            unsafe { avx2::update(out, prev, adds, subs) };
+            return;
+        }
+    }
+    scalar::update(out, prev, adds, subs);
+}
+
+/// `sum(clamp(acc, 0, QA)^2 * weights)`.
+///
+/// With `fast` set the AVX2 kernel multiplies in 16 bit, which is only exact
+/// when every weight lies in `-128..=127`.
+pub fn screlu_dot(acc: &[i16], weights: &[i16], fast: bool) -> i32 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if fast && is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU supports AVX2.
+            return unsafe { avx2::screlu_dot(acc, weights) };
+        }
+    }
+    let _ = fast;
+    scalar::screlu_dot(acc, weights)
+}
+
+pub mod scalar {
+    use super::QA;
+
+    pub fn update(out: &mut [i16], prev: &[i16], adds: &[&[i16]], subs: &[&[i16]]) {
+        out.copy_from_slice(prev);
+        for add in adds {
+            for (o, w) in out.iter_mut().zip(add.iter()) {
+                *o = o.wrapping_add(*w);
+            }
+        }
+        for sub in subs {
+            for (o, w) in out.iter_mut().zip(sub.iter()) {
+                *o = o.wrapping_sub(*w);
+            }
+        }
+    }
+
+    pub fn screlu_dot(acc: &[i16], weights: &[i16]) -> i32 {
+        acc.iter()
+            .zip(weights.iter())
+            .map(|(&x, &w)| {
+                let x = (x as i32).clamp(0, QA);
+                x * x * w as i32
+            })
+            .sum()
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+pub mod avx2 {
+    use super::QA;
+    use std::arch::x86_64::*;
+
+    const LANES: usize = 16;
+
+    /// # Safety
+    /// The CPU must support AVX2 and all slices must have the length of `out`,
+    /// which has to be a multiple of 16.
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn update(out: &mut [i16], prev: &[i16], adds: &[&[i16]], subs: &[&[i16]]) {
+        debug_assert!(out.len().is_multiple_of(LANES) && prev.len() == out.len());
+        unsafe {
+            for i in (0..out.len()).step_by(LANES) {
+                let mut v = _mm256_loadu_si256(prev.as_ptr().add(i) as *const __m256i);
+                for add in adds {
+                    v = _mm256_add_epi16(v, _mm256_loadu_si256(add.as_ptr().add(i) as *const __m256i));
+                }
+                for sub in subs {
+                    v = _mm256_sub_epi16(v, _mm256_loadu_si256(sub.as_ptr().add(i) as *const __m256i));
+                }
+                _mm256_storeu_si256(out.as_mut_ptr().add(i) as *mut __m256i, v);
+            }
+        }
+    }
+
+    /// # Safety
+    /// The CPU must support AVX2, both slices must have the same length which
+    /// has to be a multiple of 16, and every weight must lie in `-128..=127`.
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn screlu_dot(acc: &[i16], weights: &[i16]) -> i32 {
+        debug_assert!(acc.len().is_multiple_of(LANES) && weights.len() == acc.len());
+        unsafe {
+            let zero = _mm256_setzero_si256();
+            let qa = _mm256_set1_epi16(QA as i16);
+            let mut sum = _mm256_setzero_si256();
+            for i in (0..acc.len()).step_by(LANES) {
+                let v = _mm256_loadu_si256(acc.as_ptr().add(i) as *const __m256i);
+                let v = _mm256_min_epi16(_mm256_max_epi16(v, zero), qa);
+                let w = _mm256_loadu_si256(weights.as_ptr().add(i) as *const __m256i);
+                // v * w fits into 16 bits for the weight range above, the second
+                // multiplication widens to 32 bits and adds neighbouring pairs.
+                let product = _mm256_mullo_epi16(v, w);
+                sum = _mm256_add_epi32(sum, _mm256_madd_epi16(product, v));
+            }
+            let high = _mm256_extracti128_si256(sum, 1);
+            let sum = _mm_add_epi32(_mm256_castsi256_si128(sum), high);
+            let sum = _mm_add_epi32(sum, _mm_shuffle_epi32(sum, 0b01_00_11_10));
+            let sum = _mm_add_epi32(sum, _mm_shuffle_epi32(sum, 0b10_11_00_01));
+            _mm_cvtsi128_si32(sum)
+        }
+    }
+}